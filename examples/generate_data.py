@@ -5,4 +5,14 @@ import numpy as np
 def generate_normal(mean: float, std_dev: float, num: int, seed: Optional[int] = None):
     """ """
     rng = np.random.default_rng(seed=seed)
-    return rng.normal(mean, std_dev, num)
\ No newline at end of file
+    return rng.normal(mean, std_dev, num)
+
+def generate_labeled_stream(segments, seed: Optional[int] = None):
+    """ Return samples and ground truth change points of piecewise segments.
+
+    :param segments: Sequence of change_point_algorithms.Segment generated back to back.
+    :param seed: Seed for the random number generator.
+    """
+    from change_point_algorithms._change_point_algorithms import generate_stream
+    data, changepoints = generate_stream(segments, seed=seed)
+    return np.array(data), changepoints
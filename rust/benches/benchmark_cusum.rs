@@ -1,5 +1,6 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use _change_point_algorithms::cusum::{CusumV0, CusumV1};
+use _change_point_algorithms::synth::{generate_stream, Segment};
 use rand_distr::Distribution;
 use std::hint::black_box;
 use helpers::generate_normal_data;
@@ -43,5 +44,25 @@ pub fn cusumv1_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, cusumv0_benchmark, cusumv1_benchmark);
+pub fn cusumv0_shifts_benchmark(c: &mut Criterion) {
+    let (mean, std_dev, alpha, threshold) = get_params();
+    let segments = vec![
+        Segment::Normal { length: 100_000, mean, std_dev },
+        Segment::Normal { length: 100_000, mean: mean + 3.0 * std_dev, std_dev },
+        Segment::Drift { length: 100_000, start: mean, end: mean - 3.0 * std_dev, std_dev },
+        Segment::Ar { length: 100_000, mean, coef: 0.5, std_dev },
+    ];
+    let (unknowns, _changepoints) = generate_stream(segments, Some(0)).unwrap();
+    c.bench_function("Cusum v0 shifts", |b| {
+        b.iter(|| {
+            let mut model = CusumV0::new(mean, std_dev.powi(2), alpha, threshold);
+            for &point in black_box(&unknowns) {
+                model.update(black_box(point));
+                let _prediction = black_box(model.predict(point));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, cusumv0_benchmark, cusumv1_benchmark, cusumv0_shifts_benchmark);
 criterion_main!(benches);
\ No newline at end of file
@@ -0,0 +1,462 @@
+//! Run a change point detector over a column of numbers and write per-sample scores.
+//!
+//! Input is read from a file or stdin, one sample per line. Detector parameters come from
+//! a TOML config file and can be overridden with `--<parameter> <value>` flags.
+//!
+//! Needs the `cli` feature, e.g. `cargo run --features cli --bin changepoint -- data.csv`.
+use _change_point_algorithms::bocpd::bocpd_model::BocpdModel;
+use _change_point_algorithms::cusum::{CusumV0, CusumV1};
+use _change_point_algorithms::expect_max::em_model::EmModel;
+use _change_point_algorithms::expect_max::em_model_builder::{generate_samples, EmBuilder};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::iter::once;
+use std::process::ExitCode;
+use toml::{Table, Value};
+
+const USAGE: &str = "\
+Usage: changepoint [OPTIONS] [INPUT]
+
+Run a change point detector over a numeric column and write per-sample scores.
+Reads from INPUT, or stdin when INPUT is missing or '-'.
+
+Options:
+  --detector <NAME>     bocpd, cusum-v0, cusum-v1 or em
+  --config <FILE>       TOML file with detector parameters
+  --column <N>          Zero-based column to read [default: 0]
+  --delimiter <CHAR>    Column delimiter [default: comma if present, else whitespace]
+  --output <FILE>       Write CSV to FILE instead of stdout
+  --alarms-only         Only write the indices of samples that raised an alarm
+  --<PARAMETER> <VALUE> Override a parameter of the selected detector, e.g. --lamb 50
+  -h, --help            Print this message
+";
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+enum DetectorKind {
+    Bocpd,
+    CusumV0,
+    CusumV1,
+    Em,
+}
+
+impl DetectorKind {
+    /// Name of the config table holding this detector's parameters.
+    fn section(&self) -> &'static str {
+        match self {
+            DetectorKind::Bocpd => "bocpd",
+            DetectorKind::CusumV0 | DetectorKind::CusumV1 => "cusum",
+            DetectorKind::Em => "em",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    detector: Option<DetectorKind>,
+    column: usize,
+    delimiter: Option<char>,
+    bocpd: BocpdConfig,
+    cusum: CusumConfig,
+    em: EmConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BocpdConfig {
+    alpha: f64,
+    beta: f64,
+    mu: f64,
+    kappa: f64,
+    lamb: f64,
+    with_cache: bool,
+    truncation: Option<f64>,
+    /// Alarm when predicted probability is at or below this value.
+    threshold: f64,
+}
+
+impl Default for BocpdConfig {
+    fn default() -> Self {
+        Self {
+            alpha: 1.0,
+            beta: 1.0,
+            mu: 0.0,
+            kappa: 1.0,
+            lamb: 100.0,
+            with_cache: true,
+            truncation: None,
+            threshold: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CusumConfig {
+    mean: f64,
+    std_dev: f64,
+    alpha: f64,
+    /// Alarm when the cumulative sum exceeds h standard deviations.
+    h: f64,
+}
+
+impl Default for CusumConfig {
+    fn default() -> Self {
+        Self {
+            mean: 0.0,
+            std_dev: 1.0,
+            alpha: 0.95,
+            h: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct EmConfig {
+    normal: (f64, f64, f64),
+    abnormals: Vec<(f64, f64, f64)>,
+    arr_sizes: Vec<u32>,
+    epochs: u32,
+    /// Seed for drawing training samples, so runs are reproducible.
+    seed: Option<u64>,
+    /// Alarm when the probability of the normal component is below this value.
+    threshold: f64,
+}
+
+impl Default for EmConfig {
+    fn default() -> Self {
+        Self {
+            normal: (0.0, 1.0, 0.9),
+            abnormals: vec![(10.0, 1.0, 0.1)],
+            arr_sizes: vec![90, 10],
+            epochs: 10,
+            seed: None,
+            threshold: 0.5,
+        }
+    }
+}
+
+struct Args {
+    input: Option<String>,
+    output: Option<String>,
+    config: Option<String>,
+    alarms_only: bool,
+    // (key, raw value) pairs applied on top of the config file
+    overrides: Vec<(String, String)>,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        input: None,
+        output: None,
+        config: None,
+        alarms_only: false,
+        overrides: Vec::new(),
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--alarms-only" => parsed.alarms_only = true,
+            "-" => parsed.input = None,
+            flag if flag.starts_with("--") => {
+                let (key, value) = match flag[2..].split_once('=') {
+                    Some((key, value)) => (key.to_string(), value.to_string()),
+                    None => {
+                        let value = args
+                            .next()
+                            .ok_or_else(|| format!("missing value for {}", flag))?;
+                        (flag[2..].to_string(), value)
+                    }
+                };
+                match key.as_str() {
+                    "output" => parsed.output = Some(value),
+                    "config" => parsed.config = Some(value),
+                    _ => parsed.overrides.push((key.replace('-', "_"), value)),
+                }
+            }
+            _ if parsed.input.is_none() => parsed.input = Some(arg),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+/// Parse a flag value as a TOML value, falling back to a plain string.
+fn parse_value(raw: &str) -> Value {
+    format!("value = {}", raw)
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+/// Merge config file and command line overrides into a single config.
+fn load_config(path: Option<&str>, overrides: &[(String, String)]) -> Result<Config, String> {
+    let mut table = match path {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {}", path, err))?
+            .parse::<Table>()
+            .map_err(|err| format!("invalid config {}: {}", path, err))?,
+        None => Table::new(),
+    };
+    for (key, raw) in overrides {
+        if matches!(key.as_str(), "detector" | "column" | "delimiter") {
+            table.insert(key.clone(), parse_value(raw));
+        }
+    }
+    let detector = match table.get("detector") {
+        Some(value) => Some(
+            value
+                .clone()
+                .try_into::<DetectorKind>()
+                .map_err(|err| format!("invalid detector: {}", err))?,
+        ),
+        None => None,
+    };
+    let params = overrides
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "detector" | "column" | "delimiter"));
+    for (key, raw) in params {
+        let Some(kind) = detector else {
+            return Err(format!("--{} given without a detector", key.replace('_', "-")));
+        };
+        let section = table
+            .entry(kind.section())
+            .or_insert_with(|| Value::Table(Table::new()));
+        let Value::Table(section) = section else {
+            return Err(format!("config entry {} must be a table", kind.section()));
+        };
+        section.insert(key.clone(), parse_value(raw));
+    }
+    table
+        .try_into::<Config>()
+        .map_err(|err| format!("invalid parameters: {}", err))
+}
+
+enum Detector {
+    Bocpd { model: BocpdModel, lamb: f64, threshold: f64 },
+    CusumV0 { model: CusumV0, threshold: f64 },
+    CusumV1 { model: CusumV1, threshold: f64 },
+    Em { model: Box<EmModel>, threshold: f64 },
+}
+
+impl Detector {
+    fn from_config(config: &Config) -> Result<Self, String> {
+        let kind = config.detector.ok_or("no detector selected")?;
+        let detector = match kind {
+            DetectorKind::Bocpd => {
+                let BocpdConfig { alpha, beta, mu, kappa, lamb, with_cache, truncation, threshold } =
+                    config.bocpd;
+                let model = BocpdModel::new_py(alpha, beta, mu, kappa, with_cache, truncation)
+                    .map_err(|_| "could not construct BOCPD model")?;
+                Detector::Bocpd { model, lamb, threshold }
+            }
+            DetectorKind::CusumV0 => {
+                let CusumConfig { mean, std_dev, alpha, h } = config.cusum;
+                // the model resets where the alarm is raised
+                let threshold = h * std_dev;
+                let model = CusumV0::new(mean, std_dev.powi(2), alpha, threshold);
+                Detector::CusumV0 { model, threshold }
+            }
+            DetectorKind::CusumV1 => {
+                let CusumConfig { mean, std_dev, alpha, h } = config.cusum;
+                let model = CusumV1::new(mean, std_dev, alpha, h);
+                Detector::CusumV1 { model, threshold: h * std_dev }
+            }
+            DetectorKind::Em => Detector::Em {
+                model: Box::new(build_em(&config.em)?),
+                threshold: config.em.threshold,
+            },
+        };
+        Ok(detector)
+    }
+
+    /// Feed a sample to the detector and return its score and whether it raised an alarm.
+    fn step(&mut self, point: f64) -> Result<(f64, bool), String> {
+        match self {
+            Detector::Bocpd { model, lamb, threshold } => {
+                model.update(point, *lamb).map_err(|_| "BOCPD update failed")?;
+                let score = model.predict(point);
+                Ok((score, score <= *threshold))
+            }
+            Detector::CusumV0 { model, threshold } => {
+                model.update(point);
+                let score = model.predict(point);
+                Ok((score, score > *threshold))
+            }
+            Detector::CusumV1 { model, threshold } => {
+                model.update(point);
+                let score = model.predict(point);
+                Ok((score, score > *threshold))
+            }
+            Detector::Em { model, threshold } => {
+                model.update_without_diagnostics(point).map_err(|err| err.to_string())?;
+                let score = model.predict(point);
+                Ok((score, score < *threshold))
+            }
+        }
+    }
+}
+
+fn build_em(config: &EmConfig) -> Result<EmModel, String> {
+    let (mean, stddev, prob) = config.normal;
+    let params: Vec<(f64, f64, f64)> =
+        once(config.normal).chain(config.abnormals.iter().copied()).collect();
+    let samples = generate_samples(&params, &config.arr_sizes, config.seed).map_err(|err| err.to_string())?;
+    let model = EmBuilder::new()
+        .build_normal(mean, stddev, prob)
+        .map_err(|err| format!("invalid normal component: {:?}", err))?
+        .build_abnormal_from_tuples(&config.abnormals)
+        .map_err(|err| format!("invalid abnormal component: {:?}", err))?
+        .build_epochs(config.epochs)
+        .map_err(|err| format!("invalid epochs: {:?}", err))?
+        .build_samples_from_slice(&samples)
+        .build_likelihoods()
+        .get_model();
+    Ok(model)
+}
+
+/// Read the selected column of every line, skipping blank lines, comments and a header line.
+fn read_column(
+    reader: impl BufRead,
+    column: usize,
+    delimiter: Option<char>,
+) -> Result<Vec<f64>, String> {
+    let mut values = Vec::new();
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| format!("could not read input: {}", err))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let field = match delimiter {
+            Some(delimiter) => line.split(delimiter).nth(column),
+            None if line.contains(',') => line.split(',').nth(column),
+            None => line.split_whitespace().nth(column),
+        }
+        .map(str::trim)
+        .ok_or_else(|| format!("line {} has no column {}", line_number + 1, column))?;
+        match field.parse::<f64>() {
+            Ok(value) => values.push(value),
+            Err(_) if values.is_empty() && line_number == 0 => continue,
+            Err(_) => {
+                return Err(format!("line {}: '{}' is not a number", line_number + 1, field));
+            }
+        }
+    }
+    Ok(values)
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let config = load_config(args.config.as_deref(), &args.overrides)?;
+    let mut detector = Detector::from_config(&config)?;
+    let values = match args.input.as_deref() {
+        Some(path) => {
+            let file = File::open(path).map_err(|err| format!("could not open {}: {}", path, err))?;
+            read_column(BufReader::new(file), config.column, config.delimiter)?
+        }
+        None => read_column(io::stdin().lock(), config.column, config.delimiter)?,
+    };
+    let out: Box<dyn Write> = match args.output.as_deref() {
+        Some(path) => Box::new(
+            File::create(path).map_err(|err| format!("could not create {}: {}", path, err))?,
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut out = BufWriter::new(out);
+    let write_err = |err: io::Error| format!("could not write output: {}", err);
+    if !args.alarms_only {
+        writeln!(out, "index,value,score,alarm").map_err(write_err)?;
+    }
+    for (idx, &value) in values.iter().enumerate() {
+        let (score, alarm) = detector.step(value)?;
+        if args.alarms_only {
+            if alarm {
+                writeln!(out, "{}", idx).map_err(write_err)?;
+            }
+        } else {
+            writeln!(out, "{},{},{},{}", idx, value, score, alarm as u8).map_err(write_err)?;
+        }
+    }
+    out.flush().map_err(write_err)
+}
+
+fn main() -> ExitCode {
+    match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => match run(args) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                ExitCode::FAILURE
+            }
+        },
+        Ok(None) => {
+            print!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse_args(to_args(&["--detector", "bocpd", "--lamb=50", "data.csv"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.input.as_deref(), Some("data.csv"));
+        assert_eq!(args.overrides.len(), 2);
+        assert!(parse_args(to_args(&["--help"])).unwrap().is_none());
+        assert!(parse_args(to_args(&["--lamb"])).is_err());
+    }
+
+    #[test]
+    fn test_load_config_overrides() {
+        let overrides = vec![
+            (String::from("detector"), String::from("cusum-v1")),
+            (String::from("std_dev"), String::from("2")),
+            (String::from("h"), String::from("4.5")),
+        ];
+        let config = load_config(None, &overrides).unwrap();
+        assert_eq!(config.detector, Some(DetectorKind::CusumV1));
+        assert_eq!(config.cusum.std_dev, 2.0);
+        assert_eq!(config.cusum.h, 4.5);
+        assert_eq!(config.cusum.alpha, CusumConfig::default().alpha);
+    }
+
+    #[test]
+    fn test_load_config_rejects_unknown_parameter() {
+        let overrides = vec![
+            (String::from("detector"), String::from("bocpd")),
+            (String::from("lambda"), String::from("2")),
+        ];
+        assert!(load_config(None, &overrides).is_err());
+        let overrides = vec![(String::from("lamb"), String::from("2"))];
+        assert!(load_config(None, &overrides).is_err());
+    }
+
+    #[test]
+    fn test_read_column() {
+        let input = "time,value\n0.0,1.5\n\n# comment\n0.1,-2\n";
+        let values = read_column(input.as_bytes(), 1, None).unwrap();
+        assert_eq!(values, vec![1.5, -2.0]);
+        let values = read_column("1 2\n3 4\n".as_bytes(), 0, None).unwrap();
+        assert_eq!(values, vec![1.0, 3.0]);
+        assert!(read_column("1\nabc\n".as_bytes(), 0, None).is_err());
+        assert!(read_column("1;2\n".as_bytes(), 2, Some(';')).is_err());
+    }
+}
@@ -3,7 +3,7 @@ pub mod bocpd_model;
 pub mod dist_params;
 mod element;
 pub mod sparse_probs;
-mod normal_inverse_gamma;
+pub mod normal_inverse_gamma;
 
 use statrs::function::beta::beta;
 use std::collections::{HashMap, VecDeque};
@@ -1,3 +1,8 @@
+/// Normal-Inverse-Gamma distribution over the mean and variance of a normal distribution.
+///
+/// The variance follows InverseGamma(alpha, beta) and, given the variance, the mean follows
+/// Normal(mu, variance / kappa).
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct NormalInverseGamma {
     pub alpha: f64,
     pub beta: f64,
@@ -14,4 +19,4 @@ impl Default for NormalInverseGamma {
             kappa: 1.0,
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,141 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, pymethods, PyResult};
+
+/// A class implementing an exponentially weighted moving average control chart.
+///
+/// Control limits use the exact variance of the smoothed statistic,
+/// so they start narrow and widen towards the asymptotic limits.
+#[pyclass]
+pub struct EwmaChart {
+    mean: f64,
+    lamb: f64,
+    l_sigma: f64,
+    smoothed: f64,
+    // variance of the smoothed statistic once the chart has run indefinitely
+    asymptotic_variance: f64,
+    // (1 - lamb)^(2t) for t samples since the last restart
+    decay: f64,
+    count: usize,
+}
+
+#[pymethods]
+impl EwmaChart {
+    #[new]
+    pub fn new(mean: f64, std_dev: f64, lamb: f64, l_sigma: f64) -> PyResult<Self> {
+        if !(mean.is_finite() && std_dev.is_finite() && std_dev > 0.0) {
+            return Err(PyValueError::new_err("mean must be finite and standard deviation positive"));
+        }
+        if !(lamb > 0.0 && lamb <= 1.0) {
+            return Err(PyValueError::new_err("lamb must be in the interval (0, 1]"));
+        }
+        if !(l_sigma.is_finite() && l_sigma > 0.0) {
+            return Err(PyValueError::new_err("l_sigma must be positive and finite"));
+        }
+        let asymptotic_variance = std_dev.powi(2) * lamb / (2.0 - lamb);
+        Ok(Self {
+            mean,
+            lamb,
+            l_sigma,
+            smoothed: mean,
+            asymptotic_variance,
+            decay: 1.0,
+            count: 0,
+        })
+    }
+
+    /// Update smoothed statistic using given input value.
+    pub fn update(&mut self, point: f64) {
+        self.smoothed = self.lamb * point + (1.0 - self.lamb) * self.smoothed;
+        self.decay *= (1.0 - self.lamb).powi(2);
+        self.count += 1;
+    }
+
+    /// Return distance of smoothed statistic from mean in units of its standard deviation.
+    ///
+    /// The chart restarts when the distance exceeds l_sigma.
+    pub fn predict(&mut self, _point: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let out = (self.smoothed - self.mean).abs() / self.smoothed_std_dev();
+        if out > self.l_sigma {
+            self.reset();
+        }
+        out
+    }
+
+    pub fn smoothed(&self) -> f64 {
+        self.smoothed
+    }
+
+    /// Return standard deviation of the smoothed statistic at the current sample.
+    pub fn smoothed_std_dev(&self) -> f64 {
+        (self.asymptotic_variance * (1.0 - self.decay)).sqrt()
+    }
+
+    pub fn upper_limit(&self) -> f64 {
+        self.mean + self.l_sigma * self.smoothed_std_dev()
+    }
+
+    pub fn lower_limit(&self) -> f64 {
+        self.mean - self.l_sigma * self.smoothed_std_dev()
+    }
+
+    fn reset(&mut self) {
+        self.smoothed = self.mean;
+        self.decay = 1.0;
+        self.count = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floats_close(a: f64, b: f64, epsilon: f64) -> bool {
+        (a - b).abs() < epsilon
+    }
+
+    #[test]
+    fn test_ewma_chart_bad_params() {
+        assert!(EwmaChart::new(0.0, 0.0, 0.2, 3.0).is_err());
+        assert!(EwmaChart::new(0.0, 1.0, 0.0, 3.0).is_err());
+        assert!(EwmaChart::new(0.0, 1.0, 1.5, 3.0).is_err());
+        assert!(EwmaChart::new(0.0, 1.0, 0.2, -3.0).is_err());
+    }
+
+    #[test]
+    fn test_ewma_chart_exact_variance() {
+        let lamb: f64 = 0.2;
+        let mut model = EwmaChart::new(0.0, 2.0, lamb, 3.0).unwrap();
+        model.update(0.0);
+        // first smoothed value is lamb * x, so its variance is (lamb * sigma)^2
+        assert!(floats_close(model.smoothed_std_dev(), lamb * 2.0, 1e-12));
+        for _ in 0..500 {
+            model.update(0.0);
+        }
+        let asymptotic = 2.0 * (lamb / (2.0 - lamb)).sqrt();
+        assert!(floats_close(model.smoothed_std_dev(), asymptotic, 1e-12));
+        assert!(floats_close(model.upper_limit(), 3.0 * asymptotic, 1e-12));
+        assert!(floats_close(model.lower_limit(), -3.0 * asymptotic, 1e-12));
+    }
+
+    #[test]
+    fn test_ewma_chart_smoothing() {
+        let mut model = EwmaChart::new(1.0, 1.0, 0.5, 3.0).unwrap();
+        model.update(3.0);
+        assert!(floats_close(model.smoothed(), 2.0, 1e-12));
+        model.update(0.0);
+        assert!(floats_close(model.smoothed(), 1.0, 1e-12));
+    }
+
+    #[test]
+    fn test_ewma_chart_resets_after_alarm() {
+        let mut model = EwmaChart::new(0.0, 1.0, 0.2, 3.0).unwrap();
+        assert_eq!(model.predict(0.0), 0.0);
+        model.update(10.0);
+        assert!(model.predict(10.0) > 3.0);
+        assert_eq!(model.smoothed(), 0.0);
+        assert_eq!(model.predict(0.0), 0.0);
+    }
+}
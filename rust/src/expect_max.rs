@@ -1,8 +1,21 @@
+pub mod component;
+pub mod diagnostics;
 pub mod em_early_stop_model;
 pub mod em_model;
 pub mod em_model_builder;
+pub mod hmm;
+pub mod hmm_builder;
+pub mod initialization;
+pub mod model_selection;
+pub mod multivariate_em_builder;
+pub mod multivariate_em_model;
+pub mod multivariate_normal;
+pub mod online_em_model;
+pub mod priors;
+pub mod py_em_builder;
+pub mod retention;
 
-mod normal;
+pub(crate) mod normal;
 mod normal_params;
 mod pos_int;
 mod probability;
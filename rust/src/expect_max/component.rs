@@ -0,0 +1,584 @@
+//! Component distributions mixed by the Expectation Maximization engine.
+//!
+//! Every component carries its own mixture weight. The maximization step fits the
+//! shape of each component from the samples weighted by their responsibilities.
+use super::normal_params::NormalParams;
+use super::probability::{Probability, ProbabilityError};
+use ndarray::ArrayView1;
+use pyo3::exceptions::PyValueError;
+use pyo3::PyErr;
+use statrs::function::gamma::ln_gamma;
+use std::f64::consts::PI;
+use std::fmt;
+use std::iter::zip;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ComponentError {
+    BadParameter(&'static str, f64),
+    BadWeight(f64),
+}
+
+impl fmt::Display for ComponentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ComponentError::BadParameter(name, value) => write!(f, "Bad component parameter {}: {}", name, value),
+            ComponentError::BadWeight(value) => {
+                write!(f, "Component weight must be between 0 and 1, got {}.", value)
+            }
+        }
+    }
+}
+
+impl From<ComponentError> for PyErr {
+    fn from(err: ComponentError) -> PyErr {
+        PyValueError::new_err(format!("{}", err))
+    }
+}
+
+/// Outcome of fitting a component to weighted samples.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Fit {
+    Updated,
+    /// The squared scale fell below the variance floor and was raised to it.
+    Floored { variance: f64 },
+    /// The samples do not determine the component, so it was left unchanged.
+    Degenerate,
+}
+
+/// Distribution of a single mixture component.
+pub trait MixtureComponent {
+    /// Mixture weight of the component.
+    fn weight(&self) -> f64;
+
+    fn set_weight(&mut self, weight: f64) -> Result<(), ProbabilityError>;
+
+    /// Natural log of the unweighted density at point.
+    fn log_density(&self, point: f64) -> f64;
+
+    /// Natural log of the weighted density at point.
+    fn log_likelihood(&self, point: f64) -> f64 {
+        self.weight().ln() + self.log_density(point)
+    }
+
+    /// Weighted density at point.
+    fn likelihood(&self, point: f64) -> f64 {
+        self.weight() * self.log_density(point).exp()
+    }
+
+    fn mean(&self) -> f64;
+
+    fn stddev(&self) -> f64;
+
+    /// Return (location, scale, weight) of the component.
+    fn parameters(&self) -> (f64, f64, f64);
+
+    /// Weighted maximum likelihood update of location and scale.
+    ///
+    /// Squared scales below variance_floor are raised to it.
+    fn fit(&mut self, samples: ArrayView1<f64>, responsibilities: ArrayView1<f64>, variance_floor: f64) -> Fit;
+}
+
+/// Return total weight, weighted mean and weighted variance of the points.
+pub(super) fn weighted_moments<I>(pairs: I) -> (f64, f64, f64)
+where
+    I: Iterator<Item = (f64, f64)> + Clone,
+{
+    let (mass, sum) = pairs
+        .clone()
+        .fold((0.0, 0.0), |(mass, sum), (point, weight)| (mass + weight, sum + weight * point));
+    let mean = sum / mass;
+    let scatter: f64 = pairs.map(|(point, weight)| weight * (point - mean).powi(2)).sum();
+    (mass, mean, scatter / mass)
+}
+
+/// Return the squared scale to use and the fit outcome, or None if it is degenerate.
+pub(super) fn floor_variance(variance: f64, floor: f64) -> Option<(f64, Fit)> {
+    if !(variance.is_finite() && (variance > 0.0 || floor > 0.0)) {
+        None
+    } else if variance < floor {
+        Some((floor, Fit::Floored { variance }))
+    } else {
+        Some((variance, Fit::Updated))
+    }
+}
+
+fn check_finite(name: &'static str, value: f64) -> Result<(), ComponentError> {
+    if value.is_finite() { Ok(()) } else { Err(ComponentError::BadParameter(name, value)) }
+}
+
+fn check_positive(name: &'static str, value: f64) -> Result<(), ComponentError> {
+    if value.is_finite() && value > 0.0 { Ok(()) } else { Err(ComponentError::BadParameter(name, value)) }
+}
+
+fn probability(weight: f64) -> Result<Probability, ComponentError> {
+    Probability::new(weight).map_err(|_| ComponentError::BadWeight(weight))
+}
+
+/// Student-t distribution with a fixed number of degrees of freedom.
+///
+/// Heavy tails keep outliers from dragging the location and inflating the scale.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StudentT {
+    location: f64,
+    scale: f64,
+    dof: f64,
+    prob: Probability,
+}
+
+impl StudentT {
+    pub fn new(location: f64, scale: f64, dof: f64, weight: f64) -> Result<Self, ComponentError> {
+        check_finite("location", location)?;
+        check_positive("scale", scale)?;
+        check_positive("dof", dof)?;
+        Ok(Self { location, scale, dof, prob: probability(weight)? })
+    }
+
+    pub fn dof(&self) -> f64 {
+        self.dof
+    }
+}
+
+impl MixtureComponent for StudentT {
+    fn weight(&self) -> f64 {
+        self.prob.value()
+    }
+
+    fn set_weight(&mut self, weight: f64) -> Result<(), ProbabilityError> {
+        self.prob.probability(weight).map(|_| ())
+    }
+
+    fn log_density(&self, point: f64) -> f64 {
+        let nu = self.dof;
+        let z = (point - self.location) / self.scale;
+        ln_gamma((nu + 1.0) / 2.0) - ln_gamma(nu / 2.0) - 0.5 * (nu * PI).ln() - self.scale.ln()
+            - (nu + 1.0) / 2.0 * (z * z / nu).ln_1p()
+    }
+
+    fn mean(&self) -> f64 {
+        if self.dof > 1.0 { self.location } else { f64::NAN }
+    }
+
+    fn stddev(&self) -> f64 {
+        if self.dof > 2.0 { self.scale * (self.dof / (self.dof - 2.0)).sqrt() } else { f64::INFINITY }
+    }
+
+    fn parameters(&self) -> (f64, f64, f64) {
+        (self.location, self.scale, self.weight())
+    }
+
+    /// One expectation conditional maximization step: samples are reweighted by their
+    /// expected latent precision under the current parameters.
+    fn fit(&mut self, samples: ArrayView1<f64>, responsibilities: ArrayView1<f64>, variance_floor: f64) -> Fit {
+        let nu = self.dof;
+        let precision = |point: f64| {
+            let z = (point - self.location) / self.scale;
+            (nu + 1.0) / (nu + z * z)
+        };
+        let mass: f64 = responsibilities.sum();
+        let pairs = zip(samples.iter().copied(), responsibilities.iter().copied())
+            .map(|(point, weight)| (point, weight * precision(point)));
+        let (scaled_mass, location, variance) = weighted_moments(pairs);
+        if !(mass.is_normal() && scaled_mass.is_normal() && location.is_finite()) {
+            return Fit::Degenerate;
+        }
+        // the scatter is normalized by the responsibilities, not the reweighted mass
+        let Some((variance, fit)) = floor_variance(variance * scaled_mass / mass, variance_floor) else {
+            return Fit::Degenerate;
+        };
+        self.location = location;
+        self.scale = variance.sqrt();
+        fit
+    }
+}
+
+/// Log-normal distribution of positive values, parameterized on the log scale.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LogNormal {
+    mu: f64,
+    sigma: f64,
+    prob: Probability,
+}
+
+impl LogNormal {
+    pub fn new(mu: f64, sigma: f64, weight: f64) -> Result<Self, ComponentError> {
+        check_finite("mu", mu)?;
+        check_positive("sigma", sigma)?;
+        Ok(Self { mu, sigma, prob: probability(weight)? })
+    }
+}
+
+impl MixtureComponent for LogNormal {
+    fn weight(&self) -> f64 {
+        self.prob.value()
+    }
+
+    fn set_weight(&mut self, weight: f64) -> Result<(), ProbabilityError> {
+        self.prob.probability(weight).map(|_| ())
+    }
+
+    fn log_density(&self, point: f64) -> f64 {
+        if point <= 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        let log_point = point.ln();
+        let z = (log_point - self.mu) / self.sigma;
+        -log_point - self.sigma.ln() - 0.5 * (2.0 * PI).ln() - 0.5 * z * z
+    }
+
+    fn mean(&self) -> f64 {
+        (self.mu + self.sigma.powi(2) / 2.0).exp()
+    }
+
+    fn stddev(&self) -> f64 {
+        self.sigma.powi(2).exp_m1().sqrt() * self.mean()
+    }
+
+    fn parameters(&self) -> (f64, f64, f64) {
+        (self.mu, self.sigma, self.weight())
+    }
+
+    /// Fit mu and sigma to the logs of the positive samples.
+    fn fit(&mut self, samples: ArrayView1<f64>, responsibilities: ArrayView1<f64>, variance_floor: f64) -> Fit {
+        let pairs = zip(samples.iter().copied(), responsibilities.iter().copied())
+            .filter(|&(point, _)| point > 0.0)
+            .map(|(point, weight)| (point.ln(), weight));
+        let (mass, mu, variance) = weighted_moments(pairs);
+        if !(mass.is_normal() && mu.is_finite()) {
+            return Fit::Degenerate;
+        }
+        let Some((variance, fit)) = floor_variance(variance, variance_floor) else {
+            return Fit::Degenerate;
+        };
+        self.mu = mu;
+        self.sigma = variance.sqrt();
+        fit
+    }
+}
+
+/// Exponential distribution of non-negative values. Its scale is the inverse of the rate.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Exponential {
+    rate: f64,
+    prob: Probability,
+}
+
+impl Exponential {
+    pub fn new(rate: f64, weight: f64) -> Result<Self, ComponentError> {
+        check_positive("rate", rate)?;
+        Ok(Self { rate, prob: probability(weight)? })
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+impl MixtureComponent for Exponential {
+    fn weight(&self) -> f64 {
+        self.prob.value()
+    }
+
+    fn set_weight(&mut self, weight: f64) -> Result<(), ProbabilityError> {
+        self.prob.probability(weight).map(|_| ())
+    }
+
+    fn log_density(&self, point: f64) -> f64 {
+        if point < 0.0 { f64::NEG_INFINITY } else { self.rate.ln() - self.rate * point }
+    }
+
+    fn mean(&self) -> f64 {
+        self.rate.recip()
+    }
+
+    fn stddev(&self) -> f64 {
+        self.rate.recip()
+    }
+
+    fn parameters(&self) -> (f64, f64, f64) {
+        (0.0, self.rate.recip(), self.weight())
+    }
+
+    fn fit(&mut self, samples: ArrayView1<f64>, responsibilities: ArrayView1<f64>, variance_floor: f64) -> Fit {
+        let (mass, sum) = zip(samples, responsibilities)
+            .filter(|&(&point, _)| point >= 0.0)
+            .fold((0.0, 0.0), |(mass, sum), (&point, &weight)| (mass + weight, sum + weight * point));
+        let scale = sum / mass;
+        if !mass.is_normal() {
+            return Fit::Degenerate;
+        }
+        let Some((variance, fit)) = floor_variance(scale * scale, variance_floor) else {
+            return Fit::Degenerate;
+        };
+        self.rate = variance.sqrt().recip();
+        fit
+    }
+}
+
+/// Laplace distribution, whose location is the weighted median of its samples.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Laplace {
+    location: f64,
+    scale: f64,
+    prob: Probability,
+}
+
+impl Laplace {
+    pub fn new(location: f64, scale: f64, weight: f64) -> Result<Self, ComponentError> {
+        check_finite("location", location)?;
+        check_positive("scale", scale)?;
+        Ok(Self { location, scale, prob: probability(weight)? })
+    }
+}
+
+impl MixtureComponent for Laplace {
+    fn weight(&self) -> f64 {
+        self.prob.value()
+    }
+
+    fn set_weight(&mut self, weight: f64) -> Result<(), ProbabilityError> {
+        self.prob.probability(weight).map(|_| ())
+    }
+
+    fn log_density(&self, point: f64) -> f64 {
+        -(2.0 * self.scale).ln() - (point - self.location).abs() / self.scale
+    }
+
+    fn mean(&self) -> f64 {
+        self.location
+    }
+
+    fn stddev(&self) -> f64 {
+        self.scale * std::f64::consts::SQRT_2
+    }
+
+    fn parameters(&self) -> (f64, f64, f64) {
+        (self.location, self.scale, self.weight())
+    }
+
+    fn fit(&mut self, samples: ArrayView1<f64>, responsibilities: ArrayView1<f64>, variance_floor: f64) -> Fit {
+        let mut pairs: Vec<(f64, f64)> = zip(samples.iter().copied(), responsibilities.iter().copied())
+            .filter(|&(_, weight)| weight > 0.0)
+            .collect();
+        let mass: f64 = pairs.iter().map(|&(_, weight)| weight).sum();
+        if !mass.is_normal() || pairs.iter().any(|&(point, _)| !point.is_finite()) {
+            return Fit::Degenerate;
+        }
+        pairs.sort_by(|left, right| left.0.total_cmp(&right.0));
+        let mut cumulative = 0.0;
+        let location = pairs
+            .iter()
+            .find(|&&(_, weight)| {
+                cumulative += weight;
+                cumulative >= mass / 2.0
+            })
+            .map_or(self.location, |&(point, _)| point);
+        let deviation = pairs.iter().map(|&(point, weight)| weight * (point - location).abs()).sum::<f64>() / mass;
+        let Some((variance, fit)) = floor_variance(deviation * deviation, variance_floor) else {
+            return Fit::Degenerate;
+        };
+        self.location = location;
+        self.scale = variance.sqrt();
+        fit
+    }
+}
+
+/// Any component the Expectation Maximization engine can mix.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Component {
+    Normal(NormalParams),
+    StudentT(StudentT),
+    LogNormal(LogNormal),
+    Exponential(Exponential),
+    Laplace(Laplace),
+}
+
+impl From<NormalParams> for Component {
+    fn from(params: NormalParams) -> Self {
+        Component::Normal(params)
+    }
+}
+
+impl From<StudentT> for Component {
+    fn from(params: StudentT) -> Self {
+        Component::StudentT(params)
+    }
+}
+
+impl From<LogNormal> for Component {
+    fn from(params: LogNormal) -> Self {
+        Component::LogNormal(params)
+    }
+}
+
+impl From<Exponential> for Component {
+    fn from(params: Exponential) -> Self {
+        Component::Exponential(params)
+    }
+}
+
+impl From<Laplace> for Component {
+    fn from(params: Laplace) -> Self {
+        Component::Laplace(params)
+    }
+}
+
+impl Component {
+    pub fn as_normal(&self) -> Option<&NormalParams> {
+        match self {
+            Component::Normal(params) => Some(params),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! dispatch {
+    ($component:expr, $params:ident => $call:expr) => {
+        match $component {
+            Component::Normal($params) => $call,
+            Component::StudentT($params) => $call,
+            Component::LogNormal($params) => $call,
+            Component::Exponential($params) => $call,
+            Component::Laplace($params) => $call,
+        }
+    };
+}
+
+impl MixtureComponent for Component {
+    fn weight(&self) -> f64 {
+        dispatch!(self, params => MixtureComponent::weight(params))
+    }
+
+    fn set_weight(&mut self, weight: f64) -> Result<(), ProbabilityError> {
+        dispatch!(self, params => params.set_weight(weight))
+    }
+
+    fn log_density(&self, point: f64) -> f64 {
+        dispatch!(self, params => params.log_density(point))
+    }
+
+    fn log_likelihood(&self, point: f64) -> f64 {
+        dispatch!(self, params => MixtureComponent::log_likelihood(params, point))
+    }
+
+    fn likelihood(&self, point: f64) -> f64 {
+        dispatch!(self, params => MixtureComponent::likelihood(params, point))
+    }
+
+    fn mean(&self) -> f64 {
+        dispatch!(self, params => MixtureComponent::mean(params))
+    }
+
+    fn stddev(&self) -> f64 {
+        dispatch!(self, params => MixtureComponent::stddev(params))
+    }
+
+    fn parameters(&self) -> (f64, f64, f64) {
+        dispatch!(self, params => params.parameters())
+    }
+
+    fn fit(&mut self, samples: ArrayView1<f64>, responsibilities: ArrayView1<f64>, variance_floor: f64) -> Fit {
+        dispatch!(self, params => params.fit(samples, responsibilities, variance_floor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rand_distr::Distribution;
+
+    /// Integrate the density over [-limit, limit] with the trapezoid rule.
+    fn total_probability(component: &impl MixtureComponent, limit: f64) -> f64 {
+        let steps = 400_000;
+        let width = 2.0 * limit / steps as f64;
+        (0..=steps)
+            .map(|idx| {
+                let point = -limit + idx as f64 * width;
+                let scale = if idx == 0 || idx == steps { 0.5 } else { 1.0 };
+                scale * component.log_density(point).exp()
+            })
+            .sum::<f64>()
+            * width
+    }
+
+    fn fit_samples(component: &mut impl MixtureComponent, samples: Vec<f64>) -> Fit {
+        let responsibilities = Array1::ones(samples.len());
+        let samples = Array1::from(samples);
+        component.fit(samples.view(), responsibilities.view(), 0.0)
+    }
+
+    #[test]
+    fn test_densities_integrate_to_one() {
+        let components: Vec<Component> = vec![
+            NormalParams::from_tuple((1.0, 2.0, 0.5)).unwrap().into(),
+            StudentT::new(1.0, 2.0, 4.0, 0.5).unwrap().into(),
+            LogNormal::new(0.5, 0.5, 0.5).unwrap().into(),
+            Exponential::new(2.0, 0.5).unwrap().into(),
+            Laplace::new(-1.0, 0.5, 0.5).unwrap().into(),
+        ];
+        for component in components {
+            let total = total_probability(&component, 400.0);
+            // the trapezoid rule is off by half a step at the jump of the exponential
+            assert!((total - 1.0).abs() < 5e-3, "{:?}: {}", component, total);
+            assert!((component.likelihood(1.5) - 0.5 * component.log_density(1.5).exp()).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_fit_recovers_parameters() {
+        let mut rng = StdRng::seed_from_u64(21);
+        let mut student = StudentT::new(0.0, 1.0, 3.0, 1.0).unwrap();
+        let dist = rand_distr::StudentT::new(3.0).unwrap();
+        let samples: Vec<f64> = dist.sample_iter(&mut rng).take(20_000).map(|value| 5.0 + 2.0 * value).collect();
+        // each fit is a single step towards the estimate
+        for _ in 0..50 {
+            assert_eq!(fit_samples(&mut student, samples.clone()), Fit::Updated);
+        }
+        assert!((student.location - 5.0).abs() < 0.1, "{:?}", student);
+        assert!((student.scale - 2.0).abs() < 0.1, "{:?}", student);
+
+        let mut log_normal = LogNormal::new(0.0, 1.0, 1.0).unwrap();
+        let dist = rand_distr::LogNormal::new(1.0, 0.5).unwrap();
+        fit_samples(&mut log_normal, dist.sample_iter(&mut rng).take(20_000).collect());
+        assert!((log_normal.mu - 1.0).abs() < 0.02 && (log_normal.sigma - 0.5).abs() < 0.02, "{:?}", log_normal);
+
+        let mut exponential = Exponential::new(1.0, 1.0).unwrap();
+        let dist = rand_distr::Exp::new(4.0).unwrap();
+        fit_samples(&mut exponential, dist.sample_iter(&mut rng).take(20_000).collect());
+        assert!((exponential.rate - 4.0).abs() < 0.1, "{:?}", exponential);
+
+        let mut laplace = Laplace::new(0.0, 1.0, 1.0).unwrap();
+        assert_eq!(fit_samples(&mut laplace, vec![1.0, 2.0, 3.0, 10.0, -4.0]), Fit::Updated);
+        assert_eq!(laplace.location, 2.0);
+        assert_eq!(laplace.scale, 16.0 / 5.0);
+    }
+
+    #[test]
+    fn test_fit_degenerate_and_floored() {
+        let mut exponential = Exponential::new(1.0, 1.0).unwrap();
+        assert_eq!(fit_samples(&mut exponential, vec![-1.0, -2.0]), Fit::Degenerate);
+        assert_eq!(fit_samples(&mut exponential, vec![0.0, 0.0]), Fit::Degenerate);
+        assert_eq!(exponential.rate, 1.0);
+        let mut laplace = Laplace::new(0.0, 1.0, 1.0).unwrap();
+        let samples = Array1::from(vec![3.0, 3.0]);
+        let responsibilities = Array1::ones(2);
+        assert_eq!(
+            laplace.fit(samples.view(), responsibilities.view(), 0.25),
+            Fit::Floored { variance: 0.0 }
+        );
+        assert_eq!((laplace.location, laplace.scale), (3.0, 0.5));
+        let mut log_normal = LogNormal::new(0.0, 1.0, 1.0).unwrap();
+        assert_eq!(log_normal.log_density(0.0), f64::NEG_INFINITY);
+        assert_eq!(fit_samples(&mut log_normal, vec![0.0, -1.0]), Fit::Degenerate);
+    }
+
+    #[test]
+    fn test_bad_parameters() {
+        assert_eq!(StudentT::new(0.0, 1.0, 0.0, 0.5), Err(ComponentError::BadParameter("dof", 0.0)));
+        assert!(LogNormal::new(f64::NAN, 1.0, 0.5).is_err());
+        assert!(Exponential::new(-1.0, 0.5).is_err());
+        assert_eq!(Laplace::new(0.0, 1.0, 1.5), Err(ComponentError::BadWeight(1.5)));
+    }
+}
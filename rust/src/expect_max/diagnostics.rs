@@ -0,0 +1,68 @@
+use pyo3::{pyclass, pymethods};
+
+/// Intervention made by a maximization step on a degenerate component.
+///
+/// Components are numbered with the normal component first.
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmEvent {
+    /// Variance fell below the floor and was raised to it.
+    VarianceFloored { component: usize, variance: f64 },
+    /// Component explains no samples or its variance vanished, so its parameters were kept.
+    Collapsed { component: usize },
+    /// Component collapsed and was reset to its initial parameters.
+    Reinitialized { component: usize },
+}
+
+/// Report of the epochs run by a single Expectation Maximization update.
+#[pyclass]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EmDiagnostics {
+    /// Number of expectation steps run.
+    #[pyo3(get)]
+    pub epochs: u32,
+    /// Whether the update stopped early because the convergence check passed.
+    #[pyo3(get)]
+    pub converged: bool,
+    /// Log-likelihood of the samples at each expectation step.
+    #[pyo3(get)]
+    pub log_likelihoods: Vec<f64>,
+    /// Largest change of any mean, standard deviation or weight at each maximization step.
+    #[pyo3(get)]
+    pub parameter_deltas: Vec<f64>,
+    /// Interventions on degenerate components, in the order they happened.
+    #[pyo3(get)]
+    pub events: Vec<EmEvent>,
+}
+
+#[pymethods]
+impl EmDiagnostics {
+    /// Log-likelihood at the last expectation step, or None if no epoch ran.
+    pub fn final_log_likelihood(&self) -> Option<f64> {
+        self.log_likelihoods.last().copied()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl EmDiagnostics {
+    /// Return empty diagnostics with room for the given number of epochs.
+    pub(super) fn with_capacity(epochs: usize) -> Self {
+        Self {
+            log_likelihoods: Vec::with_capacity(epochs),
+            parameter_deltas: Vec::with_capacity(epochs),
+            ..Self::default()
+        }
+    }
+
+    pub(super) fn record_expectation(&mut self, log_likelihood: f64) {
+        self.epochs += 1;
+        self.log_likelihoods.push(log_likelihood);
+    }
+
+    pub(super) fn record_maximization(&mut self, parameter_delta: f64) {
+        self.parameter_deltas.push(parameter_delta);
+    }
+}
@@ -1,14 +1,49 @@
+use super::diagnostics::EmDiagnostics;
 use super::em_model::EmModel;
 use super::normal_params::NormalParamsError;
 use ndarray::{Array2, ArrayView2};
-use pyo3::{pyclass, pymethods};
+use numpy::{PyArray1, PyArray2};
+use pyo3::exceptions::PyTypeError;
+use pyo3::types::PyAnyMethods;
+use pyo3::{pyclass, pymethods, Bound, Py, PyAny, PyErr, PyResult, Python};
+use std::fmt;
+use std::iter::zip;
 
 /// Trait for any struct that checks if em model has converged
 pub trait HasConverged<T> {
+    /// Forget state from earlier updates. Called before the first epoch of every update.
+    fn reset_checker(&mut self) {}
     fn update_checker(&mut self, model: &EmModel);
     fn has_converged(&self, model: &EmModel, threshold: T) -> bool;
+
+    /// Like [`has_converged`](HasConverged::has_converged), for checkers that can fail.
+    /// Early stopping models call this, so an error stops the update.
+    fn try_has_converged(&self, model: &EmModel, threshold: T) -> PyResult<bool> {
+        Ok(self.has_converged(model, threshold))
+    }
 }
 
+impl<T, C: HasConverged<T> + ?Sized> HasConverged<T> for Box<C> {
+    fn reset_checker(&mut self) {
+        (**self).reset_checker();
+    }
+
+    fn update_checker(&mut self, model: &EmModel) {
+        (**self).update_checker(model);
+    }
+
+    fn has_converged(&self, model: &EmModel, threshold: T) -> bool {
+        (**self).has_converged(model, threshold)
+    }
+
+    fn try_has_converged(&self, model: &EmModel, threshold: T) -> PyResult<bool> {
+        (**self).try_has_converged(model, threshold)
+    }
+}
+
+/// Checker chosen at runtime, used by [`EmConvergenceCheck`].
+pub type BoxedChecker = Box<dyn HasConverged<f64> + Send + Sync>;
+
 // #[derive(Clone, Debug)]
 // #[non_exhaustive]
 // pub(super) enum ConvergenceCheckKind {
@@ -33,6 +68,39 @@ pub trait HasConverged<T> {
 //     }
 // }
 
+/// Error from an early stopping update.
+#[derive(Debug)]
+pub enum EarlyStopError {
+    Params(NormalParamsError),
+    /// The convergence check itself failed, e.g. a Python callback raised.
+    Check(PyErr),
+}
+
+impl fmt::Display for EarlyStopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EarlyStopError::Params(err) => write!(f, "{}", err),
+            EarlyStopError::Check(err) => write!(f, "Convergence check failed: {}", err),
+        }
+    }
+}
+
+impl From<NormalParamsError> for EarlyStopError {
+    fn from(err: NormalParamsError) -> EarlyStopError {
+        EarlyStopError::Params(err)
+    }
+}
+
+impl From<EarlyStopError> for PyErr {
+    fn from(err: EarlyStopError) -> PyErr {
+        match err {
+            EarlyStopError::Params(err) => err.into(),
+            // keep the exception the callback raised
+            EarlyStopError::Check(err) => err,
+        }
+    }
+}
+
 /// Expectation Maximization model that incorporates a check for early stopping.
 #[derive(Clone)]
 pub struct EarlyStopEmModel<T: HasConverged<f64>> {
@@ -45,17 +113,24 @@ impl<T: HasConverged<f64>> EarlyStopEmModel<T> {
         &mut self,
         point: f64,
         threshold: f64,
-    ) -> Result<(), NormalParamsError> {
-        self.em_model.swap_last_sample(point);
+    ) -> Result<EmDiagnostics, EarlyStopError> {
+        self.em_model.retain_sample(point);
+        self.converge_checker.reset_checker();
+        let mut diagnostics = EmDiagnostics::default();
         for _ in 0..self.em_model.epochs().value() {
             self.converge_checker.update_checker(&self.em_model);
-            self.em_model.expectation();
-            if self.has_converged(threshold) {
+            diagnostics.record_expectation(self.em_model.expectation());
+            if self
+                .converge_checker
+                .try_has_converged(&self.em_model, threshold)
+                .map_err(EarlyStopError::Check)?
+            {
+                diagnostics.converged = true;
                 break;
             }
-            self.em_model.maximization()?;
+            self.em_model.maximization_with_diagnostics(&mut diagnostics)?;
         }
-        Ok(())
+        Ok(diagnostics)
     }
 
     pub fn has_converged(&self, threshold: f64) -> bool {
@@ -69,6 +144,12 @@ pub struct LikelihoodChecker<T> {
     pub(super) prev_likelihood: Array2<T>,
 }
 
+impl Default for LikelihoodChecker<f64> {
+    fn default() -> Self {
+        Self { prev_likelihood: Array2::zeros((0, 0)) }
+    }
+}
+
 impl LikelihoodChecker<f64> {
     // pub fn has_converged(&self, em: &EmModel, threshold: f64) -> bool {
     //     let diffs = em.likelihoods() - self.prev_likelihood();
@@ -99,9 +180,208 @@ impl HasConverged<f64> for LikelihoodChecker<f64> {
     }
 }
 
+/// Checks the change in mean log-likelihood per sample between epochs.
+#[derive(Clone, Debug, Default)]
+pub struct LogLikelihoodChecker {
+    prev: Option<f64>,
+    curr: Option<f64>,
+}
+
+impl HasConverged<f64> for LogLikelihoodChecker {
+    fn reset_checker(&mut self) {
+        *self = Self::default();
+    }
+
+    fn update_checker(&mut self, em: &EmModel) {
+        self.prev = self.curr;
+        self.curr = Some(mean_log_likelihood(em));
+    }
+
+    fn has_converged(&self, _em: &EmModel, threshold: f64) -> bool {
+        match (self.prev, self.curr) {
+            (Some(prev), Some(curr)) => (curr - prev).abs() <= threshold,
+            _ => false,
+        }
+    }
+}
+
+/// Checks the largest change in any mean, standard deviation or weight between epochs.
+#[derive(Clone, Debug, Default)]
+pub struct ParameterChecker {
+    prev: Vec<(f64, f64, f64)>,
+    curr: Vec<(f64, f64, f64)>,
+}
+
+impl ParameterChecker {
+    /// Largest absolute change of any parameter since the previous epoch.
+    pub fn max_delta(&self) -> Option<f64> {
+        if self.prev.is_empty() || self.prev.len() != self.curr.len() {
+            return None;
+        }
+        let deltas = zip(&self.prev, &self.curr).flat_map(|(prev, curr)| {
+            [(prev.0 - curr.0).abs(), (prev.1 - curr.1).abs(), (prev.2 - curr.2).abs()]
+        });
+        Some(deltas.fold(0.0, f64::max))
+    }
+}
+
+impl HasConverged<f64> for ParameterChecker {
+    fn reset_checker(&mut self) {
+        self.prev.clear();
+        self.curr.clear();
+    }
+
+    fn update_checker(&mut self, em: &EmModel) {
+        std::mem::swap(&mut self.prev, &mut self.curr);
+        self.curr.clear();
+        self.curr.extend(em.parameters());
+    }
+
+    fn has_converged(&self, _em: &EmModel, threshold: f64) -> bool {
+        self.max_delta().is_some_and(|delta| delta <= threshold)
+    }
+}
+
+/// Checks the distance between the mean log-likelihood per sample and its Aitken
+/// accelerated limit, estimated from the last three epochs.
+///
+/// Stops earlier than [`LogLikelihoodChecker`] when the log-likelihood creeps up slowly.
+#[derive(Clone, Debug, Default)]
+pub struct AitkenChecker {
+    // oldest first
+    history: Vec<f64>,
+}
+
+impl AitkenChecker {
+    /// Aitken estimate of the converged mean log-likelihood.
+    pub fn asymptotic_estimate(&self) -> Option<f64> {
+        let &[older, old, new] = self.history.as_slice() else {
+            return None;
+        };
+        let step = old - older;
+        if step == 0.0 {
+            return Some(new);
+        }
+        let rate = (new - old) / step;
+        if rate >= 1.0 {
+            // not converging linearly yet
+            return None;
+        }
+        Some(old + (new - old) / (1.0 - rate))
+    }
+}
+
+impl HasConverged<f64> for AitkenChecker {
+    fn reset_checker(&mut self) {
+        self.history.clear();
+    }
+
+    fn update_checker(&mut self, em: &EmModel) {
+        if self.history.len() == 3 {
+            self.history.remove(0);
+        }
+        self.history.push(mean_log_likelihood(em));
+    }
+
+    fn has_converged(&self, _em: &EmModel, threshold: f64) -> bool {
+        match (self.asymptotic_estimate(), self.history.last()) {
+            (Some(estimate), Some(&last)) => (estimate - last).abs() <= threshold,
+            _ => false,
+        }
+    }
+}
+
+fn mean_log_likelihood(em: &EmModel) -> f64 {
+    em.log_likelihood() / em.sample_count().max(1) as f64
+}
+
+/// Checks convergence by calling a Python callable with a copy of the model and the threshold
+/// after every expectation step. The callable returns whether the model has converged.
+///
+/// An exception raised by the callable, or a result that is not a bool, stops the update with
+/// that exception.
+#[derive(Debug)]
+pub struct CallableChecker {
+    callback: Py<PyAny>,
+}
+
+impl CallableChecker {
+    pub fn new(callback: Py<PyAny>) -> Self {
+        Self { callback }
+    }
+}
+
+impl HasConverged<f64> for CallableChecker {
+    fn update_checker(&mut self, _em: &EmModel) {}
+
+    /// Errors from the callable count as not converged, use
+    /// [`try_has_converged`](HasConverged::try_has_converged) to see them.
+    fn has_converged(&self, em: &EmModel, threshold: f64) -> bool {
+        self.try_has_converged(em, threshold).unwrap_or(false)
+    }
+
+    fn try_has_converged(&self, em: &EmModel, threshold: f64) -> PyResult<bool> {
+        Python::attach(|py| {
+            let model = Py::new(py, em.clone())?;
+            let converged = self.callback.call1(py, (model, threshold))?;
+            converged.extract::<bool>(py)
+        })
+    }
+}
+
+/// Convergence check a model built from Python stops its updates early with.
+#[pyclass(eq, eq_int)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ConvergenceCheck {
+    /// Change in responsibilities, see [`LikelihoodChecker`].
+    #[default]
+    Likelihood,
+    /// Change in mean log-likelihood per sample, see [`LogLikelihoodChecker`].
+    LogLikelihood,
+    /// Change in any component parameter, see [`ParameterChecker`].
+    Parameter,
+    /// Aitken estimate of the converged log-likelihood, see [`AitkenChecker`].
+    Aitken,
+}
+
+impl ConvergenceCheck {
+    /// Return a new checker of this kind, with no state from earlier updates.
+    pub fn checker(self) -> BoxedChecker {
+        match self {
+            ConvergenceCheck::Likelihood => Box::<LikelihoodChecker<f64>>::default(),
+            ConvergenceCheck::LogLikelihood => Box::<LogLikelihoodChecker>::default(),
+            ConvergenceCheck::Parameter => Box::<ParameterChecker>::default(),
+            ConvergenceCheck::Aitken => Box::<AitkenChecker>::default(),
+        }
+    }
+}
+
+/// Return a checker for a [`ConvergenceCheck`] or a Python callable, see [`CallableChecker`].
+///
+/// # Errors
+///
+/// If check is neither.
+pub fn checker_from_py(check: &Bound<'_, PyAny>) -> PyResult<BoxedChecker> {
+    if let Ok(kind) = check.extract::<ConvergenceCheck>() {
+        return Ok(kind.checker());
+    }
+    if check.is_callable() {
+        return Ok(Box::new(CallableChecker::new(check.clone().unbind())));
+    }
+    Err(PyTypeError::new_err("check must be a ConvergenceCheck or a callable"))
+}
+
 // Now we add a macro so we can use this in a concrete way
 macro_rules! create_interface {
     ($name: ident, $type: ty) => {
+        create_interface!($name, $type, {
+            #[new]
+            pub fn new(model: EmModel) -> Self {
+                Self::from_model_and_checker(model, <$type>::default())
+            }
+        });
+    };
+    ($name: ident, $type: ty, { $($constructor: tt)* }) => {
         #[pyclass]
         pub struct $name {
             inner: EarlyStopEmModel<$type>,
@@ -109,13 +389,43 @@ macro_rules! create_interface {
 
         #[pymethods]
         impl $name {
+            $($constructor)*
+
             pub fn update_check_convergence(
         &mut self,
         point: f64,
         threshold: f64,
-        ) -> Result<(), NormalParamsError> { self.inner.update_check_convergence(point, threshold) }
+        ) -> Result<EmDiagnostics, EarlyStopError> { self.inner.update_check_convergence(point, threshold) }
 
             pub fn predict(&self, point: f64) -> f64 { self.inner.em_model.predict(point) }
+
+            pub fn predict_proba(&self, point: f64) -> Vec<f64> { self.inner.em_model.predict_proba(point) }
+
+            pub fn component_posteriors(&self, point: f64) -> Vec<f64> { self.inner.em_model.predict_proba(point) }
+
+            pub fn classify(&self, point: f64) -> (usize, f64) { self.inner.em_model.classify(point) }
+
+            pub fn novelty_score(&self, point: f64) -> f64 { self.inner.em_model.novelty_score(point) }
+
+            pub fn parameters(&self) -> Vec<(f64, f64, f64)> { self.inner.em_model.parameters() }
+
+            pub fn normal_parameters(&self) -> (f64, f64, f64) { self.inner.em_model.normal_parameters() }
+
+            pub fn abnormal_parameters(&self) -> Vec<(f64, f64, f64)> { self.inner.em_model.abnormal_parameters() }
+
+            pub fn parameters_array<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+                self.inner.em_model.parameters_array(py)
+            }
+
+            #[pyo3(name = "likelihoods")]
+            pub fn likelihoods_array<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+                self.inner.em_model.likelihoods_array(py)
+            }
+
+            #[pyo3(name = "samples")]
+            pub fn samples_array<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+                self.inner.em_model.samples_array(py)
+            }
         }
 
         impl $name {
@@ -130,3 +440,158 @@ macro_rules! create_interface {
     };
 }
 create_interface!(EmLikelihoodCheck, LikelihoodChecker<f64>);
+create_interface!(EmLogLikelihoodCheck, LogLikelihoodChecker);
+create_interface!(EmParameterCheck, ParameterChecker);
+create_interface!(EmAitkenCheck, AitkenChecker);
+create_interface!(EmConvergenceCheck, BoxedChecker, {
+    #[new]
+    pub fn new(model: EmModel, check: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self::from_model_and_checker(model, checker_from_py(check)?))
+    }
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expect_max::em_model_builder::EmBuilder;
+    use pyo3::exceptions::PyZeroDivisionError;
+    use pyo3::types::{PyDict, PyDictMethods};
+    use pyo3::IntoPyObject;
+
+    fn make_early_stop_model<C: HasConverged<f64> + Clone>(checker: C) -> EarlyStopEmModel<C> {
+        let samples = [-1.0, 0.0, 1.0, 0.5, -0.5, 30.0, 29.0, 31.0];
+        EmBuilder::new()
+            .build_normal(1.0, 2.0, 0.5).unwrap()
+            .build_abnormal_from_tuples(&[(25.0, 3.0, 0.5)]).unwrap()
+            .build_epochs(200).unwrap()
+            .build_samples_from_slice(&samples)
+            .build_likelihoods()
+            .build_converge_checker(checker)
+            .get_model()
+    }
+
+    #[test]
+    fn test_log_likelihood_checker() {
+        let mut model = make_early_stop_model(LogLikelihoodChecker::default());
+        model.converge_checker.update_checker(&model.em_model);
+        // a single epoch has nothing to compare against
+        assert!(!model.has_converged(1.0));
+        let diagnostics = model.update_check_convergence(0.2, 1e-9).unwrap();
+        assert!(diagnostics.converged);
+        assert!(diagnostics.epochs < 200);
+        assert_eq!(diagnostics.parameter_deltas.len() as u32, diagnostics.epochs - 1);
+        assert!(model.has_converged(1e-9));
+        model.converge_checker.reset_checker();
+        assert!(!model.has_converged(1.0));
+    }
+
+    #[test]
+    fn test_parameter_checker() {
+        let mut checker = ParameterChecker::default();
+        let mut model = make_early_stop_model(ParameterChecker::default());
+        checker.update_checker(&model.em_model);
+        assert_eq!(checker.max_delta(), None);
+        model.em_model.expectation();
+        model.em_model.maximization().unwrap();
+        checker.update_checker(&model.em_model);
+        let delta = checker.max_delta().unwrap();
+        assert!(delta > 0.5, "delta: {}", delta);
+        assert!(!checker.has_converged(&model.em_model, 0.5));
+        model.update_check_convergence(0.2, 1e-9).unwrap();
+        assert!(model.has_converged(1e-9));
+    }
+
+    #[test]
+    fn test_aitken_checker_estimate() {
+        let checker = AitkenChecker { history: vec![-3.0, -2.0, -1.5] };
+        // steps halve, so the limit is one more step of 0.5
+        assert_eq!(checker.asymptotic_estimate(), Some(-1.0));
+        let checker = AitkenChecker { history: vec![-3.0, -2.0] };
+        assert_eq!(checker.asymptotic_estimate(), None);
+    }
+
+    #[test]
+    fn test_aitken_checker_stops_early() {
+        let mut log_likelihood = make_early_stop_model(LogLikelihoodChecker::default());
+        let mut aitken = make_early_stop_model(AitkenChecker::default());
+        let slow = log_likelihood.update_check_convergence(0.2, 1e-9).unwrap();
+        let fast = aitken.update_check_convergence(0.2, 1e-9).unwrap();
+        assert!(slow.converged && fast.converged);
+        assert!(fast.epochs <= slow.epochs, "aitken: {}, log-likelihood: {}", fast.epochs, slow.epochs);
+    }
+
+    #[test]
+    fn test_boxed_checker_matches_unboxed() {
+        let mut unboxed = make_early_stop_model(LogLikelihoodChecker::default());
+        let standard = make_early_stop_model(LogLikelihoodChecker::default()).em_model;
+        let checker = ConvergenceCheck::LogLikelihood.checker();
+        let mut boxed = EarlyStopEmModel { em_model: standard, converge_checker: checker };
+        let expected = unboxed.update_check_convergence(0.2, 1e-9).unwrap();
+        let diagnostics = boxed.update_check_convergence(0.2, 1e-9).unwrap();
+        assert!(diagnostics.converged);
+        assert_eq!(diagnostics.epochs, expected.epochs);
+        assert_eq!(boxed.em_model.parameters(), unboxed.em_model.parameters());
+    }
+
+    #[test]
+    fn test_convergence_check_checkers() {
+        let checks = [
+            ConvergenceCheck::Likelihood,
+            ConvergenceCheck::LogLikelihood,
+            ConvergenceCheck::Parameter,
+            ConvergenceCheck::Aitken,
+        ];
+        for check in checks {
+            let em_model = make_early_stop_model(LogLikelihoodChecker::default()).em_model;
+            let mut model = EarlyStopEmModel { em_model, converge_checker: check.checker() };
+            let diagnostics = model.update_check_convergence(0.2, 1e-6).unwrap();
+            assert!(diagnostics.converged, "check: {:?}", check);
+            assert!(diagnostics.epochs < 200, "check: {:?}", check);
+        }
+    }
+
+    #[test]
+    fn test_callable_checker() {
+        Python::initialize();
+        Python::attach(|py| {
+            let globals = PyDict::new(py);
+            py.run(
+                c"calls = []\ndef check(model, threshold):\n    calls.append(threshold)\n    return len(calls) == 3\n",
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+            let check = globals.get_item("check").unwrap().unwrap();
+            let em_model = make_early_stop_model(LogLikelihoodChecker::default()).em_model;
+            let mut model = EarlyStopEmModel { em_model, converge_checker: checker_from_py(&check).unwrap() };
+            let diagnostics = model.update_check_convergence(0.2, 0.5).unwrap();
+            assert!(diagnostics.converged);
+            assert_eq!(diagnostics.epochs, 3);
+            let calls: Vec<f64> = globals.get_item("calls").unwrap().unwrap().extract().unwrap();
+            assert_eq!(calls, vec![0.5; 3]);
+        });
+    }
+
+    #[test]
+    fn test_callable_checker_propagates_errors() {
+        Python::initialize();
+        Python::attach(|py| {
+            let failing = py.eval(c"lambda model, threshold: 1 / 0", None, None).unwrap();
+            let em_model = make_early_stop_model(LogLikelihoodChecker::default()).em_model;
+            let mut model = EarlyStopEmModel { em_model, converge_checker: checker_from_py(&failing).unwrap() };
+            let Err(EarlyStopError::Check(err)) = model.update_check_convergence(0.2, 0.5) else {
+                panic!("error from the callback should stop the update");
+            };
+            assert!(err.is_instance_of::<PyZeroDivisionError>(py));
+            let not_bool = py.eval(c"lambda model, threshold: 'yes'", None, None).unwrap();
+            let checker = checker_from_py(&not_bool).unwrap();
+            assert!(checker.try_has_converged(&model.em_model, 0.5).is_err());
+            assert!(!checker.has_converged(&model.em_model, 0.5));
+            let number = 1_i32.into_pyobject(py).unwrap().into_any();
+            let Err(err) = checker_from_py(&number) else {
+                panic!("a number is neither a check nor callable");
+            };
+            assert!(err.is_instance_of::<PyTypeError>(py));
+        });
+    }
+}
@@ -1,10 +1,17 @@
+use super::component::{Component, Fit, MixtureComponent};
+use super::diagnostics::{EmDiagnostics, EmEvent};
 use super::pos_int::PositiveInteger;
-use super::normal_params::{NormalParams, NormalParamsError};
+use super::priors::MapPriors;
+use super::normal_params::NormalParamsError;
+use super::retention::{RetentionError, RetentionPolicy, SampleRetention};
 use itertools::izip;
-use ndarray::{s, Array1, Array2, ArrayView2, Axis, Zip};
-use pyo3::{pyclass, pymethods};
+use ndarray::{Array1, Array2, ArrayView2, Axis};
+use numpy::{PyArray1, PyArray2, ToPyArray};
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, pymethods, Bound, PyErr, Python};
+use std::fmt;
 use std::iter::zip;
-use super::em_model_builder::EmBuilderOne;
+use super::em_model_builder::EmBuilder;
 
 #[derive(Debug)]
 pub enum EmModelError {
@@ -13,21 +20,93 @@ pub enum EmModelError {
     ProbabilityError(f64),
 }
 
+#[derive(Copy, Clone, Debug)]
+pub struct EmOptionsError(f64);
+
+impl fmt::Display for EmOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Variance floor must be finite and non-negative, got {}.", self.0)
+    }
+}
+
+impl From<EmOptionsError> for PyErr {
+    fn from(err: EmOptionsError) -> PyErr {
+        PyValueError::new_err(format!("{}", err))
+    }
+}
+
+/// Optional behaviour of the expectation and maximization steps.
+#[pyclass]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EmOptions {
+    /// Compute responsibilities from log densities, normalized with log-sum-exp.
+    #[pyo3(get, set)]
+    pub log_domain: bool,
+    /// Smallest variance a component may take. Non-normal components floor their squared scale.
+    #[pyo3(get, set)]
+    pub variance_floor: f64,
+    /// Reset collapsed components to their initial parameters instead of keeping them.
+    #[pyo3(get, set)]
+    pub reinitialize: bool,
+}
+
+#[pymethods]
+impl EmOptions {
+    #[new]
+    #[pyo3(signature = (log_domain=false, variance_floor=0.0, reinitialize=false))]
+    pub fn new(log_domain: bool, variance_floor: f64, reinitialize: bool) -> Result<Self, EmOptionsError> {
+        let options = Self { log_domain, variance_floor, reinitialize };
+        options.validate()?;
+        Ok(options)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl EmOptions {
+    pub fn validate(&self) -> Result<(), EmOptionsError> {
+        if self.variance_floor.is_finite() && self.variance_floor >= 0.0 {
+            Ok(())
+        } else {
+            Err(EmOptionsError(self.variance_floor))
+        }
+    }
+}
+
 #[pyclass]
 #[derive(Clone)]
 pub struct EmModel {
-    pub(super) normal: NormalParams,
-    pub(super) abnormals: Vec<NormalParams>,
+    pub(super) normal: Component,
+    pub(super) abnormals: Vec<Component>,
     pub(super) samples: Array1<f64>,
     pub(super) likelihoods: Array2<f64>,
     pub(super) epochs: PositiveInteger,
+    pub(super) retention: SampleRetention,
+    pub(super) options: EmOptions,
+    // parameters the model was built with, normal first, for re-initialization
+    pub(super) initial: Vec<Component>,
+    pub(super) priors: MapPriors,
 }
 
 #[pymethods]
 impl EmModel {
 
-    pub fn update(&mut self, point: f64) -> Result<(), NormalParamsError> {
-        self.swap_last_sample(point);
+    /// Update model parameters using given point, running every epoch.
+    pub fn update(&mut self, point: f64) -> Result<EmDiagnostics, NormalParamsError> {
+        self.retain_sample(point);
+        let mut diagnostics = EmDiagnostics::with_capacity(self.epochs.value() as usize);
+        for _ in 0..self.epochs.value() {
+            diagnostics.record_expectation(self.expectation());
+            self.maximization_with_diagnostics(&mut diagnostics)?;
+        }
+        Ok(diagnostics)
+    }
+
+    /// Same as `update` without recording diagnostics, for updating on every sample.
+    pub fn update_without_diagnostics(&mut self, point: f64) -> Result<(), NormalParamsError> {
+        self.retain_sample(point);
         for _ in 0..self.epochs.value() {
             self.expectation();
             self.maximization()?;
@@ -39,60 +118,128 @@ impl EmModel {
         self.posterior_prob(point)
     }
 
-    pub fn expectation(&mut self) {
-        // raw probabilities
-        let sample_view = self.samples.view();
-        let mut normal_view = self.likelihoods.row_mut(0);
-        self.normal
-            .probs_inplace_arr(&sample_view, &mut normal_view);
-        let mut abnormals = self.likelihoods.slice_mut(s![1.., ..]);
-        let abnormals_view = abnormals.rows_mut();
-        for (mut likelihood, abnormal) in zip(abnormals_view, &self.abnormals) {
-            abnormal.probs_inplace_arr(&sample_view, &mut likelihood);
-        }
-        // normalize
-        let norms = self.likelihoods.sum_axis(Axis(0));
-        let likelihood_view = self.likelihoods.columns_mut();
-        for (mut likelihood, &norm) in zip(likelihood_view, &norms) {
-            if norm != 0.0 {
-                likelihood /= norm;
-            }
+    /// Compute responsibilities of each component for each sample.
+    ///
+    /// Returns log-likelihood of the samples under the parameters used.
+    pub fn expectation(&mut self) -> f64 {
+        if self.options.log_domain {
+            self.log_expectation()
+        } else {
+            self.raw_expectation()
         }
     }
 
     pub fn maximization(&mut self) -> Result<(), NormalParamsError> {
-        let densities = self.likelihoods.sum_axis(Axis(1));
-        // if any densities are zero, leave
-        if densities.iter().all(|&density| density.is_normal()) {
-            // Get new parameter estimates
-            let means = self.update_means(&densities);
-            let variances = self.update_variances(&densities, &means);
-            let size = self.samples.len();
-            let weights = self.update_weights(&densities, size);
-            // update parameters
-            self.normal
-                .update_params(means[0], variances[0].sqrt(), weights[0])?;
-            let param_iter = izip!(&means, &variances, &weights).skip(1);
-            for (param, (&mean, &variance, &weight)) in zip(&mut self.abnormals, param_iter) {
-                param.update_params(mean, variance.sqrt(), weight)?;
+        self.maximize(&mut Vec::new())
+    }
+
+    /// Return log-likelihood of the samples under the current parameters.
+    pub fn log_likelihood(&self) -> f64 {
+        self.samples.iter().map(|&point| self.point_log_likelihood(point)).sum()
+    }
+
+    /// Return posterior probability of every component for the point, normal first.
+    ///
+    /// A point impossible under every component has probability zero everywhere.
+    pub fn predict_proba(&self, point: f64) -> Vec<f64> {
+        let params = std::iter::once(&self.normal).chain(&self.abnormals);
+        if self.options.log_domain {
+            let log_denom = self.point_log_likelihood(point);
+            if !log_denom.is_finite() {
+                return vec![0.0; self.abnormals.len() + 1];
             }
+            return params.map(|param| (param.log_likelihood(point) - log_denom).exp()).collect();
         }
-        // else
-        Ok(())
+        let likelihoods: Vec<f64> = params.map(|param| param.likelihood(point)).collect();
+        let denom: f64 = likelihoods.iter().sum();
+        match denom {
+            0.0 => likelihoods,
+            _ => likelihoods.iter().map(|likelihood| likelihood / denom).collect(),
+        }
+    }
+
+    /// Same as `predict_proba`, kept under its earlier name.
+    pub fn component_posteriors(&self, point: f64) -> Vec<f64> {
+        self.predict_proba(point)
+    }
+
+    /// Return most probable component for the point, normal first, with its posterior probability.
+    ///
+    /// A point impossible under every component is given to the normal component with
+    /// probability zero.
+    pub fn classify(&self, point: f64) -> (usize, f64) {
+        most_probable(&self.predict_proba(point))
+    }
+
+    /// Return negative log-likelihood of the point under the whole mixture.
+    ///
+    /// Large values mean no component explains the point well. Computed from log densities,
+    /// so points far beyond every component still get finite, ordered scores.
+    pub fn novelty_score(&self, point: f64) -> f64 {
+        -self.point_log_likelihood(point)
+    }
+
+    /// Return (location, scale, weight) of every component, normal first.
+    ///
+    /// For normal components these are the mean, standard deviation and weight.
+    pub fn parameters(&self) -> Vec<(f64, f64, f64)> {
+        std::iter::once(&self.normal)
+            .chain(&self.abnormals)
+            .map(|param| param.parameters())
+            .collect()
+    }
+
+    /// Return (location, scale, weight) of the normal component.
+    pub fn normal_parameters(&self) -> (f64, f64, f64) {
+        self.normal.parameters()
+    }
+
+    /// Return (location, scale, weight) of every abnormal component.
+    pub fn abnormal_parameters(&self) -> Vec<(f64, f64, f64)> {
+        self.abnormals.iter().map(|param| param.parameters()).collect()
+    }
+
+    /// Return components by (location, scale, weight) as an array, normal first.
+    pub fn parameters_array<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        let parameters = self.parameters();
+        Array2::from_shape_fn((parameters.len(), 3), |(row, col)| {
+            let (location, scale, weight) = parameters[row];
+            [location, scale, weight][col]
+        })
+        .to_pyarray(py)
+    }
+
+    /// Return copy of the responsibilities from the last expectation step, one row per
+    /// component with the normal component first and one column per sample.
+    #[pyo3(name = "likelihoods")]
+    pub fn likelihoods_array<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        self.likelihoods.to_pyarray(py)
+    }
+
+    /// Return copy of the samples the model is fit to.
+    #[pyo3(name = "samples")]
+    pub fn samples_array<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        self.samples.to_pyarray(py)
     }
 
     fn posterior_prob(&self, point: f64) -> f64 {
-        let num: f64 = self.normal.likelihood(point);
-        let denom: f64 = num
-            + self
-                .abnormals
-                .iter()
-                .map(|param| param.likelihood(point))
-                .sum::<f64>();
-        match denom {
-            0.0 => num,
-            _ => num / denom,
+        self.predict_proba(point)[0]
+    }
+
+    /// Add point to the samples according to the retention policy.
+    ///
+    /// Returns the sample that was replaced, if any.
+    pub fn retain_sample(&mut self, point: f64) -> Option<f64> {
+        let out = self.retention.insert(&mut self.samples, point);
+        if self.likelihoods.ncols() != self.samples.len() {
+            self.likelihoods = Array2::zeros((self.likelihoods.nrows(), self.samples.len()));
         }
+        out
+    }
+
+    /// Number of samples the model is fit to.
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
     }
 
     // todo make this an error
@@ -109,31 +256,72 @@ impl EmModel {
 
 impl EmModel {
     pub fn new(
-        normal: NormalParams,
-        abnormals: Vec<NormalParams>,
+        normal: impl Into<Component>,
+        abnormals: impl IntoIterator<Item = impl Into<Component>>,
         samples: Array1<f64>,
         epochs: PositiveInteger,
     ) -> Self {
+        Self::with_retention(normal, abnormals, samples, epochs, RetentionPolicy::SwapLast())
+            .expect("Swapping the last sample needs no capacity")
+    }
+
+    /// Create model that keeps observed points according to the retention policy.
+    ///
+    /// # Errors
+    ///
+    /// If the policy has a capacity of zero.
+    pub fn with_retention(
+        normal: impl Into<Component>,
+        abnormals: impl IntoIterator<Item = impl Into<Component>>,
+        mut samples: Array1<f64>,
+        epochs: PositiveInteger,
+        policy: RetentionPolicy,
+    ) -> Result<Self, RetentionError> {
+        policy.validate()?;
+        let normal = normal.into();
+        let abnormals: Vec<Component> = abnormals.into_iter().map(Into::into).collect();
+        let retention = SampleRetention::new(policy, &mut samples);
         let sample_size = samples.len();
         let num_params = abnormals.len() + 1;
         let likelihoods = Array2::<f64>::zeros((num_params, sample_size));
-        Self {
+        let initial = std::iter::once(normal).chain(abnormals.iter().copied()).collect();
+        Ok(Self {
             normal,
             abnormals,
             samples,
             likelihoods,
             epochs,
-        }
+            retention,
+            options: EmOptions::default(),
+            initial,
+            priors: MapPriors::default(),
+        })
     }
 
-    pub fn builder() -> EmBuilderOne<f64> {
-        EmBuilderOne::new()
+    pub fn builder() -> EmBuilder {
+        EmBuilder::new()
     }
 
     pub fn epochs(&self) -> PositiveInteger {
         self.epochs
     }
 
+    pub fn normal(&self) -> &Component {
+        &self.normal
+    }
+
+    pub fn abnormals(&self) -> &[Component] {
+        &self.abnormals
+    }
+
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        self.retention.policy()
+    }
+
+    pub fn samples(&self) -> &Array1<f64> {
+        &self.samples
+    }
+
     pub fn likelihoods(&self) -> &Array2<f64> {
         &self.likelihoods
     }
@@ -142,29 +330,142 @@ impl EmModel {
         self.likelihoods.view()
     }
 
-    /// Return mean estimates for normal and abnormal distributions
-    fn update_means(&self, densities: &Array1<f64>) -> Array1<f64> {
+    /// Expectation step on raw densities. Samples far from every component get
+    /// zero responsibilities.
+    fn raw_expectation(&mut self) -> f64 {
+        // raw probabilities
         let sample_view = self.samples.view();
-        // let means = (self.likelihoods * densities).sum_axis(Axis(1));
-        let means: Array1<f64> = self.likelihoods.map_axis(
-            Axis(1),
-            |row| row.dot(&sample_view), /*dot_product(&row, &sample_view)*/
-        ) / densities;
-        means
+        let params = std::iter::once(&self.normal).chain(&self.abnormals);
+        for (mut row, param) in zip(self.likelihoods.rows_mut(), params) {
+            row.zip_mut_with(&sample_view, |res, &point| *res = param.likelihood(point));
+        }
+        // normalize
+        let norms = self.likelihoods.sum_axis(Axis(0));
+        let likelihood_view = self.likelihoods.columns_mut();
+        for (mut likelihood, &norm) in zip(likelihood_view, &norms) {
+            if norm != 0.0 {
+                likelihood /= norm;
+            }
+        }
+        norms.iter().map(|norm| norm.ln()).sum()
     }
 
-    /// Return variance value estimates for normal and abnormal distributions
-    fn update_variances(&self, densities: &Array1<f64>, means: &Array1<f64>) -> Array1<f64> {
+    /// Expectation step on log densities, normalized with log-sum-exp so every sample
+    /// gets responsibilities summing to one.
+    fn log_expectation(&mut self) -> f64 {
         let sample_view = self.samples.view();
-        let means_view = means.view();
-        let variances = Zip::from(self.likelihoods.rows())
-            .and(&means_view)
-            .map_collect(|row, &mean| {
-                let value = (&sample_view - mean).powi(2);
-                row.dot(&value)
+        let params = std::iter::once(&self.normal).chain(&self.abnormals);
+        for (mut row, param) in zip(self.likelihoods.rows_mut(), params) {
+            row.zip_mut_with(&sample_view, |res, &point| *res = param.log_likelihood(point));
+        }
+        let num_params = self.likelihoods.nrows() as f64;
+        let mut total = 0.0;
+        for mut column in self.likelihoods.columns_mut() {
+            let max = column.fold(f64::NEG_INFINITY, |acc, &value| acc.max(value));
+            if !max.is_finite() {
+                // impossible under every component, so no component is preferred
+                column.fill(num_params.recip());
+                total += max;
+                continue;
+            }
+            column.mapv_inplace(|value| (value - max).exp());
+            let norm = column.sum();
+            column /= norm;
+            total += max + norm.ln();
+        }
+        total
+    }
+
+    fn point_log_likelihood(&self, point: f64) -> f64 {
+        let log_likelihoods: Vec<f64> = std::iter::once(&self.normal)
+            .chain(&self.abnormals)
+            .map(|param| param.log_likelihood(point))
+            .collect();
+        let max = log_likelihoods.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if !max.is_finite() {
+            return max;
+        }
+        max + log_likelihoods.iter().map(|value| (value - max).exp()).sum::<f64>().ln()
+    }
+
+    /// Run maximization step, recording the largest change of any parameter and any
+    /// interventions on degenerate components.
+    pub fn maximization_with_diagnostics(
+        &mut self,
+        diagnostics: &mut EmDiagnostics,
+    ) -> Result<(), NormalParamsError> {
+        let before = self.parameters();
+        self.maximize(&mut diagnostics.events)?;
+        let after = std::iter::once(&self.normal).chain(&self.abnormals).map(|param| param.parameters());
+        let delta = zip(before, after)
+            .map(|(prev, curr)| {
+                (prev.0 - curr.0).abs().max((prev.1 - curr.1).abs()).max((prev.2 - curr.2).abs())
             })
-            / densities;
-        variances
+            .fold(0.0, f64::max);
+        diagnostics.record_maximization(delta);
+        Ok(())
+    }
+
+    /// Maximization step that handles each component on its own.
+    ///
+    /// Each component fits its own shape from the responsibilities. Normal components with
+    /// a prior take the posterior mode of their parameters instead of the maximum likelihood
+    /// estimate, as do the weights under a Dirichlet prior. Variances are raised to the
+    /// variance floor. Components the samples do not determine are kept, or reset when
+    /// re-initialization is enabled.
+    fn maximize(&mut self, events: &mut Vec<EmEvent>) -> Result<(), NormalParamsError> {
+        let densities = self.likelihoods.sum_axis(Axis(1));
+        let size = self.samples.len();
+        let weights = if self.priors.concentrations().is_empty() {
+            self.update_weights(&densities, size)
+        } else {
+            Array1::from(self.priors.map_weights(densities.as_slice().expect("densities are contiguous")))
+        };
+        let floor = self.options.variance_floor;
+        let mut intervened = false;
+        let params = std::iter::once(&mut self.normal).chain(&mut self.abnormals);
+        let estimates = izip!(self.likelihoods.rows(), &weights, &self.initial);
+        for (component, (param, (responsibilities, &weight, initial))) in params.zip(estimates).enumerate() {
+            let samples = self.samples.view();
+            // priors only apply to normal components
+            let fit = match param {
+                Component::Normal(normal) => {
+                    normal.fit_with_prior(samples, responsibilities, floor, self.priors.component(component))
+                }
+                _ => param.fit(samples, responsibilities, floor),
+            };
+            match fit {
+                Fit::Updated => {}
+                Fit::Floored { variance } => events.push(EmEvent::VarianceFloored { component, variance }),
+                Fit::Degenerate => {
+                    intervened = true;
+                    if self.options.reinitialize {
+                        *param = *initial;
+                        events.push(EmEvent::Reinitialized { component });
+                    } else {
+                        events.push(EmEvent::Collapsed { component });
+                    }
+                    continue;
+                }
+            }
+            param.set_weight(weight)?;
+        }
+        if intervened {
+            self.normalize_weights()?;
+        }
+        Ok(())
+    }
+
+    /// Rescale component weights to sum to one.
+    fn normalize_weights(&mut self) -> Result<(), NormalParamsError> {
+        let total: f64 = std::iter::once(&self.normal).chain(&self.abnormals).map(|param| param.weight()).sum();
+        if total.is_normal() {
+            for param in std::iter::once(&mut self.normal).chain(&mut self.abnormals) {
+                let weight = (param.weight() / total).min(1.0);
+                param.set_weight(weight)?;
+            }
+        }
+        Ok(())
     }
 
     /// Return an updated estimate of probabilities for normal and abnormal distributions
@@ -173,39 +474,43 @@ impl EmModel {
     }
 }
 
+/// Return index of the largest probability, the first one on ties, with the probability.
+pub(super) fn most_probable(probabilities: &[f64]) -> (usize, f64) {
+    probabilities
+        .iter()
+        .copied()
+        .enumerate()
+        .fold((0, f64::NEG_INFINITY), |best, (idx, prob)| if prob > best.1 { (idx, prob) } else { best })
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::expect_max::em_model_builder::EmBuilderOne;
+    use crate::expect_max::em_model_builder::EmBuilder;
+    use crate::expect_max::normal_params::NormalParams;
     use super::*;
     
     #[test]
     fn test_build_model() {
         let abnormals = vec![NormalParams::from_tuple((30.0, 1.0, 0.25)).unwrap()];
         let samples = vec![-1.0, 0.0, 1.0, 30.0, 29.0, 31.0];
-        let mut model_one = EmBuilderOne::new();
-        let em_model = model_one
+        let em_model = EmBuilder::new()
             .build_normal(0.0, 1.0, 0.5).unwrap()
             .build_abnormal(&abnormals)
             .build_samples_from_slice(&samples)
-            .next_builder().unwrap()
             .build_likelihoods()
-            .next_builder().unwrap()
-            .get_standard_model();
+            .get_model();
         assert_eq!(em_model.epochs, PositiveInteger::new(1).unwrap());
     }
     
     fn make_standard_model() -> EmModel {
         let abnormals = vec![NormalParams::from_tuple((30.0, 1.0, 0.25)).unwrap()];
         let samples = vec![-1.0, 0.0, 1.0, 30.0, 29.0, 31.0];
-        let mut model_one = EmBuilderOne::new();
-        model_one
+        EmBuilder::new()
             .build_normal(0.0, 1.0, 0.5).unwrap()
             .build_abnormal(&abnormals)
             .build_samples_from_slice(&samples)
-            .next_builder().unwrap()
             .build_likelihoods()
-            .next_builder().unwrap()
-            .get_standard_model()
+            .get_model()
     }
 
     #[test]
@@ -227,6 +532,197 @@ mod tests {
         let _res = model.swap_last_sample(-14.0);
     }
 
+    #[test]
+    fn test_update_fifo_resizes_likelihoods() {
+        let normal = NormalParams::from_tuple((0.0, 1.0, 0.5)).unwrap();
+        let abnormals = vec![NormalParams::from_tuple((30.0, 1.0, 0.5)).unwrap()];
+        let samples = Array1::from(vec![-1.0, 0.0, 1.0, 30.0]);
+        let epochs = PositiveInteger::new(2).unwrap();
+        let policy = RetentionPolicy::Fifo { capacity: 6 };
+        let mut model = EmModel::with_retention(normal, abnormals, samples, epochs, policy).unwrap();
+        for point in [0.5, 29.5, -0.5] {
+            model.update(point).unwrap();
+        }
+        assert_eq!(model.sample_count(), 6);
+        assert_eq!(model.likelihoods.dim(), (2, 6));
+        assert_eq!(model.samples, Array1::from(vec![-0.5, 0.0, 1.0, 30.0, 0.5, 29.5]));
+    }
+
+    #[test]
+    fn test_log_likelihood() {
+        let model = make_standard_model();
+        let expected: f64 = model
+            .samples
+            .iter()
+            .map(|&point| (model.normal.likelihood(point) + model.abnormals[0].likelihood(point)).ln())
+            .sum();
+        assert!((model.log_likelihood() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_component_posteriors() {
+        let mut model = make_standard_model();
+        for point in [0.0, 15.0, 30.0] {
+            let posteriors = model.component_posteriors(point);
+            assert_eq!(posteriors.len(), 2);
+            assert!((posteriors.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            assert_eq!(posteriors[0], model.predict(point));
+            model.options.log_domain = true;
+            let log_posteriors = model.component_posteriors(point);
+            assert!(zip(&posteriors, &log_posteriors).all(|(raw, log)| (raw - log).abs() < 1e-12));
+            model.options.log_domain = false;
+        }
+        // too far from every component for raw densities
+        assert_eq!(model.component_posteriors(1e6), vec![0.0, 0.0]);
+        assert_eq!(model.normal_parameters(), (0.0, 1.0, 0.5));
+        assert_eq!(model.abnormal_parameters(), vec![(30.0, 1.0, 0.25)]);
+        assert_eq!(model.parameters(), vec![(0.0, 1.0, 0.5), (30.0, 1.0, 0.25)]);
+    }
+
+    #[test]
+    fn test_predict_proba() {
+        let model = make_standard_model();
+        for point in [0.0, 15.0, 30.0, 1e6] {
+            assert_eq!(model.predict_proba(point), model.component_posteriors(point));
+        }
+        assert_eq!(model.classify(1e6), (0, 0.0));
+    }
+
+    #[test]
+    fn test_classify_and_novelty_score() {
+        let model = make_standard_model();
+        let (component, confidence) = model.classify(29.5);
+        assert_eq!(component, 1);
+        assert_eq!(confidence, model.predict_proba(29.5)[1]);
+        assert!(confidence > 0.99);
+        assert_eq!(model.classify(0.5).0, 0);
+        assert!((model.novelty_score(0.0) + model.point_log_likelihood(0.0)).abs() < 1e-12);
+        assert!(model.novelty_score(15.0) > model.novelty_score(0.0));
+        // log densities still rank points where raw densities underflow
+        assert!(model.novelty_score(1e3) < model.novelty_score(1e4));
+        assert_eq!(most_probable(&[0.2, 0.4, 0.4]), (1, 0.4));
+    }
+
+    #[test]
+    fn test_update_without_diagnostics() {
+        let mut model = make_standard_model();
+        let mut quiet = model.clone();
+        for point in [0.5, 29.0, -0.3] {
+            model.update(point).unwrap();
+            quiet.update_without_diagnostics(point).unwrap();
+        }
+        assert_eq!(quiet.parameters(), model.parameters());
+    }
+
+    #[test]
+    fn test_update_diagnostics() {
+        let normal = NormalParams::from_tuple((1.0, 2.0, 0.5)).unwrap();
+        let abnormals = vec![NormalParams::from_tuple((25.0, 3.0, 0.5)).unwrap()];
+        let samples = Array1::from(vec![-1.0, 0.0, 1.0, 30.0, 29.0, 31.0, 0.0]);
+        let epochs = PositiveInteger::new(5).unwrap();
+        let mut model = EmModel::new(normal, abnormals, samples, epochs);
+        let diagnostics = model.update(0.5).unwrap();
+        assert_eq!(diagnostics.epochs, 5);
+        assert!(!diagnostics.converged);
+        assert_eq!(diagnostics.log_likelihoods.len(), 5);
+        assert_eq!(diagnostics.parameter_deltas.len(), 5);
+        // EM never decreases the log-likelihood
+        assert!(diagnostics.log_likelihoods.windows(2).all(|pair| pair[1] >= pair[0] - 1e-9));
+        assert!(diagnostics.parameter_deltas[0] > diagnostics.parameter_deltas[4]);
+        let expected = model.log_likelihood();
+        model.expectation();
+        assert!((model.expectation() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_log_expectation_far_samples() {
+        let mut model = make_standard_model();
+        model.samples = Array1::from(vec![-1.0, 0.0, 1.0, 30.0, 29.0, 31.0, 1e4]);
+        model.likelihoods = Array2::zeros((2, 7));
+        let mut log_model = model.clone();
+        log_model.options.log_domain = true;
+        let raw = model.expectation();
+        let log = log_model.expectation();
+        // the far sample underflows every raw density
+        assert_eq!(raw, f64::NEG_INFINITY);
+        assert_eq!(model.likelihoods.column(6).sum(), 0.0);
+        assert!(log.is_finite());
+        assert!((log - log_model.log_likelihood()).abs() < 1e-6 * log.abs());
+        for column in log_model.likelihoods.columns() {
+            assert!((column.sum() - 1.0).abs() < 1e-12);
+        }
+        // the abnormal component is closer to the far sample
+        assert!(log_model.likelihoods[[1, 6]] > 0.99);
+        for idx in 0..6 {
+            assert!((log_model.likelihoods[[0, idx]] - model.likelihoods[[0, idx]]).abs() < 1e-9);
+        }
+        assert_eq!(log_model.predict(1e4), 0.0);
+        assert!((log_model.predict(0.5) - model.predict(0.5)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_maximization_variance_floor() {
+        let mut model = make_standard_model();
+        model.samples = Array1::from(vec![0.0, 0.0, 0.0]);
+        model.likelihoods = Array2::zeros((2, 3));
+        model.abnormals[0] = NormalParams::from_tuple((1000.0, 1.0, 0.5)).unwrap().into();
+        let mut floored = model.clone();
+        floored.options.variance_floor = 0.5;
+        floored.expectation();
+        let mut diagnostics = EmDiagnostics::default();
+        floored.maximization_with_diagnostics(&mut diagnostics).unwrap();
+        let expected = vec![
+            EmEvent::VarianceFloored { component: 0, variance: 0.0 },
+            EmEvent::Collapsed { component: 1 },
+        ];
+        assert_eq!(diagnostics.events, expected);
+        assert_eq!(floored.normal.mean(), 0.0);
+        assert!((floored.normal.stddev() - 0.5f64.sqrt()).abs() < 1e-12);
+        // without a floor the vanished variance counts as a collapse
+        model.expectation();
+        let before = model.parameters();
+        let mut diagnostics = EmDiagnostics::default();
+        model.maximization_with_diagnostics(&mut diagnostics).unwrap();
+        let expected = vec![EmEvent::Collapsed { component: 0 }, EmEvent::Collapsed { component: 1 }];
+        assert_eq!(diagnostics.events, expected);
+        assert_eq!(model.parameters(), before);
+    }
+
+    #[test]
+    fn test_maximization_collapsed_component() {
+        let mut model = make_standard_model();
+        model.samples = Array1::from(vec![-1.0, 0.0, 1.0, 2.0]);
+        model.likelihoods = Array2::zeros((2, 4));
+        model.abnormals[0] = NormalParams::from_tuple((1000.0, 1.0, 0.5)).unwrap().into();
+        let mut reinit_model = model.clone();
+        reinit_model.options.reinitialize = true;
+        // the abnormal component explains none of the samples
+        model.expectation();
+        let mut diagnostics = EmDiagnostics::default();
+        model.maximization_with_diagnostics(&mut diagnostics).unwrap();
+        assert_eq!(diagnostics.events, vec![EmEvent::Collapsed { component: 1 }]);
+        assert!((model.normal.mean() - 0.5).abs() < 1e-12);
+        assert_eq!(model.abnormals[0].mean(), 1000.0);
+        // weights are renormalized around the kept component
+        assert!((model.normal.weight() - 1.0 / 1.5).abs() < 1e-12);
+        assert!((model.abnormals[0].weight() - 0.5 / 1.5).abs() < 1e-12);
+
+        reinit_model.expectation();
+        let mut diagnostics = EmDiagnostics::default();
+        reinit_model.maximization_with_diagnostics(&mut diagnostics).unwrap();
+        assert_eq!(diagnostics.events, vec![EmEvent::Reinitialized { component: 1 }]);
+        assert_eq!(reinit_model.abnormals[0].mean(), 30.0);
+        let total = reinit_model.normal.weight() + reinit_model.abnormals[0].weight();
+        assert!((total - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_options_validate() {
+        assert!(EmOptions::new(false, -1.0, false).is_err());
+        assert!(EmOptions::new(false, f64::INFINITY, false).is_err());
+        assert!(EmOptions::new(true, 0.1, true).is_ok());
+    }
+
     #[test]
     fn test_update() {
         let mut model = make_standard_model();
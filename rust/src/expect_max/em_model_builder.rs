@@ -1,14 +1,24 @@
-use super::em_early_stop_model::{EarlyStopEmModel, LikelihoodChecker};
+use super::em_early_stop_model::{EarlyStopEmModel, HasConverged, LikelihoodChecker};
 use super::em_model_builder::BuildError::BadNormalValues;
-use super::em_model_builder::FieldStatus::Complete;
 use super::normal_params::{NormalParams, NormalParamsError};
 use ndarray::{Array1, Array2};
 use std::iter::zip;
 use pyo3::exceptions::PyValueError;
 use pyo3::PyErr;
-use super::em_model::EmModel;
-use super::normal::Normal;
+use super::component::Component;
+use super::em_model::{EmModel, EmOptions, EmOptionsError};
+use super::hmm::HmmError;
+use super::initialization::{initial_components, InitError, InitStrategy};
+use super::model_selection::{best_score, Criterion, SelectionScore};
+use super::multivariate_normal::MultivariateError;
+use super::normal::{Normal, NormalError};
+use rand::distr::Distribution;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use super::pos_int::{PositiveError, PositiveInteger};
+use super::priors::{MapPriors, PriorError};
+use crate::bocpd::normal_inverse_gamma::NormalInverseGamma;
+use super::retention::{RetentionError, RetentionPolicy};
 
 // trait EmBuild {
 //     fn build_normal();
@@ -18,17 +28,16 @@ use super::pos_int::{PositiveError, PositiveInteger};
 //     fn get_model() -> EmModel;
 // }
 
-#[derive(Debug)]
-enum FieldStatus<T> {
-    Complete(T),
-    Incomplete(T),
-    NotStarted,
-}
-
 #[derive(Debug)]
 pub enum BuildError<T: Send + Sync> {
     BadEpoch(PositiveError),
     BadNormalValues(NormalParamsError),
+    BadRetention(RetentionError),
+    BadOptions(EmOptionsError),
+    BadPrior(PriorError),
+    BadMultivariate(MultivariateError),
+    BadInitialization(InitError),
+    BadHmm(HmmError),
     // FieldConstructionError(T),
     IncompleteBuildError(MissingFieldError<T>),
 }
@@ -38,6 +47,12 @@ impl<T: Send + Sync> From<BuildError<T>> for PyErr {
         match value {
             BuildError::BadEpoch(e) => e.into(),
             BadNormalValues(e) => e.into(),
+            BuildError::BadRetention(e) => e.into(),
+            BuildError::BadOptions(e) => e.into(),
+            BuildError::BadPrior(e) => e.into(),
+            BuildError::BadMultivariate(e) => e.into(),
+            BuildError::BadInitialization(e) => e.into(),
+            BuildError::BadHmm(e) => e.into(),
             BuildError::IncompleteBuildError(e) => e.into(),
         }
     }
@@ -55,6 +70,42 @@ impl<T: Send + Sync> From<PositiveError> for BuildError<T> {
     }
 }
 
+impl<T: Send + Sync> From<RetentionError> for BuildError<T> {
+    fn from(err: RetentionError) -> Self {
+        BuildError::BadRetention(err)
+    }
+}
+
+impl<T: Send + Sync> From<EmOptionsError> for BuildError<T> {
+    fn from(err: EmOptionsError) -> Self {
+        BuildError::BadOptions(err)
+    }
+}
+
+impl<T: Send + Sync> From<PriorError> for BuildError<T> {
+    fn from(err: PriorError) -> Self {
+        BuildError::BadPrior(err)
+    }
+}
+
+impl<T: Send + Sync> From<MultivariateError> for BuildError<T> {
+    fn from(err: MultivariateError) -> Self {
+        BuildError::BadMultivariate(err)
+    }
+}
+
+impl<T: Send + Sync> From<InitError> for BuildError<T> {
+    fn from(err: InitError) -> Self {
+        BuildError::BadInitialization(err)
+    }
+}
+
+impl<T: Send + Sync> From<HmmError> for BuildError<T> {
+    fn from(err: HmmError) -> Self {
+        BuildError::BadHmm(err)
+    }
+}
+
 impl<T: Send + Sync> From<NormalParamsError> for BuildError<T> {
     fn from(err: NormalParamsError) -> Self {
         BadNormalValues(err)
@@ -79,55 +130,185 @@ impl<T> From<MissingFieldError<T>> for PyErr {
     }
 }
 
-#[derive(Debug)]
-pub struct EmBuilderOne<T> {
-    normal: NormalParams,
-    abnormals: Vec<NormalParams>,
-    sample_arr: FieldStatus<Array1<T>>,
+/// Draw training samples for each component given as (mean, standard deviation, probability).
+///
+/// The i-th component contributes `arr_sizes[i]` samples; components without a size are skipped.
+/// Identical seeds give identical samples; without a seed the thread rng is used.
+pub fn generate_samples(
+    params: &[(f64, f64, f64)],
+    arr_sizes: &[u32],
+    seed: Option<u64>,
+) -> Result<Vec<f64>, NormalError> {
+    match seed {
+        Some(seed) => generate_samples_with_rng(params, arr_sizes, &mut StdRng::seed_from_u64(seed)),
+        None => generate_samples_with_rng(params, arr_sizes, &mut rand::rng()),
+    }
+}
+
+/// Draw training samples for each component using the given random number generator.
+pub fn generate_samples_with_rng<R: Rng + ?Sized>(
+    params: &[(f64, f64, f64)],
+    arr_sizes: &[u32],
+    rng: &mut R,
+) -> Result<Vec<f64>, NormalError> {
+    let mut samples = Vec::with_capacity(arr_sizes.iter().map(|&size| size as usize).sum());
+    for (&(mean, stddev, _prob), &size) in zip(params, arr_sizes) {
+        Normal::new(mean, stddev)?;
+        let dist = rand_distr::Normal::new(mean, stddev)
+            .expect("Parameters were validated by Normal");
+        samples.extend(dist.sample_iter(&mut *rng).take(size as usize));
+    }
+    Ok(samples)
+}
+
+/// Typestate of an [`EmBuilder`] without training samples.
+#[derive(Clone, Debug)]
+pub struct NoSamples;
+
+/// Typestate of an [`EmBuilder`] with training samples, plus the slot reserved for swapping.
+#[derive(Clone, Debug)]
+pub struct WithSamples(Array1<f64>);
+
+/// Typestate of an [`EmBuilder`] whose components can still be changed.
+#[derive(Clone, Debug)]
+pub struct NoLikelihoods;
+
+/// Typestate of an [`EmBuilder`] with fixed components, from which the model sizes its likelihoods.
+#[derive(Clone, Debug)]
+pub struct WithLikelihoods;
+
+/// Typestate of an [`EmBuilder`] that builds an [`EmModel`].
+#[derive(Clone, Debug)]
+pub struct NoChecker;
+
+/// Typestate of an [`EmBuilder`] that builds an [`EarlyStopEmModel`] with the checker.
+#[derive(Clone, Debug)]
+pub struct WithChecker<C>(C);
+
+/// Builder for [`EmModel`] and [`EarlyStopEmModel`].
+///
+/// Which fields are set is tracked by the type parameters, so a model can only be requested
+/// once samples and likelihoods are built:
+///
+/// ```compile_fail
+/// use _change_point_algorithms::expect_max::em_model_builder::EmBuilder;
+///
+/// let model = EmBuilder::new().build_likelihoods().get_model();
+/// ```
+///
+/// ```compile_fail
+/// use _change_point_algorithms::expect_max::em_model_builder::EmBuilder;
+///
+/// let model = EmBuilder::new().build_samples_from_slice(&[1.0]).get_model();
+/// ```
+///
+/// Components and options are set before the likelihoods, which are sized from them.
+/// Building a checker turns the product of [`EmBuilder::get_model`] into an early stopping model.
+#[derive(Clone, Debug)]
+pub struct EmBuilder<S = NoSamples, L = NoLikelihoods, C = NoChecker> {
+    normal: Component,
+    abnormals: Vec<Component>,
     epochs: PositiveInteger,
+    retention: RetentionPolicy,
+    options: EmOptions,
+    priors: MapPriors,
+    samples: S,
+    likelihoods: L,
+    checker: C,
 }
 
-impl EmBuilderOne<f64> {
-    // #[new]
+impl EmBuilder {
     pub fn new() -> Self {
         let normal = NormalParams::new(
             Normal::new(0.0, 1.0).expect("The default values used should never fail"),
             1.0,
         )
-        .expect("The default parameters should never fail");
-        let abnormals: Vec<NormalParams> = Vec::new();
+        .expect("The default parameters should never fail")
+        .into();
         let epochs: u32 = 1;
         Self {
             normal,
-            abnormals,
-            sample_arr: FieldStatus::NotStarted,
-            // likelihoods_arr: None,
+            abnormals: Vec::new(),
             epochs: PositiveInteger::new(epochs).expect("The default value used should never fail"),
+            retention: RetentionPolicy::default(),
+            options: EmOptions::default(),
+            priors: MapPriors::default(),
+            samples: NoSamples,
+            likelihoods: NoLikelihoods,
+            checker: NoChecker,
         }
     }
+}
+
+impl Default for EmBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
+impl<S, L, C> EmBuilder<S, L, C> {
+    /// Move every field into a builder whose typestates are made from the current ones.
+    fn into_state<S2, L2, C2>(self, states: impl FnOnce(S, L, C) -> (S2, L2, C2)) -> EmBuilder<S2, L2, C2> {
+        let (samples, likelihoods, checker) = states(self.samples, self.likelihoods, self.checker);
+        EmBuilder {
+            normal: self.normal,
+            abnormals: self.abnormals,
+            epochs: self.epochs,
+            retention: self.retention,
+            options: self.options,
+            priors: self.priors,
+            samples,
+            likelihoods,
+            checker,
+        }
+    }
+}
+
+impl<S> EmBuilder<S, NoLikelihoods, NoChecker> {
     pub fn build_normal(
-        &mut self,
+        mut self,
         mean: f64,
         stddev: f64,
         prob: f64,
-    ) -> Result<&mut Self, BuildError<()>> {
-        self.normal.update_params(mean, stddev, prob)?;
+    ) -> Result<Self, BuildError<()>> {
+        self.normal = NormalParams::from_tuple((mean, stddev, prob))?.into();
         Ok(self)
     }
 
-    pub fn build_abnormal(&mut self, abnormals: &[NormalParams]) -> &mut Self {
-        abnormals.clone_into(&mut self.abnormals);
+    /// Use any component distribution for the normal class.
+    ///
+    /// # Errors
+    ///
+    /// If the component is not normal but has a Normal-Inverse-Gamma prior.
+    pub fn build_normal_component(mut self, normal: Component) -> Result<Self, BuildError<()>> {
+        self.normal = normal;
+        self.check_priors()?;
+        Ok(self)
+    }
+
+    pub fn build_abnormal(mut self, abnormals: &[NormalParams]) -> Self {
+        self.abnormals = abnormals.iter().copied().map(Component::from).collect();
         self
     }
 
+    /// Use any mix of component distributions for the abnormal classes.
+    ///
+    /// # Errors
+    ///
+    /// If a component that is not normal has a Normal-Inverse-Gamma prior.
+    pub fn build_abnormal_components(mut self, abnormals: &[Component]) -> Result<Self, BuildError<()>> {
+        abnormals.clone_into(&mut self.abnormals);
+        self.check_priors()?;
+        Ok(self)
+    }
+
     pub fn build_abnormal_from_tuples(
-        &mut self,
+        mut self,
         abnormals: &[(f64, f64, f64)],
-    ) -> Result<&mut Self, BuildError<&mut Self>> {
+    ) -> Result<Self, BuildError<()>> {
         for &(mean, stddev, prob) in abnormals {
             let abnormal = NormalParams::from_tuple((mean, stddev, prob))?;
-            self.abnormals.push(abnormal);
+            self.abnormals.push(abnormal.into());
         }
         Ok(self)
     }
@@ -138,202 +319,365 @@ impl EmBuilderOne<f64> {
     ///
     /// If failure occurs while trying to set errors to a given value,
     /// then an error will happen. This is almost exclusively caused by epochs being 0.
-    // pub fn build_epochs(&mut self, epochs: u32) -> Result<&mut Self, BuildError<Box<Self>>> {
-    pub fn build_epochs(&mut self, epochs: u32) -> Result<&mut Self, BuildError<()>> {
+    pub fn build_epochs(mut self, epochs: u32) -> Result<Self, BuildError<()>> {
         self.epochs.set(epochs)?;
         Ok(self)
     }
 
-    pub fn build_samples_from_slice(&mut self, samples: &[f64]) -> &mut Self {
+    /// Set how observed points are kept among the samples.
+    ///
+    /// # Errors
+    ///
+    /// If the policy has a capacity of zero.
+    pub fn build_retention(mut self, policy: RetentionPolicy) -> Result<Self, BuildError<()>> {
+        policy.validate()?;
+        self.retention = policy;
+        Ok(self)
+    }
+
+    /// Set whether the expectation step works on log densities with log-sum-exp normalization.
+    ///
+    /// Samples far from every component then still get responsibilities that sum to one.
+    pub fn build_log_domain(mut self, log_domain: bool) -> Self {
+        self.options.log_domain = log_domain;
+        self
+    }
+
+    /// Set the smallest variance a component may take in the maximization step.
+    ///
+    /// # Errors
+    ///
+    /// If the floor is negative or not finite.
+    pub fn build_variance_floor(self, variance_floor: f64) -> Result<Self, BuildError<()>> {
+        let options = EmOptions { variance_floor, ..self.options.clone() };
+        self.build_options(options)
+    }
+
+    /// Set whether collapsed components are reset to their initial parameters.
+    pub fn build_reinitialize(mut self, reinitialize: bool) -> Self {
+        self.options.reinitialize = reinitialize;
+        self
+    }
+
+    /// Replace all optional expectation and maximization behaviour at once.
+    ///
+    /// # Errors
+    ///
+    /// If the variance floor is negative or not finite.
+    pub fn build_options(mut self, options: EmOptions) -> Result<Self, BuildError<()>> {
+        options.validate()?;
+        self.options = options;
+        Ok(self)
+    }
+
+    /// Place a Normal-Inverse-Gamma prior on the mean and variance of a component,
+    /// making its maximization step a maximum a posteriori update.
+    ///
+    /// Components are numbered with the normal component first.
+    ///
+    /// # Errors
+    ///
+    /// If kappa, alpha or beta are not positive, any hyperparameter is not finite, or the
+    /// component is not normal.
+    pub fn build_component_prior(
+        mut self,
+        component: usize,
+        prior: NormalInverseGamma,
+    ) -> Result<Self, BuildError<()>> {
+        self.priors.set_component(component, prior)?;
+        self.check_priors()?;
+        Ok(self)
+    }
+
+    /// Place a Dirichlet prior on the component weights, normal component first.
+    ///
+    /// Each concentration adds concentration - 1 pseudo-samples to its component.
+    ///
+    /// # Errors
+    ///
+    /// If any concentration is below 1 or not finite.
+    pub fn build_weight_prior(mut self, concentrations: &[f64]) -> Result<Self, BuildError<()>> {
+        self.priors.set_concentrations(concentrations)?;
+        Ok(self)
+    }
+
+    /// Only normal components can have a Normal-Inverse-Gamma prior.
+    fn check_priors(&self) -> Result<(), PriorError> {
+        self.priors.check_components(std::iter::once(&self.normal).chain(&self.abnormals))
+    }
+
+    /// Set the training samples, replacing any set before.
+    ///
+    /// A placeholder sample is appended for the point given to each update.
+    pub fn build_samples_from_slice(self, samples: &[f64]) -> EmBuilder<WithSamples, NoLikelihoods, NoChecker> {
         let mut sample_arr = Array1::zeros(samples.len() + 1);
         for (out, &sample) in zip(&mut sample_arr, samples) {
             *out = sample;
         }
         debug_assert_eq!(samples.len() + 1, sample_arr.len());
-        self.sample_arr = Complete(sample_arr);
-        self
+        self.into_state(|_, likelihoods, checker| (WithSamples(sample_arr), likelihoods, checker))
     }
+}
 
-    /// Finish current builder changes and return the next builder.
+impl EmBuilder<WithSamples, NoLikelihoods, NoChecker> {
+    /// Choose the normal component and abnormal_count abnormal components from the samples.
+    ///
+    /// Each of restarts initializations is fit with the current epochs, options and priors,
+    /// and the fitted components with the best log-likelihood are kept. Identical seeds give
+    /// identical components; without a seed the thread rng is used.
     ///
     /// # Errors
     ///
-    /// If sample_arr does not have FieldStatus Complete, then BuildError will be returned.
-    pub fn next_builder(&mut self) -> Result<EmBuilderTwo<f64>, BuildError<Box<&mut Self>>> {
-        if let Complete(sample_arr) = &self.sample_arr {
-            let abnormals = self.abnormals.clone();
-            let sample_arr = sample_arr.clone();
-            Ok(EmBuilderTwo {
-                normal: self.normal,
-                abnormals,
-                sample_arr,
-                likelihoods_arr: FieldStatus::NotStarted,
-                epochs: self.epochs,
-            })
-        } else {
-            Err(BuildError::from(MissingFieldError { my_struct: Box::new(self), field: String::from("sample_arr") }))
+    /// If restarts is zero, or there are fewer finite samples than components.
+    pub fn build_initialization(
+        self,
+        abnormal_count: usize,
+        strategy: InitStrategy,
+        restarts: u32,
+        seed: Option<u64>,
+    ) -> Result<Self, BuildError<()>> {
+        match seed {
+            Some(seed) => self.build_initialization_with_rng(abnormal_count, strategy, restarts, &mut StdRng::seed_from_u64(seed)),
+            None => self.build_initialization_with_rng(abnormal_count, strategy, restarts, &mut rand::rng()),
         }
     }
-}
 
-impl Default for EmBuilderOne<f64> {
-    fn default() -> Self {
-        Self::new()
+    /// Choose components from the samples using the given random number generator.
+    ///
+    /// # Errors
+    ///
+    /// See [`EmBuilder::build_initialization`].
+    pub fn build_initialization_with_rng<R: Rng + ?Sized>(
+        mut self,
+        abnormal_count: usize,
+        strategy: InitStrategy,
+        restarts: u32,
+        rng: &mut R,
+    ) -> Result<Self, BuildError<()>> {
+        let model = self.fit_initialization(abnormal_count, strategy, restarts, rng)?;
+        self.normal = *model.normal();
+        model.abnormals().clone_into(&mut self.abnormals);
+        Ok(self)
     }
-}
-
-#[derive(Debug)]
-pub struct EmBuilderTwo<T> {
-    normal: NormalParams,
-    abnormals: Vec<NormalParams>,
-    sample_arr: Array1<T>,
-    likelihoods_arr: FieldStatus<Array2<T>>,
-    epochs: PositiveInteger,
-}
-
-impl<T: Clone + num_traits::identities::Zero + Send + Sync> EmBuilderTwo<T> {
 
-    /// Initialize array for likelihoods
-    pub fn build_likelihoods(&mut self) -> &mut Self {
-        let sample_size = self.sample_arr.len();
-        let num_params = self.abnormals.len() + 1;
-        let likelihoods = Array2::<T>::zeros((num_params, sample_size));
-        self.likelihoods_arr = Complete(likelihoods);
-        self
+    /// Initialize and fit components for every abnormal count and keep the count with the best
+    /// score under the criterion.
+    ///
+    /// Returns the builder with the chosen components and the scores of every count, in the
+    /// order given. Identical seeds give identical components; without a seed the thread rng
+    /// is used.
+    ///
+    /// # Errors
+    ///
+    /// If no abnormal count has a score, or initialization fails for any of them.
+    pub fn build_selected_components(
+        self,
+        abnormal_counts: &[usize],
+        criterion: Criterion,
+        strategy: InitStrategy,
+        restarts: u32,
+        seed: Option<u64>,
+    ) -> Result<(Self, Vec<SelectionScore>), BuildError<()>> {
+        match seed {
+            Some(seed) => self.build_selected_components_with_rng(abnormal_counts, criterion, strategy, restarts, &mut StdRng::seed_from_u64(seed)),
+            None => self.build_selected_components_with_rng(abnormal_counts, criterion, strategy, restarts, &mut rand::rng()),
+        }
     }
 
-    /// Finish current builder changes and return the next builder.
+    /// Select the number of abnormal components using the given random number generator.
     ///
     /// # Errors
     ///
-    /// If likelihoods_arr does not have FieldStatus Complete, then BuildError will be returned.
-    pub fn next_builder(&mut self) -> Result<EmBuilderLast<T>, BuildError<Box<&mut Self>>> {
-        if let Complete(likelihoods_arr) = &self.likelihoods_arr {
-            let sample_arr: Array1<T> = self.sample_arr.clone();
-            Ok(EmBuilderLast {
-                normal: self.normal,
-                abnormals: self.abnormals.clone(),
-                sample_arr,
-                likelihoods_arr: likelihoods_arr.clone(),
-                epochs: self.epochs,
-                converge_checker: None,
-            })
-        } else {
-            Err(BuildError::from(MissingFieldError { my_struct: Box::new(self), field: String::from("likelihoods_arr" )}))
+    /// See [`EmBuilder::build_selected_components`].
+    pub fn build_selected_components_with_rng<R: Rng + ?Sized>(
+        mut self,
+        abnormal_counts: &[usize],
+        criterion: Criterion,
+        strategy: InitStrategy,
+        restarts: u32,
+        rng: &mut R,
+    ) -> Result<(Self, Vec<SelectionScore>), BuildError<()>> {
+        let samples = self.training_samples();
+        let mut scores = Vec::with_capacity(abnormal_counts.len());
+        let mut fitted = Vec::with_capacity(abnormal_counts.len());
+        for &abnormal_count in abnormal_counts {
+            let model = self.fit_initialization(abnormal_count, strategy, restarts, rng)?;
+            let mut model = EmModel::new(*model.normal(), model.abnormals().iter().copied(), samples.clone(), self.epochs);
+            model.options = self.options.clone();
+            scores.push(SelectionScore::from_model(&mut model));
+            fitted.push((*model.normal(), model.abnormals().to_vec()));
         }
+        let Some(best) = best_score(&scores, criterion) else {
+            return Err(InitError::NoCandidates.into());
+        };
+        (self.normal, self.abnormals) = fitted.swap_remove(best);
+        Ok((self, scores))
     }
-}
 
-#[derive(Debug)]
-pub struct EmBuilderLast<T> {
-    normal: NormalParams,
-    abnormals: Vec<NormalParams>,
-    sample_arr: Array1<T>,
-    likelihoods_arr: Array2<T>,
-    converge_checker: Option<LikelihoodChecker<f64>>,
-    epochs: PositiveInteger,
-}
+    /// Fix the components, after which the model allocates the likelihood of every component
+    /// for every sample.
+    pub fn build_likelihoods(self) -> EmBuilder<WithSamples, WithLikelihoods, NoChecker> {
+        self.into_state(|samples, _, checker| (samples, WithLikelihoods, checker))
+    }
 
-impl EmBuilderLast<f64> {
-    // pub fn get_model(&self) {
-    //     match self.converge_checker {
-    //         Some(_) => self.get_early_stop_model(),
-    //         None => self.get_standard_model()
-    //     }
-    // }
+    fn training_samples(&self) -> Array1<f64> {
+        // the last sample is the slot reserved for swapping in observed points
+        self.samples.0.slice(ndarray::s![..-1]).to_owned()
+    }
+
+    /// Fit every restart of the initialization and return the model with the best log-likelihood.
+    fn fit_initialization<R: Rng + ?Sized>(
+        &self,
+        abnormal_count: usize,
+        strategy: InitStrategy,
+        restarts: u32,
+        rng: &mut R,
+    ) -> Result<EmModel, BuildError<()>> {
+        if restarts == 0 {
+            return Err(InitError::NoRestarts.into());
+        }
+        let samples = self.training_samples();
+        let sample_vec = samples.to_vec();
+        let runs = if strategy == InitStrategy::Quantiles { 1 } else { restarts };
+        let mut best: Option<(f64, EmModel)> = None;
+        for _ in 0..runs {
+            let components = initial_components(&sample_vec, abnormal_count + 1, strategy, rng)?;
+            let mut model = EmModel::new(components[0], components[1..].iter().copied(), samples.clone(), self.epochs);
+            model.options = self.options.clone();
+            model.priors = self.priors.clone();
+            for _ in 0..self.epochs.value() {
+                model.expectation();
+                model.maximization()?;
+            }
+            let log_likelihood = model.log_likelihood();
+            if best.as_ref().is_none_or(|(best_log_likelihood, _)| log_likelihood > *best_log_likelihood) {
+                best = Some((log_likelihood, model));
+            }
+        }
+        let (_, model) = best.expect("At least one restart was run");
+        Ok(model)
+    }
+}
 
+impl<C> EmBuilder<WithSamples, WithLikelihoods, C> {
+    /// Return model without early stopping, whether or not a checker was built.
     pub fn get_standard_model(&self) -> EmModel {
-        let samples = self.sample_arr.clone();
         let abnormals = self.abnormals.clone();
-        let likelihoods = self.likelihoods_arr.clone();
-        EmModel { normal: self.normal, abnormals, samples, likelihoods, epochs: self.epochs}
+        let sample_arr = &self.samples.0;
+        let mut model = if self.retention == RetentionPolicy::SwapLast() {
+            EmModel::new(self.normal, abnormals, sample_arr.clone(), self.epochs)
+        } else {
+            // other policies add points themselves, so drop the slot reserved for swapping
+            let samples = sample_arr.slice(ndarray::s![..-1]).to_owned();
+            EmModel::with_retention(self.normal, abnormals, samples, self.epochs, self.retention)
+                .expect("Retention policy was validated when set")
+        };
+        model.options = self.options.clone();
+        model.priors = self.priors.clone();
+        model
     }
+}
 
-    pub fn get_early_stop_model(&self) -> EarlyStopEmModel<LikelihoodChecker<f64>> {
-        let Some(checker) = &self.converge_checker else {
-            panic!("Converge checker not initialized!");
-        };
-        let em_model = self.get_standard_model();
-        let converge_checker = checker.clone();
-        EarlyStopEmModel {
-            em_model,
-            converge_checker,
-        }
+impl EmBuilder<WithSamples, WithLikelihoods, NoChecker> {
+    /// Stop updates early when the checker reports convergence.
+    pub fn build_converge_checker<C: HasConverged<f64>>(self, checker: C) -> EmBuilder<WithSamples, WithLikelihoods, WithChecker<C>> {
+        self.into_state(|samples, likelihoods, _| (samples, likelihoods, WithChecker(checker)))
     }
 
-    pub fn build_likelihood_converge_checker(&mut self) -> &mut Self {
-        let likelihood_check = Array2::zeros(self.likelihoods_arr.raw_dim());
-        self.converge_checker = Some(LikelihoodChecker {
+    /// Stop updates early when the responsibilities stop changing.
+    pub fn build_likelihood_converge_checker(self) -> EmBuilder<WithSamples, WithLikelihoods, WithChecker<LikelihoodChecker<f64>>> {
+        let likelihood_check = Array2::zeros((self.abnormals.len() + 1, self.samples.0.len()));
+        self.build_converge_checker(LikelihoodChecker {
             prev_likelihood: likelihood_check,
-        });
-        self
+        })
+    }
+
+    pub fn get_model(&self) -> EmModel {
+        self.get_standard_model()
+    }
+}
+
+impl<C: HasConverged<f64> + Clone> EmBuilder<WithSamples, WithLikelihoods, WithChecker<C>> {
+    pub fn get_model(&self) -> EarlyStopEmModel<C> {
+        EarlyStopEmModel {
+            em_model: self.get_standard_model(),
+            converge_checker: self.checker.0.clone(),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    // EmBuilderOne tests
+    use crate::expect_max::component::{Laplace, MixtureComponent};
+    use crate::expect_max::em_early_stop_model::ParameterChecker;
 
     #[test]
-    fn test_em_builder_one_build_normal() {
-        let mut em = EmBuilderOne::new();
-        let result = em.build_normal(-2.0, 10.0, 0.5);
+    fn test_em_builder_build_normal() {
+        let result = EmBuilder::new().build_normal(-2.0, 10.0, 0.5);
         assert!(result.is_ok());
     }
 
     #[test]
-    fn test_em_builder_one_build_normal_fails_bad_mean() {
-        let mut em = EmBuilderOne::new();
-        let result = em.build_normal(f64::INFINITY, 2.0, 0.5);
+    fn test_em_builder_build_normal_fails_bad_mean() {
+        let result = EmBuilder::new().build_normal(f64::INFINITY, 2.0, 0.5);
         assert!(result.is_err());
     }
 
     #[test]
-    fn test_em_builder_one_build_normal_fails_bad_std_dev() {
-        let mut em = EmBuilderOne::new();
-        let result = em.build_normal(0.0, -2.0, 0.5);
+    fn test_em_builder_build_normal_fails_bad_std_dev() {
+        let result = EmBuilder::new().build_normal(0.0, -2.0, 0.5);
         assert!(result.is_err());
     }
 
     #[test]
-    fn test_em_builder_one_build_normal_fails_bad_prob() {
-        let mut em = EmBuilderOne::new();
-        let result = em.build_normal(0.0, 2.0, 1.5);
+    fn test_em_builder_build_normal_fails_bad_prob() {
+        let result = EmBuilder::new().build_normal(0.0, 2.0, 1.5);
         assert!(result.is_err());
     }
 
     #[test]
-    fn test_em_builder_one_build_abnormal() {
-        let mut em = EmBuilderOne::new();
+    fn test_em_builder_build_abnormal() {
+        let em = EmBuilder::new();
         assert!(em.abnormals.is_empty());
         let values = vec![
             NormalParams::new(Normal::new(0.0, 1.0).unwrap(), 0.5).unwrap(),
             NormalParams::new(Normal::new(1.0, 2.0).unwrap(), 0.5).unwrap()];
-        em.build_abnormal(&values);
-        assert_eq!(em.abnormals.get(0), Some(&values[0]));
-        assert_eq!(em.abnormals.get(1), Some(&values[1]));
+        let em = em.build_abnormal(&values);
+        assert_eq!(em.abnormals.get(0), Some(&values[0].into()));
+        assert_eq!(em.abnormals.get(1), Some(&values[1].into()));
     }
 
     #[test]
-    fn test_em_builder_one_build_abnormal_from_tuples() {
-        let mut em = EmBuilderOne::new();
-        assert!(em.abnormals.is_empty());
+    fn test_em_builder_build_abnormal_from_tuples() {
         let values = vec![
             (0.0, 1.0, 0.5),
             (1.0, 2.0, 0.5)];
-        let result = em.build_abnormal_from_tuples(&values);
-        assert!(result.is_ok());
-        assert_eq!(em.abnormals.get(0), Some(&NormalParams::from_tuple(values[0]).unwrap()));
-        assert_eq!(em.abnormals.get(1), Some(&NormalParams::from_tuple(values[1]).unwrap()));
+        let em = EmBuilder::new().build_abnormal_from_tuples(&values).unwrap();
+        assert_eq!(em.abnormals.get(0), Some(&NormalParams::from_tuple(values[0]).unwrap().into()));
+        assert_eq!(em.abnormals.get(1), Some(&NormalParams::from_tuple(values[1]).unwrap().into()));
+        assert!(EmBuilder::new().build_abnormal_from_tuples(&[(0.0, -1.0, 0.5)]).is_err());
     }
 
     #[test]
-    fn test_em_builder_one_build_epochs() {
-        let mut em = EmBuilderOne::new();
-        let result = em.build_epochs(10);
-        assert!(result.is_ok());
+    fn test_em_builder_rejects_prior_on_non_normal_component() {
+        let prior = NormalInverseGamma { alpha: 2.0, beta: 2.0, mu: 0.0, kappa: 1.0 };
+        let laplace: Component = Laplace::new(5.0, 1.0, 0.2).unwrap().into();
+        let result = EmBuilder::new()
+            .build_abnormal_components(&[laplace]).unwrap()
+            .build_component_prior(1, prior);
+        assert!(matches!(result, Err(BuildError::BadPrior(PriorError::NotNormal(1)))));
+        // swapping a prior's normal component for another family is rejected too
+        let result = EmBuilder::new()
+            .build_component_prior(0, prior).unwrap()
+            .build_normal_component(laplace);
+        assert!(matches!(result, Err(BuildError::BadPrior(PriorError::NotNormal(0)))));
+        assert!(EmBuilder::new().build_component_prior(0, prior).is_ok());
+    }
+
+    #[test]
+    fn test_em_builder_build_epochs() {
+        let em = EmBuilder::new().build_epochs(10).unwrap();
         assert_eq!(em.epochs, PositiveInteger::new(10).unwrap());
         // Failing case
         let result = em.build_epochs(0);
@@ -342,100 +686,98 @@ mod tests {
 
     #[test]
     fn test_build_samples_from_slice() {
-        let mut em = EmBuilderOne::new();
         let samples = vec![0.0, 2.0, -1.0];
-        em.build_samples_from_slice(&samples);
-        let Complete(new_samples) = em.sample_arr else { panic!("Sample array not initialized") };
-        assert_eq!(new_samples, Array1::from_vec(vec![0.0, 2.0, -1.0, 0.0]));
+        let em = EmBuilder::new().build_samples_from_slice(&samples);
+        assert_eq!(em.samples.0, Array1::from_vec(vec![0.0, 2.0, -1.0, 0.0]));
+        // samples can be replaced until the likelihoods are built
+        let em = em.build_epochs(2).unwrap().build_samples_from_slice(&[1.0]);
+        assert_eq!(em.samples.0, Array1::from_vec(vec![1.0, 0.0]));
     }
 
     #[test]
-    fn test_em_builder_one_next_builder() {
-        let mut em = EmBuilderOne::new();
-        let result = em.next_builder();
-        assert!(result.is_err());
-        let samples = vec![0.0, 2.0, -1.0];
-        em.build_samples_from_slice(&samples);
-        let result = em.next_builder();
-        assert!(result.is_ok());
-        let new_samples = result.unwrap().sample_arr;
-        assert_eq!(new_samples, Array1::from_vec(vec![0.0, 2.0, -1.0, 0.0]));
+    fn test_em_builder_build_initialization() {
+        let mut samples: Vec<f64> = (0..40).map(|idx| f64::from(idx % 4)).collect();
+        samples.extend([20.0, 21.0, 22.0, 21.0]);
+        let em = EmBuilder::new().build_samples_from_slice(&samples);
+        let result = em.clone().build_initialization(1, InitStrategy::KMeansPlusPlus, 0, Some(1));
+        assert!(matches!(result, Err(BuildError::BadInitialization(InitError::NoRestarts))));
+        let em = em.build_initialization(1, InitStrategy::KMeansPlusPlus, 3, Some(1)).unwrap();
+        assert_eq!(em.abnormals.len(), 1);
+        assert!((em.normal.mean() - 1.5).abs() < 1e-6);
+        assert!((em.abnormals[0].mean() - 21.0).abs() < 1e-6);
+        // the same seed picks the same components
+        let (normal, abnormals) = (em.normal, em.abnormals.clone());
+        let em = em.build_initialization(1, InitStrategy::KMeansPlusPlus, 3, Some(1)).unwrap();
+        assert_eq!((em.normal, em.abnormals.clone()), (normal, abnormals));
     }
 
-    // EmBuilderTwo tests
-
-    fn make_em_builder_two() -> EmBuilderTwo<f64> {
-        let mut em = EmBuilderOne::new();
+    #[test]
+    fn test_em_builder_build_selected_components() {
+        let mut samples: Vec<f64> = (0..60).map(|idx| f64::from(idx % 5) - 2.0).collect();
+        samples.extend((0..20).map(|idx| 15.0 + f64::from(idx % 3)));
+        let em = EmBuilder::new().build_epochs(10).unwrap().build_samples_from_slice(&samples);
+        let result = em.clone().build_selected_components(&[], Criterion::Bic, InitStrategy::Quantiles, 1, None);
+        assert!(matches!(result, Err(BuildError::BadInitialization(InitError::NoCandidates))));
+        let (em, scores) = em.build_selected_components(&[0, 1], Criterion::Bic, InitStrategy::Quantiles, 1, None).unwrap();
+        assert_eq!(scores.iter().map(|score| score.abnormal_count).collect::<Vec<_>>(), vec![0, 1]);
+        assert!(scores[1].bic < scores[0].bic);
+        assert_eq!(em.abnormals.len(), 1);
+        assert!((em.abnormals[0].mean() - 15.95).abs() < 1e-6, "{:?}", em.abnormals);
+    }
+
+    fn make_em_builder() -> EmBuilder<WithSamples, WithLikelihoods, NoChecker> {
         let samples = vec![0.0, 2.0, -1.0];
-        em.build_samples_from_slice(&samples);
-        let result = em.next_builder();
-        assert!(result.is_ok());
-        result.unwrap()
+        EmBuilder::new().build_samples_from_slice(&samples).build_likelihoods()
     }
 
     #[test]
-    fn test_em_builder_two_build_likelihoods() {
-        let mut em = make_em_builder_two();
-        em.build_likelihoods();
-        let Complete(new_likelihoods) = em.likelihoods_arr else { panic!("Likelihoods array not initialized") };
-        assert_eq!(new_likelihoods.shape(), &[1, 4]);
+    fn test_em_builder_build_likelihoods() {
+        let em = make_em_builder();
+        assert_eq!(em.get_model().likelihoods.shape(), &[1, 4]);
     }
 
     #[test]
-    fn test_em_builder_two_next_builder() {
-        let mut em = make_em_builder_two();
-        let result = em.next_builder();
-        assert!(result.is_err());
-        if let Err(e) = result {
-            println!("{:?}", e);
-        }
-        em.build_likelihoods();
-        let result = em.next_builder();
-        assert!(result.is_ok());
-        let new_likelihoods = result.unwrap().likelihoods_arr;
-        assert_eq!(new_likelihoods.shape(), &[1, 4]);
-    }
-
-    // EmBuilderLast tests
-
-    fn make_em_builder_last() -> EmBuilderLast<f64> {
-        let mut em = EmBuilderOne::new();
-        let samples = vec![0.0, 2.0, -1.0];
-        em.build_samples_from_slice(&samples);
-        let result = em.next_builder();
-        assert!(result.is_ok());
-        result.unwrap().build_likelihoods().next_builder().unwrap()
-    }
-
-    #[test]
-    fn test_get_standard_model() {
-        let em = make_em_builder_last();
-        let standard_model = em.get_standard_model();
+    fn test_get_model() {
+        let em = make_em_builder();
+        let standard_model = em.get_model();
         assert_eq!(standard_model.normal, em.normal);
         assert_eq!(standard_model.abnormals, em.abnormals);
-        assert_eq!(standard_model.samples, em.sample_arr);
-        assert_eq!(standard_model.likelihoods, em.likelihoods_arr);
+        assert_eq!(standard_model.samples, em.samples.0);
+        assert_eq!(standard_model.likelihoods, Array2::<f64>::zeros((1, 4)));
         assert_eq!(standard_model.epochs, em.epochs);
     }
 
     #[test]
     fn test_get_early_stop_model() {
-        let mut em = make_em_builder_last();
-        em.build_likelihood_converge_checker();
-        let early_stop_model = em.get_early_stop_model();
-        let EarlyStopEmModel { em_model, converge_checker } = early_stop_model;
+        let em = make_em_builder().build_likelihood_converge_checker();
+        let EarlyStopEmModel { em_model, converge_checker } = em.get_model();
         assert_eq!(em_model.normal, em.normal);
         assert_eq!(em_model.abnormals, em.abnormals);
-        assert_eq!(em_model.samples, em.sample_arr);
-        assert_eq!(em_model.likelihoods, em.likelihoods_arr);
+        assert_eq!(em_model.samples, em.samples.0);
+        assert_eq!(em_model.likelihoods, Array2::<f64>::zeros((1, 4)));
         assert_eq!(em_model.epochs, em.epochs);
-        assert_eq!(converge_checker.prev_likelihood.shape(), em.likelihoods_arr.shape());
+        assert_eq!(converge_checker.prev_likelihood.shape(), em_model.likelihoods.shape());
+        // any checker can be built, and the standard model is still available
+        let em = make_em_builder().build_converge_checker(ParameterChecker::default());
+        assert_eq!(em.get_model().em_model.samples, em.get_standard_model().samples);
+    }
+
+    #[test]
+    fn test_generate_samples() {
+        let params = [(0.0, 1.0, 0.7), (50.0, 2.0, 0.3)];
+        let samples = generate_samples(&params, &[7, 3], None).unwrap();
+        assert_eq!(samples.len(), 10);
+        assert!(samples[7..].iter().all(|&sample| sample > 25.0));
+        assert!(generate_samples(&[(0.0, -1.0, 1.0)], &[5], None).is_err());
     }
 
     #[test]
-    #[should_panic(expected = "Converge checker not initialized")]
-    fn test_get_early_stop_model_panics_without_converge_checker() {
-        let em = make_em_builder_last();
-        em.get_early_stop_model();
+    fn test_generate_samples_seeded() {
+        let params = [(0.0, 1.0, 0.7), (50.0, 2.0, 0.3)];
+        let first = generate_samples(&params, &[7, 3], Some(42)).unwrap();
+        let second = generate_samples(&params, &[7, 3], Some(42)).unwrap();
+        let other = generate_samples(&params, &[7, 3], Some(43)).unwrap();
+        assert_eq!(first, second);
+        assert_ne!(first, other);
     }
 }
@@ -0,0 +1,442 @@
+use super::component::{Fit, MixtureComponent};
+use super::diagnostics::{EmDiagnostics, EmEvent};
+use super::hmm_builder::HmmBuilder;
+use super::normal_params::{NormalParams, NormalParamsError};
+use super::pos_int::PositiveInteger;
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, pymethods, PyErr};
+use std::fmt;
+use std::iter::zip;
+
+// rows of a transition matrix may be off by rounding when given from Python
+const ROW_SUM_TOLERANCE: f64 = 1e-6;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HmmError {
+    NoStates,
+    DimensionMismatch { expected: usize, got: usize },
+    BadTransitions,
+    BadSelfTransition(f64),
+}
+
+impl fmt::Display for HmmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HmmError::NoStates => write!(f, "Hidden Markov Model needs at least one state."),
+            HmmError::DimensionMismatch { expected, got } => {
+                write!(f, "Expected {} by {} transition matrix, got {} rows or columns.", expected, expected, got)
+            }
+            HmmError::BadTransitions => {
+                write!(f, "Transition probabilities must be non-negative and every row must sum to 1.")
+            }
+            HmmError::BadSelfTransition(value) => {
+                write!(f, "Self transition probability must be between 0 and 1, got {}.", value)
+            }
+        }
+    }
+}
+
+impl From<HmmError> for PyErr {
+    fn from(err: HmmError) -> PyErr {
+        PyValueError::new_err(format!("{}", err))
+    }
+}
+
+/// Check that rows form a states by states stochastic matrix and return it with exactly
+/// normalized rows.
+pub fn transitions_from_rows(rows: &[Vec<f64>], states: usize) -> Result<Array2<f64>, HmmError> {
+    if rows.len() != states {
+        return Err(HmmError::DimensionMismatch { expected: states, got: rows.len() });
+    }
+    if let Some(row) = rows.iter().find(|row| row.len() != states) {
+        return Err(HmmError::DimensionMismatch { expected: states, got: row.len() });
+    }
+    let mut matrix = Array2::from_shape_fn((states, states), |(row, col)| rows[row][col]);
+    for mut row in matrix.rows_mut() {
+        let sum = row.sum();
+        if row.iter().any(|value| !(value.is_finite() && *value >= 0.0)) || (sum - 1.0).abs() > ROW_SUM_TOLERANCE {
+            return Err(HmmError::BadTransitions);
+        }
+        row /= sum;
+    }
+    Ok(matrix)
+}
+
+/// Return transition matrix that stays in every state with probability stay and moves to
+/// each other state with equal probability.
+pub fn sticky_transitions(states: usize, stay: f64) -> Result<Array2<f64>, HmmError> {
+    if !(0.0..=1.0).contains(&stay) {
+        return Err(HmmError::BadSelfTransition(stay));
+    }
+    if states == 0 {
+        return Err(HmmError::NoStates);
+    }
+    if states == 1 {
+        return Ok(Array2::ones((1, 1)));
+    }
+    let leave = (1.0 - stay) / (states - 1) as f64;
+    Ok(Array2::from_shape_fn((states, states), |(row, col)| if row == col { stay } else { leave }))
+}
+
+/// Hidden Markov Model with normal emissions, the first state being the normal regime.
+///
+/// Transitions between regimes are learned with Baum-Welch on the training samples, so a
+/// single unusual point does not flip the regime the way an independent mixture would.
+/// The weight of each state is its initial probability, re-estimated as the average state
+/// occupancy over the training samples.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct HmmModel {
+    pub(super) states: Vec<NormalParams>,
+    // row is the current state, column the next state
+    pub(super) transitions: Array2<f64>,
+    pub(super) samples: Array1<f64>,
+    // state distribution given every point passed to update
+    pub(super) filtered: Array1<f64>,
+    pub(super) epochs: PositiveInteger,
+}
+
+/// Scaled forward pass. Emissions are scaled per point so their largest entry is one.
+struct Forward {
+    alpha: Array2<f64>,
+    emissions: Array2<f64>,
+    scales: Vec<f64>,
+    log_likelihood: f64,
+}
+
+#[pymethods]
+impl HmmModel {
+    /// Advance the forward filter with given point and return probability of the normal state.
+    pub fn update(&mut self, point: f64) -> f64 {
+        self.filtered = self.filter_step(self.filtered.view(), point);
+        self.filtered[0]
+    }
+
+    /// Return probability of the normal state if point were the next observation,
+    /// without advancing the filter.
+    pub fn predict(&self, point: f64) -> f64 {
+        self.filter_step(self.filtered.view(), point)[0]
+    }
+
+    /// Return current state distribution, normal state first.
+    pub fn filtered(&self) -> Vec<f64> {
+        self.filtered.to_vec()
+    }
+
+    /// Restart filtering from the initial state distribution.
+    pub fn reset_filter(&mut self) {
+        self.filtered = self.initial();
+    }
+
+    /// Run Baum-Welch on the training samples for every epoch and restart filtering.
+    ///
+    /// States that explain no samples or whose variance vanishes keep their parameters and
+    /// are reported as collapsed.
+    pub fn train(&mut self) -> Result<EmDiagnostics, NormalParamsError> {
+        let mut diagnostics = EmDiagnostics::default();
+        for _ in 0..self.epochs.value() {
+            let before = self.parameter_vector();
+            let forward = self.forward(self.samples.view());
+            diagnostics.record_expectation(forward.log_likelihood);
+            self.baum_welch_step(&forward, &mut diagnostics.events)?;
+            let delta = zip(before, self.parameter_vector())
+                .map(|(prev, curr)| (prev - curr).abs())
+                .fold(0.0, f64::max);
+            diagnostics.record_maximization(delta);
+        }
+        self.reset_filter();
+        Ok(diagnostics)
+    }
+
+    /// Return most likely state of every point, normal state being 0.
+    pub fn viterbi(&self, points: Vec<f64>) -> Vec<usize> {
+        let Some(&first) = points.first() else {
+            return Vec::new();
+        };
+        let log_transitions = self.transitions.mapv(f64::ln);
+        let mut scores: Array1<f64> = &self.initial().mapv(f64::ln) + &self.log_emissions(first);
+        let mut back_pointers: Vec<Vec<usize>> = Vec::with_capacity(points.len());
+        for &point in &points[1..] {
+            let emissions = self.log_emissions(point);
+            let (next, pointers): (Vec<f64>, Vec<usize>) = (0..self.states.len())
+                .map(|state| {
+                    let (best, score) = scores
+                        .iter()
+                        .zip(log_transitions.column(state))
+                        .map(|(score, transition)| score + transition)
+                        .enumerate()
+                        .fold((0, f64::NEG_INFINITY), |best, (idx, score)| if score > best.1 { (idx, score) } else { best });
+                    (score + emissions[state], best)
+                })
+                .unzip();
+            scores = Array1::from(next);
+            back_pointers.push(pointers);
+        }
+        let mut state = scores
+            .iter()
+            .enumerate()
+            .fold((0, f64::NEG_INFINITY), |best, (idx, &score)| if score > best.1 { (idx, score) } else { best })
+            .0;
+        let mut path = vec![state];
+        for pointers in back_pointers.iter().rev() {
+            state = pointers[state];
+            path.push(state);
+        }
+        path.reverse();
+        path
+    }
+
+    /// Return log-likelihood of the points under the model.
+    pub fn log_likelihood(&self, points: Vec<f64>) -> f64 {
+        self.forward(ArrayView1::from(&points)).log_likelihood
+    }
+
+    /// Return (mean, standard deviation, initial probability) of every state, normal first.
+    pub fn parameters(&self) -> Vec<(f64, f64, f64)> {
+        self.states.iter().map(|state| state.parameters()).collect()
+    }
+
+    /// Return rows of the transition matrix, row being the current state.
+    pub fn transitions(&self) -> Vec<Vec<f64>> {
+        self.transitions.rows().into_iter().map(|row| row.to_vec()).collect()
+    }
+
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+}
+
+impl HmmModel {
+    /// Create model from states, normal first, a stochastic transition matrix and training samples.
+    ///
+    /// # Errors
+    ///
+    /// If there are no states or the transition matrix does not match them.
+    pub fn new(
+        states: Vec<NormalParams>,
+        transitions: Array2<f64>,
+        samples: Array1<f64>,
+        epochs: PositiveInteger,
+    ) -> Result<Self, HmmError> {
+        if states.is_empty() {
+            return Err(HmmError::NoStates);
+        }
+        let rows: Vec<Vec<f64>> = transitions.rows().into_iter().map(|row| row.to_vec()).collect();
+        let transitions = transitions_from_rows(&rows, states.len())?;
+        let mut model = Self { states, transitions, samples, filtered: Array1::zeros(0), epochs };
+        model.reset_filter();
+        Ok(model)
+    }
+
+    pub fn builder() -> HmmBuilder {
+        HmmBuilder::new()
+    }
+
+    pub fn states(&self) -> &[NormalParams] {
+        &self.states
+    }
+
+    pub fn transition_matrix(&self) -> &Array2<f64> {
+        &self.transitions
+    }
+
+    pub fn samples(&self) -> &Array1<f64> {
+        &self.samples
+    }
+
+    /// Initial state distribution from the state weights; uniform if every weight is zero.
+    fn initial(&self) -> Array1<f64> {
+        let weights: Array1<f64> = self.states.iter().map(MixtureComponent::weight).collect();
+        let total = weights.sum();
+        if total.is_normal() {
+            weights / total
+        } else {
+            Array1::from_elem(self.states.len(), (self.states.len() as f64).recip())
+        }
+    }
+
+    fn log_emissions(&self, point: f64) -> Array1<f64> {
+        self.states.iter().map(|state| state.log_density(point)).collect()
+    }
+
+    fn filter_step(&self, prior: ArrayView1<f64>, point: f64) -> Array1<f64> {
+        let predicted = prior.dot(&self.transitions);
+        let log_emissions = self.log_emissions(point);
+        let max = log_emissions.fold(f64::NEG_INFINITY, |acc, &value| acc.max(value));
+        if !max.is_finite() {
+            return predicted;
+        }
+        let posterior = &predicted * &log_emissions.mapv(|value| (value - max).exp());
+        let norm = posterior.sum();
+        // the point is impossible in every state we can be in, so it tells us nothing
+        if norm.is_normal() { posterior / norm } else { predicted }
+    }
+
+    fn forward(&self, points: ArrayView1<f64>) -> Forward {
+        let (length, states) = (points.len(), self.states.len());
+        let mut alpha = Array2::zeros((length, states));
+        let mut emissions = Array2::zeros((length, states));
+        let mut scales = Vec::with_capacity(length);
+        let mut log_likelihood = 0.0;
+        for (t, &point) in points.iter().enumerate() {
+            let log_emissions = self.log_emissions(point);
+            let max = log_emissions.fold(f64::NEG_INFINITY, |acc, &value| acc.max(value));
+            let scaled = if max.is_finite() { log_emissions.mapv(|value| (value - max).exp()) } else { Array1::ones(states) };
+            let predicted = if t == 0 { self.initial() } else { alpha.row(t - 1).dot(&self.transitions) };
+            let mut current = &predicted * &scaled;
+            let mut scale = current.sum();
+            if scale.is_normal() && max.is_finite() {
+                current /= scale;
+                log_likelihood += scale.ln() + max;
+            } else {
+                current = predicted;
+                scale = 1.0;
+                log_likelihood = f64::NEG_INFINITY;
+            }
+            alpha.row_mut(t).assign(&current);
+            emissions.row_mut(t).assign(&scaled);
+            scales.push(scale);
+        }
+        Forward { alpha, emissions, scales, log_likelihood }
+    }
+
+    fn baum_welch_step(&mut self, forward: &Forward, events: &mut Vec<EmEvent>) -> Result<(), NormalParamsError> {
+        let Forward { alpha, emissions, scales, .. } = forward;
+        let (length, states) = alpha.dim();
+        if length == 0 {
+            return Ok(());
+        }
+        let mut beta = Array2::<f64>::ones((length, states));
+        let mut transition_counts = Array2::<f64>::zeros((states, states));
+        for t in (0..length - 1).rev() {
+            let ahead = &emissions.row(t + 1) * &beta.row(t + 1);
+            let next = self.transitions.dot(&ahead) / scales[t + 1];
+            beta.row_mut(t).assign(&next);
+            let outer = alpha.row(t).insert_axis(Axis(1)).dot(&ahead.insert_axis(Axis(0)));
+            transition_counts += &(&outer * &self.transitions / scales[t + 1]);
+        }
+        let mut occupancy = alpha * &beta;
+        for mut row in occupancy.rows_mut() {
+            let norm = row.sum();
+            if norm.is_normal() {
+                row /= norm;
+            }
+        }
+        for (mut row, counts) in zip(self.transitions.rows_mut(), transition_counts.rows()) {
+            let total = counts.sum();
+            // a state never left keeps its transitions
+            if total.is_normal() {
+                row.assign(&(&counts / total));
+            }
+        }
+        for (state, (params, responsibilities)) in zip(&mut self.states, occupancy.columns()).enumerate() {
+            if let Fit::Degenerate = params.fit(self.samples.view(), responsibilities, 0.0) {
+                events.push(EmEvent::Collapsed { component: state });
+            }
+            params.set_weight(responsibilities.sum() / length as f64)?;
+        }
+        Ok(())
+    }
+
+    /// Means, standard deviations, weights and transition probabilities in one vector.
+    fn parameter_vector(&self) -> Vec<f64> {
+        self.states
+            .iter()
+            .flat_map(|state| {
+                let (mean, stddev, weight) = state.parameters();
+                [mean, stddev, weight]
+            })
+            .chain(self.transitions.iter().copied())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expect_max::normal::Normal;
+    use itertools::Itertools;
+    use ndarray::array;
+
+    fn make_model(samples: Vec<f64>) -> HmmModel {
+        let normal = NormalParams::new(Normal::new(0.0, 1.0).unwrap(), 0.5).unwrap();
+        let abnormal = NormalParams::new(Normal::new(5.0, 1.0).unwrap(), 0.5).unwrap();
+        let transitions = array![[0.9, 0.1], [0.2, 0.8]];
+        HmmModel::new(vec![normal, abnormal], transitions, Array1::from(samples), PositiveInteger::new(1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_log_likelihood_matches_path_sum() {
+        let model = make_model(Vec::new());
+        let points = [0.3, 4.0, 1.0];
+        let initial = model.initial();
+        let total: f64 = (0..points.len())
+            .map(|_| 0..2)
+            .multi_cartesian_product()
+            .map(|path| {
+                let mut prob = initial[path[0]];
+                for (t, &state) in path.iter().enumerate() {
+                    if t > 0 {
+                        prob *= model.transitions[[path[t - 1], state]];
+                    }
+                    prob *= model.states[state].log_density(points[t]).exp();
+                }
+                prob
+            })
+            .sum();
+        assert!((model.log_likelihood(points.to_vec()) - total.ln()).abs() < 1e-12);
+        assert_eq!(model.log_likelihood(Vec::new()), 0.0);
+    }
+
+    #[test]
+    fn test_viterbi_and_filter() {
+        let mut model = make_model(Vec::new());
+        let points = vec![0.1, -0.4, 5.2, 4.8, 5.1, 0.2];
+        assert_eq!(model.viterbi(points.clone()), vec![0, 0, 1, 1, 1, 0]);
+        assert!(model.viterbi(Vec::new()).is_empty());
+        // a borderline point after a normal run stays normal, unlike the independent mixture
+        for &point in &points[..2] {
+            model.update(point);
+        }
+        let filtered = model.predict(2.6);
+        let mixture = 0.5 * Normal::new(0.0, 1.0).unwrap().phi(2.6)
+            / (0.5 * Normal::new(0.0, 1.0).unwrap().phi(2.6) + 0.5 * Normal::new(5.0, 1.0).unwrap().phi(2.6));
+        assert!(filtered > 0.5 && mixture < 0.5, "filtered: {}, mixture: {}", filtered, mixture);
+        assert!((model.filtered().iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        model.reset_filter();
+        assert_eq!(model.filtered(), vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_train() {
+        let mut samples = vec![0.0, 0.5, -0.5, 1.0, -1.0, 0.2, -0.2];
+        samples.extend([6.0, 6.5, 5.5, 6.2]);
+        samples.extend([0.1, -0.1, 0.4]);
+        let mut model = make_model(samples);
+        model.epochs = PositiveInteger::new(20).unwrap();
+        let diagnostics = model.train().unwrap();
+        assert_eq!(diagnostics.epochs, 20);
+        assert!(diagnostics.log_likelihoods.windows(2).all(|pair| pair[1] >= pair[0] - 1e-9));
+        assert!(diagnostics.events.is_empty());
+        let parameters = model.parameters();
+        assert!((parameters[1].0 - 6.05).abs() < 1e-3, "{:?}", parameters);
+        assert!((parameters[0].2 - 10.0 / 14.0).abs() < 1e-3, "{:?}", parameters);
+        // one switch into and one out of the abnormal regime
+        let transitions = model.transitions();
+        assert!((transitions[1][0] - 0.25).abs() < 1e-2, "{:?}", transitions);
+        assert!(transitions.iter().all(|row| (row.iter().sum::<f64>() - 1.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_transitions() {
+        assert_eq!(sticky_transitions(3, 0.5).unwrap()[[0, 2]], 0.25);
+        assert_eq!(sticky_transitions(1, 0.8).unwrap(), array![[1.0]]);
+        assert_eq!(sticky_transitions(2, 1.5), Err(HmmError::BadSelfTransition(1.5)));
+        let rows = vec![vec![0.5, 0.5], vec![1.0, 0.0]];
+        assert_eq!(transitions_from_rows(&rows, 2).unwrap(), array![[0.5, 0.5], [1.0, 0.0]]);
+        assert_eq!(transitions_from_rows(&rows, 3), Err(HmmError::DimensionMismatch { expected: 3, got: 2 }));
+        let rows = vec![vec![0.5, 0.6], vec![1.0, 0.0]];
+        assert_eq!(transitions_from_rows(&rows, 2), Err(HmmError::BadTransitions));
+    }
+}
@@ -0,0 +1,150 @@
+use super::em_model_builder::BuildError;
+use super::hmm::{sticky_transitions, transitions_from_rows, HmmError, HmmModel};
+use super::normal_params::NormalParams;
+use super::pos_int::PositiveInteger;
+use ndarray::Array1;
+
+/// Builder for [`HmmModel`]. States default to a standard normal regime with no abnormal
+/// regimes, and transitions default to staying in the current regime with probability 0.9.
+#[derive(Debug)]
+pub struct HmmBuilder {
+    normal: NormalParams,
+    abnormals: Vec<NormalParams>,
+    samples: Array1<f64>,
+    epochs: PositiveInteger,
+    transitions: Option<Vec<Vec<f64>>>,
+    self_transition: f64,
+}
+
+impl Default for HmmBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HmmBuilder {
+    pub fn new() -> Self {
+        Self {
+            normal: NormalParams::from_tuple((0.0, 1.0, 1.0)).expect("The default value used should never fail"),
+            abnormals: Vec::new(),
+            samples: Array1::zeros(0),
+            epochs: PositiveInteger::new(1).expect("The default value used should never fail"),
+            transitions: None,
+            self_transition: 0.9,
+        }
+    }
+
+    /// Set the normal state from its mean, standard deviation and initial probability.
+    ///
+    /// # Errors
+    ///
+    /// If the parameters do not form a valid normal distribution or prob is not a probability.
+    pub fn build_normal(&mut self, mean: f64, stddev: f64, prob: f64) -> Result<&mut Self, BuildError<()>> {
+        self.normal = NormalParams::from_tuple((mean, stddev, prob))?;
+        Ok(self)
+    }
+
+    pub fn build_abnormal(&mut self, abnormals: &[NormalParams]) -> &mut Self {
+        abnormals.clone_into(&mut self.abnormals);
+        self
+    }
+
+    /// Append abnormal states given as (mean, standard deviation, initial probability).
+    ///
+    /// # Errors
+    ///
+    /// If any state is invalid; states before it are kept.
+    pub fn build_abnormal_from_tuples(&mut self, abnormals: &[(f64, f64, f64)]) -> Result<&mut Self, BuildError<()>> {
+        for &abnormal in abnormals {
+            self.abnormals.push(NormalParams::from_tuple(abnormal)?);
+        }
+        Ok(self)
+    }
+
+    /// Set the samples Baum-Welch trains on, in the order they were observed.
+    pub fn build_samples_from_slice(&mut self, samples: &[f64]) -> &mut Self {
+        self.samples = Array1::from(samples.to_vec());
+        self
+    }
+
+    /// Set the number of Baum-Welch epochs to run.
+    ///
+    /// # Errors
+    ///
+    /// If epochs is 0.
+    pub fn build_epochs(&mut self, epochs: u32) -> Result<&mut Self, BuildError<()>> {
+        self.epochs.set(epochs)?;
+        Ok(self)
+    }
+
+    /// Set the rows of the transition matrix, normal state first. Overrides the self
+    /// transition probability.
+    ///
+    /// # Errors
+    ///
+    /// If any probability is negative or a row does not sum to 1. The number of rows is
+    /// checked against the states when the model is built.
+    pub fn build_transitions(&mut self, rows: &[Vec<f64>]) -> Result<&mut Self, BuildError<()>> {
+        transitions_from_rows(rows, rows.len())?;
+        self.transitions = Some(rows.to_vec());
+        Ok(self)
+    }
+
+    /// Set the probability of staying in the same state, used when no transition matrix is given.
+    ///
+    /// # Errors
+    ///
+    /// If stay is not between 0 and 1.
+    pub fn build_self_transition(&mut self, stay: f64) -> Result<&mut Self, BuildError<()>> {
+        if !(0.0..=1.0).contains(&stay) {
+            return Err(HmmError::BadSelfTransition(stay).into());
+        }
+        self.self_transition = stay;
+        Ok(self)
+    }
+
+    /// Return the untrained model.
+    ///
+    /// # Errors
+    ///
+    /// If the transition matrix does not match the number of states.
+    pub fn get_model(&self) -> Result<HmmModel, BuildError<()>> {
+        let states: Vec<NormalParams> = std::iter::once(self.normal).chain(self.abnormals.iter().copied()).collect();
+        let transitions = match &self.transitions {
+            Some(rows) => transitions_from_rows(rows, states.len())?,
+            None => sticky_transitions(states.len(), self.self_transition)?,
+        };
+        Ok(HmmModel::new(states, transitions, self.samples.clone(), self.epochs)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_builder() {
+        let mut builder = HmmBuilder::new();
+        builder
+            .build_normal(0.0, 1.0, 0.8)
+            .unwrap()
+            .build_abnormal_from_tuples(&[(5.0, 1.0, 0.2)])
+            .unwrap()
+            .build_self_transition(0.75)
+            .unwrap()
+            .build_samples_from_slice(&[0.0, 1.0])
+            .build_epochs(3)
+            .unwrap();
+        let model = builder.get_model().unwrap();
+        assert_eq!(model.transition_matrix(), array![[0.75, 0.25], [0.25, 0.75]]);
+        assert_eq!(model.parameters(), vec![(0.0, 1.0, 0.8), (5.0, 1.0, 0.2)]);
+        assert_eq!(model.samples(), array![0.0, 1.0]);
+        assert!(builder.build_self_transition(-0.1).is_err());
+        builder.build_transitions(&[vec![1.0]]).unwrap();
+        assert!(builder.get_model().is_err());
+        assert!(builder.build_transitions(&[vec![0.5, 0.4], vec![0.5, 0.5]]).is_err());
+        builder.build_transitions(&[vec![0.5, 0.5], vec![0.0, 1.0]]).unwrap();
+        assert_eq!(builder.get_model().unwrap().transitions(), vec![vec![0.5, 0.5], vec![0.0, 1.0]]);
+    }
+}
@@ -0,0 +1,224 @@
+use super::normal::Normal;
+use super::normal_params::NormalParams;
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, PyErr};
+use rand::distr::weighted::WeightedIndex;
+use rand::distr::Distribution;
+use rand::Rng;
+use std::fmt;
+
+// Lloyd iterations are cheap in one dimension, so this is only a guard against cycling
+const MAX_LLOYD_ITERATIONS: usize = 100;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InitError {
+    TooFewSamples { components: usize, samples: usize },
+    NoRestarts,
+    NoCandidates,
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InitError::TooFewSamples { components, samples } => {
+                write!(f, "Need at least one finite sample per component, got {} samples for {} components.", samples, components)
+            }
+            InitError::NoRestarts => write!(f, "Number of restarts must be positive."),
+            InitError::NoCandidates => write!(f, "Need at least one component count to select from."),
+        }
+    }
+}
+
+impl From<InitError> for PyErr {
+    fn from(err: InitError) -> PyErr {
+        PyValueError::new_err(format!("{}", err))
+    }
+}
+
+/// How initial component parameters are chosen from the training samples.
+#[pyclass(eq, eq_int)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum InitStrategy {
+    /// Draw centers with probability proportional to squared distance from the centers
+    /// already chosen, then refine them with k-means.
+    #[default]
+    KMeansPlusPlus,
+    /// Start k-means from the midpoints of equally sized groups of the sorted samples.
+    /// Deterministic, so restarts have no effect.
+    Quantiles,
+}
+
+/// Draw k-means++ seed centers from the samples.
+pub fn kmeans_plus_plus_centers<R: Rng + ?Sized>(samples: &[f64], count: usize, rng: &mut R) -> Vec<f64> {
+    let mut centers = Vec::with_capacity(count);
+    if samples.is_empty() {
+        return centers;
+    }
+    centers.push(samples[rng.random_range(0..samples.len())]);
+    let mut distances: Vec<f64> = samples.iter().map(|sample| (sample - centers[0]).powi(2)).collect();
+    while centers.len() < count {
+        // all samples coincide with a center, so any sample is as good as another
+        let idx = match WeightedIndex::new(&distances) {
+            Ok(dist) => dist.sample(rng),
+            Err(_) => rng.random_range(0..samples.len()),
+        };
+        let center = samples[idx];
+        centers.push(center);
+        for (distance, sample) in distances.iter_mut().zip(samples) {
+            *distance = distance.min((sample - center).powi(2));
+        }
+    }
+    centers
+}
+
+/// Return midpoints of count equally sized groups of the sorted samples.
+pub fn quantile_centers(samples: &[f64], count: usize) -> Vec<f64> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    if sorted.is_empty() {
+        return Vec::new();
+    }
+    (0..count).map(|idx| sorted[((2 * idx + 1) * sorted.len()) / (2 * count)]).collect()
+}
+
+fn nearest(centers: &[f64], sample: f64) -> usize {
+    centers
+        .iter()
+        .enumerate()
+        .min_by(|(_, left), (_, right)| (sample - **left).abs().total_cmp(&(sample - **right).abs()))
+        .map_or(0, |(idx, _)| idx)
+}
+
+/// Refine centers with k-means and turn each cluster into a component.
+///
+/// The largest cluster becomes the normal component and comes first; abnormal components
+/// follow in order of their means. Clusters with a single point or no spread get a standard
+/// deviation from the spread of all samples, and weights are smoothed so none is zero.
+pub fn components_from_centers(samples: &[f64], mut centers: Vec<f64>) -> Result<Vec<NormalParams>, InitError> {
+    let count = centers.len();
+    let mut assignments: Vec<usize> = samples.iter().map(|&sample| nearest(&centers, sample)).collect();
+    for _ in 0..MAX_LLOYD_ITERATIONS {
+        for (idx, center) in centers.iter_mut().enumerate() {
+            let (sum, size) = samples
+                .iter()
+                .zip(&assignments)
+                .filter(|&(_, &cluster)| cluster == idx)
+                .fold((0.0, 0usize), |(sum, size), (sample, _)| (sum + sample, size + 1));
+            // empty clusters keep their center
+            if size > 0 {
+                *center = sum / size as f64;
+            }
+        }
+        let next: Vec<usize> = samples.iter().map(|&sample| nearest(&centers, sample)).collect();
+        if next == assignments {
+            break;
+        }
+        assignments = next;
+    }
+    let size = samples.len() as f64;
+    let overall_mean = samples.iter().sum::<f64>() / size;
+    let overall_stddev = (samples.iter().map(|sample| (sample - overall_mean).powi(2)).sum::<f64>() / size).sqrt();
+    let fallback = if overall_stddev.is_normal() { overall_stddev / count as f64 } else { 1.0 };
+    let mut clusters: Vec<(usize, f64, f64)> = centers
+        .iter()
+        .enumerate()
+        .map(|(idx, &center)| {
+            let members: Vec<f64> =
+                samples.iter().zip(&assignments).filter(|&(_, &cluster)| cluster == idx).map(|(sample, _)| *sample).collect();
+            let variance = members.iter().map(|sample| (sample - center).powi(2)).sum::<f64>() / members.len().max(1) as f64;
+            let stddev = if members.len() > 1 && variance.is_normal() { variance.sqrt() } else { fallback };
+            (members.len(), center, stddev)
+        })
+        .collect();
+    let normal_idx = clusters
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, (members, _, _))| *members)
+        .map_or(0, |(idx, _)| idx);
+    let normal = clusters.remove(normal_idx);
+    clusters.sort_by(|left, right| left.1.total_cmp(&right.1));
+    std::iter::once(normal)
+        .chain(clusters)
+        .map(|(members, center, stddev)| {
+            let weight = (members + 1) as f64 / (size + count as f64);
+            let dist = Normal::new(center, stddev).map_err(|_| InitError::TooFewSamples { components: count, samples: samples.len() })?;
+            Ok(NormalParams::new(dist, weight).expect("Smoothed weights are between 0 and 1"))
+        })
+        .collect()
+}
+
+/// Choose count components for the samples, normal component first.
+///
+/// # Errors
+///
+/// If count is zero or there are fewer finite samples than components.
+pub fn initial_components<R: Rng + ?Sized>(
+    samples: &[f64],
+    count: usize,
+    strategy: InitStrategy,
+    rng: &mut R,
+) -> Result<Vec<NormalParams>, InitError> {
+    let finite: Vec<f64> = samples.iter().copied().filter(|sample| sample.is_finite()).collect();
+    if count == 0 || finite.len() < count {
+        return Err(InitError::TooFewSamples { components: count, samples: finite.len() });
+    }
+    let centers = match strategy {
+        InitStrategy::KMeansPlusPlus => kmeans_plus_plus_centers(&finite, count, rng),
+        InitStrategy::Quantiles => quantile_centers(&finite, count),
+    };
+    components_from_centers(&finite, centers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn two_clusters() -> Vec<f64> {
+        let mut samples: Vec<f64> = (0..30).map(|idx| f64::from(idx % 5) * 0.1).collect();
+        samples.extend((0..10).map(|idx| 10.0 + f64::from(idx % 2) * 0.2));
+        samples
+    }
+
+    #[test]
+    fn test_quantile_centers() {
+        let samples = [5.0, 1.0, 4.0, 2.0, 3.0, 6.0];
+        assert_eq!(quantile_centers(&samples, 2), vec![2.0, 5.0]);
+        assert_eq!(quantile_centers(&samples, 3), vec![2.0, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn test_kmeans_plus_plus_centers_are_samples() {
+        let samples = two_clusters();
+        let centers = kmeans_plus_plus_centers(&samples, 3, &mut StdRng::seed_from_u64(3));
+        assert_eq!(centers.len(), 3);
+        assert!(centers.iter().all(|center| samples.contains(center)));
+        // all samples equal still gives the requested number of centers
+        let centers = kmeans_plus_plus_centers(&[1.0; 4], 2, &mut StdRng::seed_from_u64(3));
+        assert_eq!(centers, vec![1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_initial_components_orders_normal_first() {
+        let samples = two_clusters();
+        for strategy in [InitStrategy::KMeansPlusPlus, InitStrategy::Quantiles] {
+            let components = initial_components(&samples, 2, strategy, &mut StdRng::seed_from_u64(5)).unwrap();
+            assert!((components[0].mean() - 0.2).abs() < 1e-9, "{:?}", components);
+            assert!((components[1].mean() - 10.1).abs() < 1e-9, "{:?}", components);
+            assert!((components[0].weight() - 31.0 / 42.0).abs() < 1e-12);
+            assert!((components[1].stddev() - 0.1).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_initial_components_errors() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let err = initial_components(&[1.0, f64::NAN], 2, InitStrategy::Quantiles, &mut rng);
+        assert_eq!(err.unwrap_err(), InitError::TooFewSamples { components: 2, samples: 1 });
+        assert!(initial_components(&[1.0], 0, InitStrategy::Quantiles, &mut rng).is_err());
+        // constant samples still give valid components
+        let components = initial_components(&[2.0; 5], 2, InitStrategy::KMeansPlusPlus, &mut rng).unwrap();
+        assert!(components.iter().all(|params| params.stddev() == 1.0));
+    }
+}
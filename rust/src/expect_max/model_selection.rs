@@ -0,0 +1,128 @@
+use super::em_model::EmModel;
+use pyo3::{pyclass, pymethods};
+
+/// Information criterion used to choose the number of components. Lower scores are better.
+#[pyclass(eq, eq_int)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Criterion {
+    /// Akaike information criterion, -2 log-likelihood + 2 parameters.
+    Aic,
+    /// Bayesian information criterion, -2 log-likelihood + parameters * ln(samples).
+    #[default]
+    Bic,
+    /// Integrated completed likelihood, BIC plus twice the entropy of the responsibilities,
+    /// which penalizes components that overlap.
+    Icl,
+}
+
+/// Fit quality of a model with a given number of abnormal components.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectionScore {
+    #[pyo3(get)]
+    pub abnormal_count: usize,
+    /// Log-likelihood of the training samples.
+    #[pyo3(get)]
+    pub log_likelihood: f64,
+    /// Number of free means, standard deviations and weights.
+    #[pyo3(get)]
+    pub parameter_count: usize,
+    #[pyo3(get)]
+    pub aic: f64,
+    #[pyo3(get)]
+    pub bic: f64,
+    #[pyo3(get)]
+    pub icl: f64,
+}
+
+#[pymethods]
+impl SelectionScore {
+    /// Return the score under the given criterion.
+    pub fn score(&self, criterion: Criterion) -> f64 {
+        match criterion {
+            Criterion::Aic => self.aic,
+            Criterion::Bic => self.bic,
+            Criterion::Icl => self.icl,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SelectionScore(abnormal_count={}, log_likelihood={}, aic={}, bic={}, icl={})",
+            self.abnormal_count, self.log_likelihood, self.aic, self.bic, self.icl
+        )
+    }
+}
+
+impl SelectionScore {
+    /// Score model on its own samples, running an expectation step for the responsibilities.
+    pub fn from_model(model: &mut EmModel) -> Self {
+        model.expectation();
+        let log_likelihood = model.log_likelihood();
+        let abnormal_count = model.abnormals().len();
+        let components = abnormal_count + 1;
+        // a location and scale per component, and weights that sum to one
+        let parameter_count = 3 * components - 1;
+        let size = model.sample_count() as f64;
+        let entropy: f64 = model
+            .likelihoods()
+            .iter()
+            .filter(|&&resp| resp > 0.0)
+            .map(|&resp| -resp * resp.ln())
+            .sum();
+        let aic = -2.0 * log_likelihood + 2.0 * parameter_count as f64;
+        let bic = -2.0 * log_likelihood + parameter_count as f64 * size.ln();
+        Self { abnormal_count, log_likelihood, parameter_count, aic, bic, icl: bic + 2.0 * entropy }
+    }
+}
+
+/// Return index of the best score under the criterion, ignoring scores that are not a number.
+pub fn best_score(scores: &[SelectionScore], criterion: Criterion) -> Option<usize> {
+    scores
+        .iter()
+        .enumerate()
+        .filter(|(_, score)| !score.score(criterion).is_nan())
+        .min_by(|(_, left), (_, right)| left.score(criterion).total_cmp(&right.score(criterion)))
+        .map(|(idx, _)| idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expect_max::normal::Normal;
+    use crate::expect_max::normal_params::NormalParams;
+    use crate::expect_max::pos_int::PositiveInteger;
+    use ndarray::Array1;
+
+    #[test]
+    fn test_from_model() {
+        let normal = NormalParams::new(Normal::new(0.0, 1.0).unwrap(), 0.5).unwrap();
+        let abnormal = NormalParams::new(Normal::new(0.0, 1.0).unwrap(), 0.5).unwrap();
+        let samples = Array1::from(vec![-1.0, 0.0, 1.0, 2.0]);
+        let mut model = EmModel::new(normal, [abnormal], samples.clone(), PositiveInteger::new(1).unwrap());
+        let score = SelectionScore::from_model(&mut model);
+        let expected: f64 = samples.iter().map(|&point| Normal::new(0.0, 1.0).unwrap().log_phi(point)).sum();
+        assert!((score.log_likelihood - expected).abs() < 1e-12);
+        assert_eq!(score.parameter_count, 5);
+        assert!((score.aic - (-2.0 * expected + 10.0)).abs() < 1e-12);
+        assert!((score.bic - (-2.0 * expected + 5.0 * 4f64.ln())).abs() < 1e-12);
+        // identical components split every sample evenly
+        assert!((score.icl - score.bic - 2.0 * 4.0 * 2f64.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_best_score() {
+        let make = |abnormal_count, aic, bic| SelectionScore {
+            abnormal_count,
+            log_likelihood: 0.0,
+            parameter_count: 0,
+            aic,
+            bic,
+            icl: f64::NAN,
+        };
+        let scores = vec![make(0, 3.0, 1.0), make(1, 2.0, 4.0)];
+        assert_eq!(best_score(&scores, Criterion::Aic), Some(1));
+        assert_eq!(best_score(&scores, Criterion::Bic), Some(0));
+        assert_eq!(best_score(&scores, Criterion::Icl), None);
+    }
+}
@@ -0,0 +1,187 @@
+use super::em_model_builder::{BuildError, MissingFieldError};
+use super::multivariate_em_model::{CovarianceType, MultivariateEmModel};
+use super::multivariate_normal::{rows_to_array, MultivariateError, MultivariateNormalParams, MultivariateNormalTuple};
+use super::pos_int::PositiveInteger;
+use ndarray::{Array2, Axis};
+
+/// Builder for [`MultivariateEmModel`], analogous to `EmBuilderOne` for a single channel.
+#[derive(Debug)]
+pub struct MultivariateEmBuilder {
+    normal: Option<MultivariateNormalParams>,
+    abnormals: Vec<MultivariateNormalParams>,
+    samples: Option<Array2<f64>>,
+    epochs: PositiveInteger,
+    covariance_type: CovarianceType,
+    regularization: f64,
+}
+
+impl Default for MultivariateEmBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultivariateEmBuilder {
+    pub fn new() -> Self {
+        Self {
+            normal: None,
+            abnormals: Vec::new(),
+            samples: None,
+            epochs: PositiveInteger::new(1).expect("The default value used should never fail"),
+            covariance_type: CovarianceType::default(),
+            regularization: 1e-6,
+        }
+    }
+
+    /// Set the normal component from its mean, covariance rows and weight.
+    ///
+    /// # Errors
+    ///
+    /// If the covariance is not symmetric positive definite, does not match the mean,
+    /// or the weight is not a probability.
+    pub fn build_normal(
+        &mut self,
+        mean: Vec<f64>,
+        covariance: Vec<Vec<f64>>,
+        prob: f64,
+    ) -> Result<&mut Self, BuildError<()>> {
+        self.normal = Some(MultivariateNormalParams::from_tuple((mean, covariance, prob))?);
+        Ok(self)
+    }
+
+    pub fn build_abnormal(&mut self, abnormals: &[MultivariateNormalParams]) -> &mut Self {
+        abnormals.clone_into(&mut self.abnormals);
+        self
+    }
+
+    /// Append abnormal components given as (mean, covariance rows, weight).
+    ///
+    /// # Errors
+    ///
+    /// If any component is invalid; components before it are kept.
+    pub fn build_abnormal_from_tuples(
+        &mut self,
+        abnormals: &[MultivariateNormalTuple],
+    ) -> Result<&mut Self, BuildError<()>> {
+        for abnormal in abnormals {
+            self.abnormals.push(MultivariateNormalParams::from_tuple(abnormal.clone())?);
+        }
+        Ok(self)
+    }
+
+    /// Set the training samples, one row per sample.
+    ///
+    /// A placeholder row is appended for the point given to each update.
+    pub fn build_samples(&mut self, samples: &Array2<f64>) -> &mut Self {
+        let placeholder = Array2::zeros((1, samples.ncols()));
+        let samples = ndarray::concatenate![Axis(0), samples.view(), placeholder];
+        self.samples = Some(samples);
+        self
+    }
+
+    /// Set the training samples from rows of channels.
+    ///
+    /// # Errors
+    ///
+    /// If the rows do not all have the same number of channels.
+    pub fn build_samples_from_rows(&mut self, rows: &[Vec<f64>]) -> Result<&mut Self, BuildError<()>> {
+        let samples = rows_to_array(rows)?;
+        Ok(self.build_samples(&samples))
+    }
+
+    /// Set the number of epochs to run.
+    ///
+    /// # Errors
+    ///
+    /// If epochs is 0.
+    pub fn build_epochs(&mut self, epochs: u32) -> Result<&mut Self, BuildError<()>> {
+        self.epochs.set(epochs)?;
+        Ok(self)
+    }
+
+    pub fn build_covariance_type(&mut self, covariance_type: CovarianceType) -> &mut Self {
+        self.covariance_type = covariance_type;
+        self
+    }
+
+    /// Set the value added to the diagonal of every fitted covariance.
+    ///
+    /// # Errors
+    ///
+    /// If regularization is negative or not finite.
+    pub fn build_regularization(&mut self, regularization: f64) -> Result<&mut Self, BuildError<()>> {
+        if !(regularization.is_finite() && regularization >= 0.0) {
+            return Err(MultivariateError::BadRegularization(regularization).into());
+        }
+        self.regularization = regularization;
+        Ok(self)
+    }
+
+    /// Return the model.
+    ///
+    /// # Errors
+    ///
+    /// If the normal component or samples were never set, or components and samples
+    /// disagree on the number of channels.
+    pub fn get_model(&self) -> Result<MultivariateEmModel, BuildError<()>> {
+        let Some(normal) = &self.normal else {
+            return Err(MissingFieldError { my_struct: (), field: String::from("normal") }.into());
+        };
+        let Some(samples) = &self.samples else {
+            return Err(MissingFieldError { my_struct: (), field: String::from("samples") }.into());
+        };
+        let components = std::iter::once(normal).chain(&self.abnormals).cloned().collect();
+        let model = MultivariateEmModel::new(
+            components,
+            samples.clone(),
+            self.epochs,
+            self.covariance_type,
+            self.regularization,
+        )?;
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_build_model() {
+        let mut builder = MultivariateEmBuilder::new();
+        assert!(matches!(builder.get_model(), Err(BuildError::IncompleteBuildError(_))));
+        builder
+            .build_normal(vec![0.0, 0.0], vec![vec![1.0, 0.0], vec![0.0, 1.0]], 0.9)
+            .unwrap()
+            .build_abnormal_from_tuples(&[(vec![5.0, 5.0], vec![vec![1.0, 0.0], vec![0.0, 1.0]], 0.1)])
+            .unwrap()
+            .build_covariance_type(CovarianceType::Diagonal)
+            .build_epochs(2)
+            .unwrap();
+        assert!(matches!(builder.get_model(), Err(BuildError::IncompleteBuildError(_))));
+        builder.build_samples(&array![[0.0, 1.0], [5.0, 4.0]]);
+        let model = builder.get_model().unwrap();
+        assert_eq!(model.sample_count(), 3);
+        assert_eq!(model.dim(), 2);
+        assert_eq!(model.covariance_type(), CovarianceType::Diagonal);
+        assert_eq!(model.components().len(), 2);
+    }
+
+    #[test]
+    fn test_build_errors() {
+        let mut builder = MultivariateEmBuilder::new();
+        assert!(builder.build_normal(vec![0.0], vec![vec![0.0]], 0.5).is_err());
+        assert!(builder.build_regularization(-1.0).is_err());
+        assert!(builder.build_samples_from_rows(&[vec![1.0, 2.0], vec![1.0]]).is_err());
+        builder
+            .build_normal(vec![0.0], vec![vec![1.0]], 1.0)
+            .unwrap()
+            .build_samples_from_rows(&[vec![1.0, 2.0]])
+            .unwrap();
+        assert!(matches!(
+            builder.get_model(),
+            Err(BuildError::BadMultivariate(MultivariateError::DimensionMismatch { expected: 2, got: 1 }))
+        ));
+    }
+}
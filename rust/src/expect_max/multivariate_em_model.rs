@@ -0,0 +1,347 @@
+use super::diagnostics::{EmDiagnostics, EmEvent};
+use super::em_model::most_probable;
+use super::multivariate_em_builder::MultivariateEmBuilder;
+use super::multivariate_normal::{MultivariateError, MultivariateNormalParams};
+use super::pos_int::PositiveInteger;
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use pyo3::{pyclass, pymethods};
+use std::iter::zip;
+
+/// Shape of the covariance matrices fitted by the maximization step.
+#[pyclass(eq, eq_int)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum CovarianceType {
+    /// Every channel may correlate with every other channel.
+    #[default]
+    Full,
+    /// Channels are independent within a component; off-diagonal entries stay zero.
+    Diagonal,
+}
+
+/// Expectation Maximization over multi-channel samples with multivariate normal components.
+///
+/// The first component is the normal class. Densities are evaluated through the Cholesky
+/// factor of each covariance and responsibilities are normalized in the log domain.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct MultivariateEmModel {
+    pub(super) components: Vec<MultivariateNormalParams>,
+    // one row per sample, one column per channel
+    pub(super) samples: Array2<f64>,
+    pub(super) likelihoods: Array2<f64>,
+    pub(super) epochs: PositiveInteger,
+    pub(super) covariance_type: CovarianceType,
+    // added to the diagonal of every fitted covariance
+    pub(super) regularization: f64,
+}
+
+#[pymethods]
+impl MultivariateEmModel {
+    /// Update model parameters using given multi-channel point, running every epoch.
+    ///
+    /// The point replaces the last sample.
+    pub fn update(&mut self, point: Vec<f64>) -> Result<EmDiagnostics, MultivariateError> {
+        let point = self.check_point(point)?;
+        if let Some(mut last) = self.samples.axis_iter_mut(Axis(0)).last() {
+            last.assign(&point);
+        }
+        let mut diagnostics = EmDiagnostics::default();
+        for _ in 0..self.epochs.value() {
+            diagnostics.record_expectation(self.expectation());
+            let before = self.parameter_vector();
+            self.maximize(&mut diagnostics.events)?;
+            let delta = zip(before, self.parameter_vector())
+                .map(|(prev, curr)| (prev - curr).abs())
+                .fold(0.0, f64::max);
+            diagnostics.record_maximization(delta);
+        }
+        Ok(diagnostics)
+    }
+
+    /// Return posterior probability that the multi-channel point belongs to the normal component.
+    pub fn predict(&self, point: Vec<f64>) -> Result<f64, MultivariateError> {
+        Ok(self.predict_proba(point)?[0])
+    }
+
+    /// Return posterior probability of every component for the multi-channel point, normal first.
+    pub fn predict_proba(&self, point: Vec<f64>) -> Result<Vec<f64>, MultivariateError> {
+        let log_likelihoods = self.point_log_likelihoods(point)?;
+        let log_denom = log_sum_exp(&log_likelihoods);
+        if !log_denom.is_finite() {
+            return Ok(vec![0.0; log_likelihoods.len()]);
+        }
+        Ok(log_likelihoods.iter().map(|value| (value - log_denom).exp()).collect())
+    }
+
+    /// Return most probable component for the multi-channel point with its posterior probability.
+    pub fn classify(&self, point: Vec<f64>) -> Result<(usize, f64), MultivariateError> {
+        Ok(most_probable(&self.predict_proba(point)?))
+    }
+
+    /// Return negative log-likelihood of the multi-channel point under the whole mixture.
+    pub fn novelty_score(&self, point: Vec<f64>) -> Result<f64, MultivariateError> {
+        Ok(-log_sum_exp(&self.point_log_likelihoods(point)?))
+    }
+
+    /// Compute responsibilities of each component for each sample.
+    ///
+    /// Returns log-likelihood of the samples under the parameters used.
+    pub fn expectation(&mut self) -> f64 {
+        for (mut row, params) in zip(self.likelihoods.rows_mut(), &self.components) {
+            for (res, sample) in zip(&mut row, self.samples.rows()) {
+                *res = params.log_likelihood(sample);
+            }
+        }
+        let num_params = self.likelihoods.nrows() as f64;
+        let mut total = 0.0;
+        for mut column in self.likelihoods.columns_mut() {
+            let max = column.fold(f64::NEG_INFINITY, |acc, &value| acc.max(value));
+            if !max.is_finite() {
+                // impossible under every component, so no component is preferred
+                column.fill(num_params.recip());
+                total += max;
+                continue;
+            }
+            column.mapv_inplace(|value| (value - max).exp());
+            let norm = column.sum();
+            column /= norm;
+            total += max + norm.ln();
+        }
+        total
+    }
+
+    /// Return log-likelihood of the samples under the current parameters.
+    pub fn log_likelihood(&self) -> f64 {
+        self.samples
+            .rows()
+            .into_iter()
+            .map(|sample| {
+                let log_likelihoods: Vec<f64> =
+                    self.components.iter().map(|params| params.log_likelihood(sample)).collect();
+                log_sum_exp(&log_likelihoods)
+            })
+            .sum()
+    }
+
+    /// Number of channels in every sample.
+    pub fn dim(&self) -> usize {
+        self.samples.ncols()
+    }
+
+    /// Number of samples the model is fit to.
+    pub fn sample_count(&self) -> usize {
+        self.samples.nrows()
+    }
+}
+
+impl MultivariateEmModel {
+    /// Create model from components, normal first, and samples with one row per sample.
+    ///
+    /// # Errors
+    ///
+    /// If there are no components, the components and samples disagree on the number of
+    /// channels, or regularization is negative.
+    pub fn new(
+        components: Vec<MultivariateNormalParams>,
+        samples: Array2<f64>,
+        epochs: PositiveInteger,
+        covariance_type: CovarianceType,
+        regularization: f64,
+    ) -> Result<Self, MultivariateError> {
+        let dim = samples.ncols();
+        if components.is_empty() {
+            return Err(MultivariateError::DimensionMismatch { expected: dim, got: 0 });
+        }
+        if let Some(params) = components.iter().find(|params| params.dim() != dim) {
+            return Err(MultivariateError::DimensionMismatch { expected: dim, got: params.dim() });
+        }
+        if !(regularization.is_finite() && regularization >= 0.0) {
+            return Err(MultivariateError::BadRegularization(regularization));
+        }
+        let likelihoods = Array2::zeros((components.len(), samples.nrows()));
+        Ok(Self { components, samples, likelihoods, epochs, covariance_type, regularization })
+    }
+
+    pub fn builder() -> MultivariateEmBuilder {
+        MultivariateEmBuilder::new()
+    }
+
+    pub fn components(&self) -> &[MultivariateNormalParams] {
+        &self.components
+    }
+
+    pub fn samples(&self) -> &Array2<f64> {
+        &self.samples
+    }
+
+    pub fn covariance_type(&self) -> CovarianceType {
+        self.covariance_type
+    }
+
+    pub fn maximization(&mut self) -> Result<(), MultivariateError> {
+        self.maximize(&mut Vec::new())
+    }
+
+    fn point_log_likelihoods(&self, point: Vec<f64>) -> Result<Vec<f64>, MultivariateError> {
+        let point = self.check_point(point)?;
+        Ok(self.components.iter().map(|params| params.log_likelihood(point.view())).collect())
+    }
+
+    fn check_point(&self, point: Vec<f64>) -> Result<Array1<f64>, MultivariateError> {
+        if point.len() == self.dim() {
+            Ok(Array1::from(point))
+        } else {
+            Err(MultivariateError::DimensionMismatch { expected: self.dim(), got: point.len() })
+        }
+    }
+
+    /// Maximization step. Components without responsibility or whose covariance is not
+    /// positive definite keep their parameters and are reported as collapsed.
+    fn maximize(&mut self, events: &mut Vec<EmEvent>) -> Result<(), MultivariateError> {
+        let size = self.samples.nrows() as f64;
+        let mut collapsed = false;
+        for (component, (params, responsibilities)) in
+            zip(&mut self.components, self.likelihoods.rows()).enumerate()
+        {
+            let mass = responsibilities.sum();
+            let fitted = mass.is_normal()
+                && fit(&self.samples, responsibilities, mass, self.covariance_type, self.regularization)
+                    .and_then(|(mean, covariance)| params.update_params(mean, covariance).ok())
+                    .is_some();
+            if fitted {
+                params.set_weight((mass / size).min(1.0))?;
+            } else {
+                collapsed = true;
+                events.push(EmEvent::Collapsed { component });
+            }
+        }
+        if collapsed {
+            let total: f64 = self.components.iter().map(MultivariateNormalParams::weight).sum();
+            if total.is_normal() {
+                for params in &mut self.components {
+                    params.set_weight((params.weight() / total).min(1.0))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Means, covariance entries and weights of every component in one vector.
+    fn parameter_vector(&self) -> Vec<f64> {
+        self.components
+            .iter()
+            .flat_map(|params| {
+                params
+                    .mean()
+                    .iter()
+                    .chain(params.covariance().iter())
+                    .copied()
+                    .chain(std::iter::once(params.weight()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Return weighted mean and covariance of the samples, or None if they are not finite.
+fn fit(
+    samples: &Array2<f64>,
+    responsibilities: ArrayView1<f64>,
+    mass: f64,
+    covariance_type: CovarianceType,
+    regularization: f64,
+) -> Option<(Array1<f64>, Array2<f64>)> {
+    let mean = responsibilities.dot(samples) / mass;
+    let centered = samples - &mean;
+    let mut covariance = match covariance_type {
+        CovarianceType::Full => {
+            let weighted = &centered * &responsibilities.insert_axis(Axis(1));
+            weighted.t().dot(&centered) / mass
+        }
+        CovarianceType::Diagonal => {
+            let variances = responsibilities.dot(&centered.mapv(|value| value * value)) / mass;
+            Array2::from_diag(&variances)
+        }
+    };
+    covariance.diag_mut().mapv_inplace(|value| value + regularization);
+    let finite = mean.iter().chain(covariance.iter()).all(|value| value.is_finite());
+    finite.then_some((mean, covariance))
+}
+
+fn log_sum_exp(values: &[f64]) -> f64 {
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if !max.is_finite() {
+        return max;
+    }
+    max + values.iter().map(|value| (value - max).exp()).sum::<f64>().ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    fn make_model(normal_samples: Array2<f64>, covariance_type: CovarianceType) -> MultivariateEmModel {
+        let normal = MultivariateNormalParams::new(array![0.0, 0.0], Array2::eye(2), 0.5).unwrap();
+        let abnormal = MultivariateNormalParams::new(array![10.0, 10.0], Array2::eye(2), 0.5).unwrap();
+        let abnormal_samples = array![[9.0, 10.0], [11.0, 10.0], [10.0, 9.0], [10.0, 11.0]];
+        let samples = ndarray::concatenate![Axis(0), normal_samples, abnormal_samples];
+        let epochs = PositiveInteger::new(1).unwrap();
+        MultivariateEmModel::new(vec![normal, abnormal], samples, epochs, covariance_type, 0.0).unwrap()
+    }
+
+    #[test]
+    fn test_maximization_full_and_diagonal() {
+        let normal_samples = array![[-2.0, -1.0], [2.0, 1.0], [-1.0, -1.0], [1.0, 1.0]];
+        let mut full = make_model(normal_samples.clone(), CovarianceType::Full);
+        full.expectation();
+        full.maximization().unwrap();
+        let normal = &full.components()[0];
+        assert!(normal.mean().iter().all(|value| value.abs() < 1e-9));
+        let expected = array![[2.5, 1.5], [1.5, 1.0]];
+        assert!(zip(normal.covariance(), &expected).all(|(left, right)| (left - right).abs() < 1e-9));
+        let abnormal = &full.components()[1];
+        assert!(zip(abnormal.covariance(), &array![[0.5, 0.0], [0.0, 0.5]]).all(|(left, right)| (left - right).abs() < 1e-9));
+        assert!((normal.weight() - 0.5).abs() < 1e-9);
+
+        let mut diagonal = make_model(normal_samples, CovarianceType::Diagonal);
+        diagonal.expectation();
+        diagonal.maximization().unwrap();
+        let covariance = diagonal.components()[0].covariance();
+        assert_eq!(covariance[[0, 1]], 0.0);
+        assert!((covariance[[0, 0]] - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_singular_covariance_collapses() {
+        // the normal samples lie on a line, so their covariance is singular without regularization
+        let normal_samples = array![[-1.0, -1.0], [1.0, 1.0], [0.5, 0.5], [0.0, 0.0]];
+        let mut model = make_model(normal_samples.clone(), CovarianceType::Full);
+        let diagnostics = model.update(vec![-0.5, -0.5]).unwrap();
+        assert_eq!(diagnostics.events, vec![EmEvent::Collapsed { component: 0 }]);
+        assert_eq!(model.components()[0].covariance(), Array2::<f64>::eye(2));
+        let mut regularized = make_model(normal_samples, CovarianceType::Full);
+        regularized.regularization = 1e-3;
+        assert!(regularized.update(vec![-0.5, -0.5]).unwrap().events.is_empty());
+    }
+
+    #[test]
+    fn test_update_and_predict() {
+        let normal_samples = array![[-1.0, -1.0], [1.0, 1.0], [1.0, -1.0], [0.0, 0.0]];
+        let mut model = make_model(normal_samples, CovarianceType::Diagonal);
+        model.epochs = PositiveInteger::new(3).unwrap();
+        let diagnostics = model.update(vec![9.5, 10.5]).unwrap();
+        assert_eq!(model.samples.row(7), array![9.5, 10.5]);
+        assert_eq!(diagnostics.epochs, 3);
+        assert!(diagnostics.log_likelihoods.windows(2).all(|pair| pair[1] >= pair[0] - 1e-9));
+        assert!(model.predict(vec![0.5, -0.5]).unwrap() > 0.99);
+        assert!(model.predict(vec![10.0, 9.0]).unwrap() < 0.01);
+        let proba = model.predict_proba(vec![10.0, 9.0]).unwrap();
+        assert!((proba.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        let (component, confidence) = model.classify(vec![10.0, 9.0]).unwrap();
+        assert_eq!((component, confidence), (1, proba[1]));
+        assert!(model.novelty_score(vec![30.0, -30.0]).unwrap() > model.novelty_score(vec![10.0, 9.0]).unwrap());
+        assert_eq!(model.predict(vec![1.0]), Err(MultivariateError::DimensionMismatch { expected: 2, got: 1 }));
+        assert!(model.update(vec![1.0, 2.0, 3.0]).is_err());
+    }
+}
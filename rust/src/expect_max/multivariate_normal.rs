@@ -0,0 +1,232 @@
+use super::probability::{Probability, ProbabilityError};
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+use pyo3::exceptions::PyValueError;
+use pyo3::PyErr;
+use std::f64::consts::PI;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MultivariateError {
+    DimensionMismatch { expected: usize, got: usize },
+    NotPositiveDefinite,
+    BadMean,
+    BadWeight(f64),
+    BadRegularization(f64),
+    RaggedSamples,
+}
+
+impl fmt::Display for MultivariateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MultivariateError::DimensionMismatch { expected, got } => {
+                write!(f, "Expected {} channels, got {}.", expected, got)
+            }
+            MultivariateError::NotPositiveDefinite => write!(f, "Covariance must be symmetric positive definite."),
+            MultivariateError::BadMean => write!(f, "Mean must be finite."),
+            MultivariateError::BadWeight(value) => {
+                write!(f, "Component weight must be between 0 and 1, got {}.", value)
+            }
+            MultivariateError::BadRegularization(value) => {
+                write!(f, "Regularization must be finite and non-negative, got {}.", value)
+            }
+            MultivariateError::RaggedSamples => write!(f, "Every sample must have the same number of channels."),
+        }
+    }
+}
+
+impl From<ProbabilityError> for MultivariateError {
+    fn from(err: ProbabilityError) -> MultivariateError {
+        MultivariateError::BadWeight(err.value())
+    }
+}
+
+impl From<MultivariateError> for PyErr {
+    fn from(err: MultivariateError) -> PyErr {
+        PyValueError::new_err(format!("{}", err))
+    }
+}
+
+/// Return lower triangular L with matrix = L L^T, or None if matrix is not symmetric
+/// positive definite. Numerically singular matrices count as not positive definite.
+pub fn cholesky(matrix: ArrayView2<f64>) -> Option<Array2<f64>> {
+    let dim = matrix.nrows();
+    if matrix.ncols() != dim {
+        return None;
+    }
+    let tolerance = 1e-9 * matrix.diag().iter().fold(0.0f64, |acc, value| acc.max(value.abs()));
+    if (0..dim).any(|row| (0..row).any(|col| (matrix[[row, col]] - matrix[[col, row]]).abs() > tolerance)) {
+        return None;
+    }
+    let mut lower = Array2::<f64>::zeros((dim, dim));
+    for col in 0..dim {
+        let diag = matrix[[col, col]] - (0..col).map(|idx| lower[[col, idx]].powi(2)).sum::<f64>();
+        // pivots lost to rounding mean the matrix is singular
+        if !(diag.is_finite() && diag > 1e-12 * matrix[[col, col]]) {
+            return None;
+        }
+        let diag = diag.sqrt();
+        lower[[col, col]] = diag;
+        for row in col + 1..dim {
+            let dot: f64 = (0..col).map(|idx| lower[[row, idx]] * lower[[col, idx]]).sum();
+            lower[[row, col]] = (matrix[[row, col]] - dot) / diag;
+        }
+    }
+    Some(lower)
+}
+
+/// Solve lower * x = rhs for lower triangular lower.
+fn forward_substitute(lower: &Array2<f64>, rhs: ArrayView1<f64>) -> Array1<f64> {
+    let mut out = Array1::<f64>::zeros(rhs.len());
+    for row in 0..rhs.len() {
+        let dot: f64 = (0..row).map(|idx| lower[[row, idx]] * out[idx]).sum();
+        out[row] = (rhs[row] - dot) / lower[[row, row]];
+    }
+    out
+}
+
+/// Convert rows of channels into a samples by channels array.
+pub fn rows_to_array(rows: &[Vec<f64>]) -> Result<Array2<f64>, MultivariateError> {
+    let dim = rows.first().map_or(0, Vec::len);
+    if rows.iter().any(|row| row.len() != dim) {
+        return Err(MultivariateError::RaggedSamples);
+    }
+    Ok(Array2::from_shape_fn((rows.len(), dim), |(row, col)| rows[row][col]))
+}
+
+/// Mean, rows of the covariance and weight of a component.
+pub type MultivariateNormalTuple = (Vec<f64>, Vec<Vec<f64>>, f64);
+
+/// Multivariate normal mixture component with its weight.
+///
+/// The Cholesky factor of the covariance is kept so densities cost one triangular solve.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultivariateNormalParams {
+    mean: Array1<f64>,
+    covariance: Array2<f64>,
+    cholesky: Array2<f64>,
+    // log of the determinant of the covariance
+    log_det: f64,
+    prob: Probability,
+}
+
+impl MultivariateNormalParams {
+    pub fn new(mean: Array1<f64>, covariance: Array2<f64>, weight: f64) -> Result<Self, MultivariateError> {
+        if covariance.dim() != (mean.len(), mean.len()) {
+            return Err(MultivariateError::DimensionMismatch { expected: mean.len(), got: covariance.nrows() });
+        }
+        if !mean.iter().all(|value| value.is_finite()) {
+            return Err(MultivariateError::BadMean);
+        }
+        let prob = Probability::new(weight)?;
+        let (cholesky, log_det) = factor(&covariance)?;
+        Ok(Self { mean, covariance, cholesky, log_det, prob })
+    }
+
+    /// Construct from a mean, rows of the covariance and a weight.
+    pub fn from_tuple(tuple: MultivariateNormalTuple) -> Result<Self, MultivariateError> {
+        let (mean, covariance, weight) = tuple;
+        Self::new(Array1::from(mean), rows_to_array(&covariance)?, weight)
+    }
+
+    pub fn dim(&self) -> usize {
+        self.mean.len()
+    }
+
+    pub fn mean(&self) -> &Array1<f64> {
+        &self.mean
+    }
+
+    pub fn covariance(&self) -> &Array2<f64> {
+        &self.covariance
+    }
+
+    pub fn weight(&self) -> f64 {
+        self.prob.value()
+    }
+
+    pub fn set_weight(&mut self, weight: f64) -> Result<(), MultivariateError> {
+        self.prob.probability(weight)?;
+        Ok(())
+    }
+
+    /// Replace mean and covariance, leaving the component unchanged on error.
+    pub fn update_params(&mut self, mean: Array1<f64>, covariance: Array2<f64>) -> Result<(), MultivariateError> {
+        if !mean.iter().all(|value| value.is_finite()) {
+            return Err(MultivariateError::BadMean);
+        }
+        let (cholesky, log_det) = factor(&covariance)?;
+        self.mean = mean;
+        self.covariance = covariance;
+        self.cholesky = cholesky;
+        self.log_det = log_det;
+        Ok(())
+    }
+
+    /// Natural log of the unweighted density at point.
+    pub fn log_density(&self, point: ArrayView1<f64>) -> f64 {
+        let centered = &point - &self.mean;
+        let whitened = forward_substitute(&self.cholesky, centered.view());
+        let dim = self.dim() as f64;
+        -0.5 * (dim * (2.0 * PI).ln() + self.log_det + whitened.dot(&whitened))
+    }
+
+    /// Natural log of the weighted density at point.
+    pub fn log_likelihood(&self, point: ArrayView1<f64>) -> f64 {
+        self.weight().ln() + self.log_density(point)
+    }
+}
+
+fn factor(covariance: &Array2<f64>) -> Result<(Array2<f64>, f64), MultivariateError> {
+    let cholesky = cholesky(covariance.view()).ok_or(MultivariateError::NotPositiveDefinite)?;
+    let log_det = 2.0 * cholesky.diag().iter().map(|value| value.ln()).sum::<f64>();
+    Ok((cholesky, log_det))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expect_max::normal::Normal;
+    use ndarray::array;
+
+    #[test]
+    fn test_cholesky() {
+        let matrix = array![[4.0, 2.0, 0.4], [2.0, 2.0, 0.5], [0.4, 0.5, 3.0]];
+        let lower = cholesky(matrix.view()).unwrap();
+        assert!(lower[[0, 1]] == 0.0 && lower[[0, 2]] == 0.0 && lower[[1, 2]] == 0.0);
+        let product = lower.dot(&lower.t());
+        assert!(product.iter().zip(&matrix).all(|(left, right)| (left - right).abs() < 1e-12));
+        assert_eq!(cholesky(array![[1.0, 2.0], [2.0, 1.0]].view()), None);
+        assert_eq!(cholesky(array![[1.0, 0.5], [0.0, 1.0]].view()), None);
+    }
+
+    #[test]
+    fn test_log_density_matches_univariate() {
+        let params = MultivariateNormalParams::new(array![1.0, -2.0], array![[4.0, 0.0], [0.0, 0.25]], 0.5).unwrap();
+        let (first, second) = (Normal::new(1.0, 2.0).unwrap(), Normal::new(-2.0, 0.5).unwrap());
+        let point = array![2.5, -1.0];
+        let expected = first.log_phi(2.5) + second.log_phi(-1.0);
+        assert!((params.log_density(point.view()) - expected).abs() < 1e-12);
+        assert!((params.log_likelihood(point.view()) - expected - 0.5f64.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_log_density_correlated() {
+        let rho: f64 = 0.6;
+        let params = MultivariateNormalParams::new(array![0.0, 0.0], array![[1.0, rho], [rho, 1.0]], 1.0).unwrap();
+        let (x, y) = (0.7, -0.3);
+        let quad = (x * x - 2.0 * rho * x * y + y * y) / (1.0 - rho * rho);
+        let expected = -(2.0 * PI).ln() - 0.5 * (1.0 - rho * rho).ln() - 0.5 * quad;
+        assert!((params.log_density(array![x, y].view()) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_bad_params() {
+        let err = MultivariateNormalParams::new(array![0.0, 0.0], array![[1.0]], 0.5);
+        assert_eq!(err, Err(MultivariateError::DimensionMismatch { expected: 2, got: 1 }));
+        let err = MultivariateNormalParams::new(array![0.0], array![[-1.0]], 0.5);
+        assert_eq!(err, Err(MultivariateError::NotPositiveDefinite));
+        let err = MultivariateNormalParams::new(array![0.0], array![[1.0]], 1.5);
+        assert_eq!(err, Err(MultivariateError::BadWeight(1.5)));
+        assert_eq!(rows_to_array(&[vec![1.0, 2.0], vec![3.0]]), Err(MultivariateError::RaggedSamples));
+    }
+}
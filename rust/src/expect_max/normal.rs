@@ -39,6 +39,12 @@ impl fmt::Display for NormalError {
     }
 }
 
+impl From<NormalError> for PyErr {
+    fn from(err: NormalError) -> PyErr {
+        PyValueError::new_err(format!("{}", err))
+    }
+}
+
 // impl error::Error for NormalError {
 //     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
 //         match *self {
@@ -126,6 +132,23 @@ impl Normal {
         }
     }
 
+    /// Natural log of the density at point.
+    pub fn log_phi(&self, point: f64) -> f64 {
+        match self.stddev {
+            0.0 => {
+                if point == self.mean {
+                    0.0
+                } else {
+                    f64::NEG_INFINITY
+                }
+            }
+            _ => {
+                let log_denom = self.stddev.ln() + 0.5 * (2.0 * std::f64::consts::PI).ln();
+                -(0.5 * (point - self.mean).powi(2) / self.stddev.powi(2)) - log_denom
+            }
+        }
+    }
+
     pub fn update_params(&mut self, mean: f64, stddev: f64) -> Result<(), NormalError> {
         self.set_mean(mean)?;
         self.set_stddev(stddev)?;
@@ -204,4 +227,15 @@ mod tests {
         let answer: f64 = (2.0 * std::f64::consts::PI).sqrt().recip();
         assert_eq!(normal.phi(0.0), answer);
     }
+
+    #[test]
+    fn test_log_phi() {
+        let normal = Normal::new(1.5, 2.0).unwrap();
+        for point in [-3.0, 0.0, 1.5, 4.2] {
+            assert!((normal.log_phi(point) - normal.phi(point).ln()).abs() < 1e-12);
+        }
+        let dirac = Normal::new(1.0, 0.0).unwrap();
+        assert_eq!(dirac.log_phi(1.0), 0.0);
+        assert_eq!(dirac.log_phi(2.0), f64::NEG_INFINITY);
+    }
 }
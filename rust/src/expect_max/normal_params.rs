@@ -1,6 +1,10 @@
+use crate::bocpd::normal_inverse_gamma::NormalInverseGamma;
+use crate::expect_max::component::{floor_variance, weighted_moments, Fit, MixtureComponent};
 use crate::expect_max::normal::{Normal, NormalError};
+use crate::expect_max::priors::posterior_mode;
 use crate::expect_max::probability::{Probability, ProbabilityError};
-use ndarray::{ArrayBase, Data, DataMut, Ix1};
+use ndarray::{ArrayBase, ArrayView1, Data, DataMut, Ix1};
+use std::iter::zip;
 use pyo3::exceptions::PyValueError;
 use pyo3::PyErr;
 use std::fmt;
@@ -59,6 +63,24 @@ impl NormalParams {
         })
     }
 
+    pub fn mean(&self) -> f64 {
+        self.dist.mean()
+    }
+
+    pub fn stddev(&self) -> f64 {
+        self.dist.stddev()
+    }
+
+    /// Mixture weight of the component.
+    pub fn weight(&self) -> f64 {
+        self.prob.value()
+    }
+
+    /// Natural log of the weighted density at point.
+    pub fn log_likelihood(&self, point: f64) -> f64 {
+        self.prob.value().ln() + self.dist.log_phi(point)
+    }
+
     pub fn likelihood(&self, point: f64) -> f64 {
         self.prob.value() * self.dist.phi(point)
     }
@@ -96,6 +118,71 @@ impl NormalParams {
     }
 }
 
+impl NormalParams {
+    /// Fit mean and variance, taking the posterior mode under a Normal-Inverse-Gamma prior if given.
+    ///
+    /// With a prior the fit is never degenerate for lack of samples.
+    pub fn fit_with_prior(
+        &mut self,
+        samples: ArrayView1<f64>,
+        responsibilities: ArrayView1<f64>,
+        variance_floor: f64,
+        prior: Option<&NormalInverseGamma>,
+    ) -> Fit {
+        let (mass, mean, variance) = weighted_moments(zip(samples.iter().copied(), responsibilities.iter().copied()));
+        let (mean, variance) = match prior {
+            Some(prior) => posterior_mode(prior, mass, mean, variance),
+            None if mass.is_normal() => (mean, variance),
+            None => return Fit::Degenerate,
+        };
+        let Some((variance, fit)) = floor_variance(variance, variance_floor).filter(|_| mean.is_finite()) else {
+            return Fit::Degenerate;
+        };
+        match self.update_params(mean, variance.sqrt(), self.weight()) {
+            Ok(_) => fit,
+            Err(_) => Fit::Degenerate,
+        }
+    }
+}
+
+impl MixtureComponent for NormalParams {
+    fn weight(&self) -> f64 {
+        NormalParams::weight(self)
+    }
+
+    fn set_weight(&mut self, weight: f64) -> Result<(), ProbabilityError> {
+        self.prob.probability(weight).map(|_| ())
+    }
+
+    fn log_density(&self, point: f64) -> f64 {
+        self.dist.log_phi(point)
+    }
+
+    fn log_likelihood(&self, point: f64) -> f64 {
+        NormalParams::log_likelihood(self, point)
+    }
+
+    fn likelihood(&self, point: f64) -> f64 {
+        NormalParams::likelihood(self, point)
+    }
+
+    fn mean(&self) -> f64 {
+        NormalParams::mean(self)
+    }
+
+    fn stddev(&self) -> f64 {
+        NormalParams::stddev(self)
+    }
+
+    fn parameters(&self) -> (f64, f64, f64) {
+        (NormalParams::mean(self), NormalParams::stddev(self), NormalParams::weight(self))
+    }
+
+    fn fit(&mut self, samples: ArrayView1<f64>, responsibilities: ArrayView1<f64>, variance_floor: f64) -> Fit {
+        self.fit_with_prior(samples, responsibilities, variance_floor, None)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,6 +228,13 @@ mod tests {
         assert_eq!(params.prob.value(), 0.7);
     }
 
+    #[test]
+    fn test_getters_and_log_likelihood() {
+        let params = NormalParams::from_tuple((1.0, 2.0, 0.25)).unwrap();
+        assert_eq!((params.mean(), params.stddev(), params.weight()), (1.0, 2.0, 0.25));
+        assert!((params.log_likelihood(3.0) - params.likelihood(3.0).ln()).abs() < 1e-12);
+    }
+
     #[test]
     fn test_update_params_failure_all_bad() {
         let mut params = get_params();
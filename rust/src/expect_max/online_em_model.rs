@@ -0,0 +1,328 @@
+use super::normal_params::{NormalParams, NormalParamsError};
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, pymethods, PyErr};
+use std::fmt;
+use std::iter::{once, zip};
+
+#[derive(Debug)]
+pub enum OnlineEmError {
+    ParamsError(NormalParamsError),
+    StepSizeError(&'static str, f64),
+    PriorWeightError(f64),
+}
+
+impl fmt::Display for OnlineEmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OnlineEmError::ParamsError(ref err) => write!(f, "{}", err),
+            OnlineEmError::StepSizeError(name, value) => {
+                write!(f, "Bad step size parameter {}: {}", name, value)
+            }
+            OnlineEmError::PriorWeightError(value) => {
+                write!(f, "Prior weight must be finite and non-negative: {}", value)
+            }
+        }
+    }
+}
+
+impl From<NormalParamsError> for OnlineEmError {
+    fn from(err: NormalParamsError) -> OnlineEmError {
+        OnlineEmError::ParamsError(err)
+    }
+}
+
+impl From<OnlineEmError> for PyErr {
+    fn from(err: OnlineEmError) -> PyErr {
+        PyValueError::new_err(format!("{}", err))
+    }
+}
+
+/// Step sizes gamma_n = gamma0 * (n + offset)^(-decay) for the n-th observation.
+///
+/// Decay in (0.5, 1] satisfies the Robbins-Monro conditions so the estimates settle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StepSize {
+    gamma0: f64,
+    decay: f64,
+    offset: f64,
+}
+
+impl StepSize {
+    pub fn new(gamma0: f64, decay: f64, offset: f64) -> Result<Self, OnlineEmError> {
+        if !(gamma0 > 0.0 && gamma0 <= 1.0) {
+            return Err(OnlineEmError::StepSizeError("gamma0", gamma0));
+        }
+        if !(decay > 0.5 && decay <= 1.0) {
+            return Err(OnlineEmError::StepSizeError("decay", decay));
+        }
+        // first step must keep part of the initial statistics
+        if !(offset.is_finite() && gamma0 * (1.0 + offset).powf(-decay) < 1.0) {
+            return Err(OnlineEmError::StepSizeError("offset", offset));
+        }
+        Ok(Self { gamma0, decay, offset })
+    }
+
+    pub fn value(&self, count: u64) -> f64 {
+        self.gamma0 * (count as f64 + self.offset).powf(-self.decay)
+    }
+}
+
+/// Running averages of responsibility, responsibility * x and responsibility * x^2.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct SufficientStats {
+    weight: f64,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl SufficientStats {
+    fn from_params(params: &NormalParams) -> Self {
+        let (mean, stddev, weight) = (params.mean(), params.stddev(), params.weight());
+        Self {
+            weight,
+            sum: weight * mean,
+            sum_sq: weight * (stddev.powi(2) + mean.powi(2)),
+        }
+    }
+
+    fn scaled(&self, scale: f64) -> Self {
+        Self {
+            weight: scale * self.weight,
+            sum: scale * self.sum,
+            sum_sq: scale * self.sum_sq,
+        }
+    }
+
+    fn step(&mut self, gamma: f64, responsibility: f64, point: f64) {
+        let keep = 1.0 - gamma;
+        let scaled = gamma * responsibility;
+        self.weight = keep * self.weight + scaled;
+        self.sum = keep * self.sum + scaled * point;
+        self.sum_sq = keep * self.sum_sq + scaled * point * point;
+    }
+}
+
+/// Expectation Maximization model updated with a stochastic approximation of the E step.
+///
+/// Each observation moves the sufficient statistics towards its own contribution by the
+/// current step size, then the M step is solved in closed form from the statistics
+/// (Cappé and Moulines, 2009). Updates cost O(components) per observation.
+///
+/// The initial parameters are kept as pseudo-observations with weight prior_weight, so a
+/// component that has not explained data for a long time does not collapse onto the next
+/// point it explains.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct OnlineEmModel {
+    normal: NormalParams,
+    abnormals: Vec<NormalParams>,
+    stats: Vec<SufficientStats>,
+    prior: Vec<SufficientStats>,
+    step_size: StepSize,
+    count: u64,
+}
+
+#[pymethods]
+impl OnlineEmModel {
+    #[new]
+    #[pyo3(signature = (normal, abnormals, gamma0=1.0, decay=0.6, offset=10.0, prior_weight=0.01))]
+    pub fn py_new(
+        normal: (f64, f64, f64),
+        abnormals: Vec<(f64, f64, f64)>,
+        gamma0: f64,
+        decay: f64,
+        offset: f64,
+        prior_weight: f64,
+    ) -> Result<Self, OnlineEmError> {
+        let normal = NormalParams::from_tuple(normal)?;
+        let abnormals = abnormals
+            .into_iter()
+            .map(NormalParams::from_tuple)
+            .collect::<Result<Vec<_>, _>>()?;
+        let step_size = StepSize::new(gamma0, decay, offset)?;
+        Self::new(normal, abnormals, step_size, prior_weight)
+    }
+
+    /// Update model parameters using given point.
+    pub fn update(&mut self, point: f64) -> Result<(), NormalParamsError> {
+        let Some(responsibilities) = self.responsibilities(point) else {
+            // point is impossible under every component
+            return Ok(());
+        };
+        self.count += 1;
+        let gamma = self.step_size.value(self.count);
+        for (stats, responsibility) in zip(&mut self.stats, responsibilities) {
+            stats.step(gamma, responsibility, point);
+        }
+        self.maximization()
+    }
+
+    /// Return posterior probability that point belongs to the normal component.
+    pub fn predict(&self, point: f64) -> f64 {
+        self.responsibilities(point)
+            .map_or(0.0, |responsibilities| responsibilities[0])
+    }
+
+    /// Number of observations used to update the model.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Step size that will be applied to the next observation.
+    pub fn next_step_size(&self) -> f64 {
+        self.step_size.value(self.count + 1)
+    }
+}
+
+impl OnlineEmModel {
+    pub fn new(
+        normal: NormalParams,
+        abnormals: Vec<NormalParams>,
+        step_size: StepSize,
+        prior_weight: f64,
+    ) -> Result<Self, OnlineEmError> {
+        if !(prior_weight.is_finite() && prior_weight >= 0.0) {
+            return Err(OnlineEmError::PriorWeightError(prior_weight));
+        }
+        let stats: Vec<SufficientStats> = once(&normal)
+            .chain(&abnormals)
+            .map(SufficientStats::from_params)
+            .collect();
+        let prior = stats.iter().map(|stats| stats.scaled(prior_weight)).collect();
+        Ok(Self {
+            normal,
+            abnormals,
+            stats,
+            prior,
+            step_size,
+            count: 0,
+        })
+    }
+
+    pub fn normal(&self) -> &NormalParams {
+        &self.normal
+    }
+
+    pub fn abnormals(&self) -> &[NormalParams] {
+        &self.abnormals
+    }
+
+    /// Return posterior probability of each component, normal first, or None if point
+    /// is impossible under every component.
+    fn responsibilities(&self, point: f64) -> Option<Vec<f64>> {
+        let log_likelihoods: Vec<f64> = once(&self.normal)
+            .chain(&self.abnormals)
+            .map(|params| params.log_likelihood(point))
+            .collect();
+        let max = log_likelihoods
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if !max.is_finite() {
+            return None;
+        }
+        let mut out: Vec<f64> = log_likelihoods.iter().map(|value| (value - max).exp()).collect();
+        let norm: f64 = out.iter().sum();
+        out.iter_mut().for_each(|value| *value /= norm);
+        Some(out)
+    }
+
+    fn maximization(&mut self) -> Result<(), NormalParamsError> {
+        let combined: Vec<SufficientStats> = zip(&self.stats, &self.prior)
+            .map(|(stats, prior)| SufficientStats {
+                weight: stats.weight + prior.weight,
+                sum: stats.sum + prior.sum,
+                sum_sq: stats.sum_sq + prior.sum_sq,
+            })
+            .collect();
+        let total: f64 = combined.iter().map(|stats| stats.weight).sum();
+        let params = once(&mut self.normal).chain(&mut self.abnormals);
+        for (param, stats) in zip(params, &combined) {
+            // leave components that no longer explain any data
+            if !stats.weight.is_normal() {
+                continue;
+            }
+            let mean = stats.sum / stats.weight;
+            let variance = (stats.sum_sq / stats.weight - mean.powi(2)).max(0.0);
+            param.update_params(mean, variance.sqrt(), stats.weight / total)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rand_distr::{Distribution, Normal};
+
+    fn make_model() -> OnlineEmModel {
+        OnlineEmModel::py_new((0.0, 2.0, 0.5), vec![(8.0, 2.0, 0.5)], 1.0, 0.6, 10.0, 0.0).unwrap()
+    }
+
+    #[test]
+    fn test_step_size_bad_params() {
+        assert!(StepSize::new(0.0, 0.6, 10.0).is_err());
+        assert!(StepSize::new(1.0, 0.5, 10.0).is_err());
+        assert!(StepSize::new(1.0, 1.1, 10.0).is_err());
+        assert!(StepSize::new(1.0, 0.6, 0.0).is_err());
+        let step_size = StepSize::new(1.0, 1.0, 1.0).unwrap();
+        assert_eq!(step_size.value(1), 0.5);
+        assert!(step_size.value(2) < step_size.value(1));
+    }
+
+    #[test]
+    fn test_update_single_step() {
+        let mut model = make_model();
+        let gamma = model.next_step_size();
+        // a point halfway between the components is split evenly and keeps the weights
+        model.update(4.0).unwrap();
+        assert!((model.normal().mean() - 4.0 * gamma).abs() < 1e-12);
+        assert!((model.abnormals()[0].mean() - (8.0 - 4.0 * gamma)).abs() < 1e-12);
+        assert!((model.normal().weight() - 0.5).abs() < 1e-12);
+        assert_eq!(model.count(), 1);
+    }
+
+    #[test]
+    fn test_update_recovers_mixture() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let normal = Normal::new(1.0, 1.0).unwrap();
+        let abnormal = Normal::new(10.0, 2.0).unwrap();
+        let mut model = make_model();
+        for idx in 0..50_000 {
+            let point = if idx % 4 == 0 { abnormal.sample(&mut rng) } else { normal.sample(&mut rng) };
+            model.update(point).unwrap();
+        }
+        let (normal, abnormal) = (model.normal(), &model.abnormals()[0]);
+        assert!((normal.mean() - 1.0).abs() < 0.2, "normal: {:?}", normal);
+        assert!((normal.stddev() - 1.0).abs() < 0.2, "normal: {:?}", normal);
+        assert!((normal.weight() - 0.75).abs() < 0.05, "normal: {:?}", normal);
+        assert!((abnormal.mean() - 10.0).abs() < 0.4, "abnormal: {:?}", abnormal);
+        assert!(model.predict(1.0) > 0.99);
+        assert!(model.predict(10.0) < 0.01);
+    }
+
+    #[test]
+    fn test_prior_weight_prevents_collapse() {
+        let mut model =
+            OnlineEmModel::py_new((0.0, 1.0, 0.9), vec![(50.0, 2.0, 0.1)], 1.0, 0.6, 10.0, 0.01).unwrap();
+        for _ in 0..1_000 {
+            model.update(0.0).unwrap();
+        }
+        model.update(48.0).unwrap();
+        let abnormal = model.abnormals()[0];
+        assert!(abnormal.stddev() > 0.1, "abnormal: {:?}", abnormal);
+        assert!(model.predict(46.0) < 0.5);
+        assert!(OnlineEmModel::py_new((0.0, 1.0, 0.9), vec![], 1.0, 0.6, 10.0, -1.0).is_err());
+    }
+
+    #[test]
+    fn test_update_impossible_point() {
+        let mut model =
+            OnlineEmModel::py_new((0.0, 0.0, 0.5), vec![(1.0, 0.0, 0.5)], 1.0, 0.6, 10.0, 0.01).unwrap();
+        model.update(3.0).unwrap();
+        assert_eq!(model.count(), 0);
+        assert_eq!(model.predict(3.0), 0.0);
+    }
+}
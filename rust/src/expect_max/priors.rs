@@ -0,0 +1,192 @@
+use super::component::Component;
+use crate::bocpd::normal_inverse_gamma::NormalInverseGamma;
+use pyo3::exceptions::PyValueError;
+use pyo3::PyErr;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PriorError {
+    BadHyperparameter(&'static str, f64),
+    BadConcentration(f64),
+    NotNormal(usize),
+}
+
+impl fmt::Display for PriorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PriorError::BadHyperparameter(name, value) => {
+                write!(f, "Bad Normal-Inverse-Gamma hyperparameter {}: {}", name, value)
+            }
+            PriorError::BadConcentration(value) => {
+                write!(f, "Dirichlet concentration must be finite and at least 1, got {}.", value)
+            }
+            PriorError::NotNormal(component) => {
+                write!(f, "Normal-Inverse-Gamma prior is only supported on normal components, component {} is not normal.", component)
+            }
+        }
+    }
+}
+
+impl From<PriorError> for PyErr {
+    fn from(err: PriorError) -> PyErr {
+        PyValueError::new_err(format!("{}", err))
+    }
+}
+
+/// Check that prior is a proper Normal-Inverse-Gamma distribution.
+pub fn validate_component_prior(prior: &NormalInverseGamma) -> Result<(), PriorError> {
+    if !prior.mu.is_finite() {
+        return Err(PriorError::BadHyperparameter("mu", prior.mu));
+    }
+    for (name, value) in [("kappa", prior.kappa), ("alpha", prior.alpha), ("beta", prior.beta)] {
+        if !(value.is_finite() && value > 0.0) {
+            return Err(PriorError::BadHyperparameter(name, value));
+        }
+    }
+    Ok(())
+}
+
+/// Return joint posterior mode of the mean and variance of a normal distribution.
+///
+/// Takes the responsibility mass, weighted mean and weighted variance of the samples;
+/// with no mass the mode of the prior is returned.
+pub fn posterior_mode(prior: &NormalInverseGamma, mass: f64, mean: f64, variance: f64) -> (f64, f64) {
+    let NormalInverseGamma { alpha, beta, mu, kappa } = *prior;
+    let (mass, mean, scatter) = if mass.is_normal() { (mass, mean, mass * variance) } else { (0.0, mu, 0.0) };
+    let kappa_n = kappa + mass;
+    let mu_n = (kappa * mu + mass * mean) / kappa_n;
+    let alpha_n = alpha + mass / 2.0;
+    let beta_n = beta + scatter / 2.0 + kappa * mass * (mean - mu).powi(2) / (2.0 * kappa_n);
+    (mu_n, beta_n / (alpha_n + 1.5))
+}
+
+/// Conjugate priors turning the maximization step into a maximum a posteriori update.
+///
+/// Components are numbered with the normal component first. Components without a prior,
+/// and weights without a concentration, keep their maximum likelihood update.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MapPriors {
+    components: Vec<Option<NormalInverseGamma>>,
+    concentrations: Vec<f64>,
+}
+
+impl MapPriors {
+    pub fn is_empty(&self) -> bool {
+        self.components.iter().all(Option::is_none) && self.concentrations.is_empty()
+    }
+
+    pub fn component(&self, component: usize) -> Option<&NormalInverseGamma> {
+        self.components.get(component).and_then(Option::as_ref)
+    }
+
+    pub fn concentrations(&self) -> &[f64] {
+        &self.concentrations
+    }
+
+    /// Place a Normal-Inverse-Gamma prior on the mean and variance of a component.
+    pub fn set_component(&mut self, component: usize, prior: NormalInverseGamma) -> Result<(), PriorError> {
+        validate_component_prior(&prior)?;
+        if self.components.len() <= component {
+            self.components.resize(component + 1, None);
+        }
+        self.components[component] = Some(prior);
+        Ok(())
+    }
+
+    /// Check that every component with a Normal-Inverse-Gamma prior is normal.
+    pub fn check_components<'a>(&self, components: impl IntoIterator<Item = &'a Component>) -> Result<(), PriorError> {
+        for (idx, component) in components.into_iter().enumerate() {
+            if self.component(idx).is_some() && !matches!(component, Component::Normal(_)) {
+                return Err(PriorError::NotNormal(idx));
+            }
+        }
+        Ok(())
+    }
+
+    /// Place a Dirichlet prior on the component weights.
+    ///
+    /// Concentrations below 1 have no mode inside the simplex, so they are rejected.
+    pub fn set_concentrations(&mut self, concentrations: &[f64]) -> Result<(), PriorError> {
+        if let Some(&bad) = concentrations.iter().find(|&&value| !(value.is_finite() && value >= 1.0)) {
+            return Err(PriorError::BadConcentration(bad));
+        }
+        self.concentrations = concentrations.to_vec();
+        Ok(())
+    }
+
+    /// Return posterior mode of the mean and variance of a component, if it has a prior.
+    pub fn map_mean_variance(&self, component: usize, mass: f64, mean: f64, variance: f64) -> Option<(f64, f64)> {
+        self.component(component).map(|prior| posterior_mode(prior, mass, mean, variance))
+    }
+
+    /// Return posterior mode of the weights given the responsibility mass of each component.
+    pub fn map_weights(&self, masses: &[f64]) -> Vec<f64> {
+        let pseudo: Vec<f64> = masses
+            .iter()
+            .enumerate()
+            .map(|(idx, &mass)| mass.max(0.0) + self.concentrations.get(idx).map_or(0.0, |value| value - 1.0))
+            .collect();
+        let total: f64 = pseudo.iter().sum();
+        pseudo.iter().map(|value| value / total).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expect_max::component::Laplace;
+    use crate::expect_max::normal_params::NormalParams;
+
+    fn prior(mu: f64, kappa: f64, alpha: f64, beta: f64) -> NormalInverseGamma {
+        NormalInverseGamma { alpha, beta, mu, kappa }
+    }
+
+    #[test]
+    fn test_map_mean_variance() {
+        let mut priors = MapPriors::default();
+        priors.set_component(1, prior(10.0, 1.0, 2.0, 3.0)).unwrap();
+        assert_eq!(priors.map_mean_variance(0, 5.0, 0.0, 1.0), None);
+        // without data the prior mode is returned
+        assert_eq!(priors.map_mean_variance(1, 0.0, f64::NAN, f64::NAN), Some((10.0, 3.0 / 3.5)));
+        let (mean, variance) = priors.map_mean_variance(1, 3.0, 6.0, 2.0).unwrap();
+        assert!((mean - 7.0).abs() < 1e-12);
+        // beta_n = 3 + 3 + 1 * 3 * 16 / 8
+        assert!((variance - 12.0 / 5.0).abs() < 1e-12);
+        // a heavy sample mass dominates the prior
+        let (mean, variance) = priors.map_mean_variance(1, 1e9, 6.0, 2.0).unwrap();
+        assert!((mean - 6.0).abs() < 1e-6);
+        assert!((variance - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_map_weights() {
+        let mut priors = MapPriors::default();
+        assert_eq!(priors.map_weights(&[3.0, 1.0]), vec![0.75, 0.25]);
+        priors.set_concentrations(&[1.0, 3.0]).unwrap();
+        assert_eq!(priors.map_weights(&[3.0, 1.0]), vec![0.5, 0.5]);
+        assert_eq!(priors.map_weights(&[4.0, 0.0]), vec![4.0 / 6.0, 2.0 / 6.0]);
+    }
+
+    #[test]
+    fn test_bad_priors() {
+        let mut priors = MapPriors::default();
+        assert_eq!(
+            priors.set_component(0, prior(0.0, 0.0, 1.0, 1.0)),
+            Err(PriorError::BadHyperparameter("kappa", 0.0))
+        );
+        assert!(priors.set_component(0, prior(f64::NAN, 1.0, 1.0, 1.0)).is_err());
+        assert_eq!(priors.set_concentrations(&[2.0, 0.5]), Err(PriorError::BadConcentration(0.5)));
+        assert!(priors.is_empty());
+    }
+
+    #[test]
+    fn test_check_components() {
+        let normal: Component = NormalParams::from_tuple((0.0, 1.0, 0.5)).unwrap().into();
+        let laplace: Component = Laplace::new(0.0, 1.0, 0.5).unwrap().into();
+        let mut priors = MapPriors::default();
+        assert_eq!(priors.check_components(&[laplace, laplace]), Ok(()));
+        priors.set_component(0, prior(0.0, 1.0, 1.0, 1.0)).unwrap();
+        assert_eq!(priors.check_components(&[normal, laplace]), Ok(()));
+        assert_eq!(priors.check_components(&[laplace, normal]), Err(PriorError::NotNormal(0)));
+    }
+}
@@ -5,6 +5,13 @@ use std::fmt;
 #[derive(Debug)]
 pub struct ProbabilityError(f64);
 
+impl ProbabilityError {
+    /// The rejected value.
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+}
+
 impl fmt::Display for ProbabilityError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -0,0 +1,199 @@
+use super::em_early_stop_model::{
+    AitkenChecker, ConvergenceCheck, EmAitkenCheck, EmLikelihoodCheck, EmLogLikelihoodCheck, EmParameterCheck,
+    LogLikelihoodChecker, ParameterChecker,
+};
+use super::em_model::EmOptions;
+use super::em_model_builder::{EmBuilder, MissingFieldError, NoChecker, WithLikelihoods, WithSamples};
+use super::normal_params::NormalParams;
+use super::pos_int::PositiveInteger;
+use super::retention::RetentionPolicy;
+use numpy::PyReadonlyArray1;
+use pyo3::{pyclass, pymethods, Py, PyAny, PyRefMut, PyResult, Python};
+
+/// Builder of Expectation Maximization models from real training samples, usable from Python.
+///
+/// Every setter validates its values straight away and returns the builder, so calls can be
+/// chained. The model is built by [`EmBuilder`] when `get_model` is called.
+#[pyclass(name = "EmBuilder")]
+#[derive(Clone, Debug)]
+pub struct PyEmBuilder {
+    normal: (f64, f64, f64),
+    abnormals: Vec<(f64, f64, f64)>,
+    samples: Option<Vec<f64>>,
+    epochs: u32,
+    retention: RetentionPolicy,
+    options: EmOptions,
+    convergence_check: Option<ConvergenceCheck>,
+}
+
+impl Default for PyEmBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[pymethods]
+impl PyEmBuilder {
+    #[new]
+    pub fn new() -> Self {
+        Self {
+            normal: (0.0, 1.0, 1.0),
+            abnormals: Vec::new(),
+            samples: None,
+            epochs: 1,
+            retention: RetentionPolicy::default(),
+            options: EmOptions::default(),
+            convergence_check: None,
+        }
+    }
+
+    /// Set the normal component from its mean, standard deviation and weight.
+    pub fn build_normal(mut slf: PyRefMut<'_, Self>, mean: f64, stddev: f64, prob: f64) -> PyResult<PyRefMut<'_, Self>> {
+        NormalParams::from_tuple((mean, stddev, prob))?;
+        slf.normal = (mean, stddev, prob);
+        Ok(slf)
+    }
+
+    /// Set the abnormal components, each given as (mean, standard deviation, weight).
+    pub fn build_abnormal(mut slf: PyRefMut<'_, Self>, abnormals: Vec<(f64, f64, f64)>) -> PyResult<PyRefMut<'_, Self>> {
+        for &abnormal in &abnormals {
+            NormalParams::from_tuple(abnormal)?;
+        }
+        slf.abnormals = abnormals;
+        Ok(slf)
+    }
+
+    /// Set the training samples. The array is copied.
+    pub fn build_samples<'py>(mut slf: PyRefMut<'py, Self>, samples: PyReadonlyArray1<'_, f64>) -> PyRefMut<'py, Self> {
+        slf.samples = Some(samples.as_array().to_vec());
+        slf
+    }
+
+    /// Set the number of epochs to run for every update.
+    pub fn build_epochs(mut slf: PyRefMut<'_, Self>, epochs: u32) -> PyResult<PyRefMut<'_, Self>> {
+        PositiveInteger::new(epochs)?;
+        slf.epochs = epochs;
+        Ok(slf)
+    }
+
+    pub fn build_retention(mut slf: PyRefMut<'_, Self>, retention: RetentionPolicy) -> PyResult<PyRefMut<'_, Self>> {
+        retention.validate()?;
+        slf.retention = retention;
+        Ok(slf)
+    }
+
+    pub fn build_options(mut slf: PyRefMut<'_, Self>, options: EmOptions) -> PyResult<PyRefMut<'_, Self>> {
+        options.validate()?;
+        slf.options = options;
+        Ok(slf)
+    }
+
+    /// Choose the check that stops updates early, or None to run every epoch.
+    #[pyo3(signature = (convergence_check=None))]
+    pub fn build_convergence_check(
+        mut slf: PyRefMut<'_, Self>,
+        convergence_check: Option<ConvergenceCheck>,
+    ) -> PyRefMut<'_, Self> {
+        slf.convergence_check = convergence_check;
+        slf
+    }
+
+    /// Return `EmModel` if no convergence check was chosen, otherwise the early stopping
+    /// model for the check.
+    ///
+    /// # Errors
+    ///
+    /// If samples were never given.
+    pub fn get_model(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let final_builder = self.final_builder()?;
+        let model = match self.convergence_check {
+            None => Py::new(py, final_builder.get_model())?.into_any(),
+            Some(ConvergenceCheck::Likelihood) => {
+                let model = final_builder.build_likelihood_converge_checker().get_model();
+                Py::new(py, EmLikelihoodCheck::from_early_stop_model(model))?.into_any()
+            }
+            Some(ConvergenceCheck::LogLikelihood) => {
+                let model = final_builder.build_converge_checker(LogLikelihoodChecker::default()).get_model();
+                Py::new(py, EmLogLikelihoodCheck::from_early_stop_model(model))?.into_any()
+            }
+            Some(ConvergenceCheck::Parameter) => {
+                let model = final_builder.build_converge_checker(ParameterChecker::default()).get_model();
+                Py::new(py, EmParameterCheck::from_early_stop_model(model))?.into_any()
+            }
+            Some(ConvergenceCheck::Aitken) => {
+                let model = final_builder.build_converge_checker(AitkenChecker::default()).get_model();
+                Py::new(py, EmAitkenCheck::from_early_stop_model(model))?.into_any()
+            }
+        };
+        Ok(model)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl PyEmBuilder {
+    /// Run the Rust builder up to the likelihoods with the values set so far.
+    fn final_builder(&self) -> PyResult<EmBuilder<WithSamples, WithLikelihoods, NoChecker>> {
+        // samples are optional from Python, so their absence can only be checked here
+        let Some(samples) = &self.samples else {
+            return Err(MissingFieldError { my_struct: (), field: String::from("samples") }.into());
+        };
+        let (mean, stddev, prob) = self.normal;
+        let final_builder = EmBuilder::new()
+            .build_normal(mean, stddev, prob)?
+            .build_abnormal_from_tuples(&self.abnormals)?
+            .build_epochs(self.epochs)?
+            .build_retention(self.retention)?
+            .build_options(self.options.clone())?
+            .build_samples_from_slice(samples)
+            .build_likelihoods();
+        Ok(final_builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expect_max::em_early_stop_model::{
+        EmAitkenCheck, EmLikelihoodCheck, EmLogLikelihoodCheck, EmParameterCheck,
+    };
+    use crate::expect_max::em_model::EmModel;
+    use pyo3::types::PyAnyMethods;
+
+    #[test]
+    fn test_final_builder() {
+        let mut builder = PyEmBuilder::new();
+        assert!(builder.final_builder().is_err());
+        builder.normal = (1.0, 2.0, 0.75);
+        builder.abnormals = vec![(10.0, 1.0, 0.25)];
+        builder.samples = Some(vec![0.0, 1.0, 2.0, 11.0]);
+        builder.epochs = 3;
+        let model = builder.final_builder().unwrap().get_model();
+        assert_eq!(model.parameters(), vec![(1.0, 2.0, 0.75), (10.0, 1.0, 0.25)]);
+        assert_eq!(model.epochs().value(), 3);
+        // a placeholder sample is kept for the point of every update
+        assert_eq!(model.sample_count(), 5);
+    }
+
+    #[test]
+    fn test_get_model_dispatch() {
+        Python::initialize();
+        Python::attach(|py| {
+            let mut builder = PyEmBuilder::new();
+            assert!(builder.get_model(py).is_err());
+            builder.abnormals = vec![(10.0, 1.0, 0.25)];
+            builder.samples = Some(vec![0.0, 1.0, 2.0, 11.0]);
+            assert!(builder.get_model(py).unwrap().bind(py).is_instance_of::<EmModel>());
+            builder.convergence_check = Some(ConvergenceCheck::Likelihood);
+            assert!(builder.get_model(py).unwrap().bind(py).is_instance_of::<EmLikelihoodCheck>());
+            builder.convergence_check = Some(ConvergenceCheck::LogLikelihood);
+            assert!(builder.get_model(py).unwrap().bind(py).is_instance_of::<EmLogLikelihoodCheck>());
+            builder.convergence_check = Some(ConvergenceCheck::Parameter);
+            assert!(builder.get_model(py).unwrap().bind(py).is_instance_of::<EmParameterCheck>());
+            builder.convergence_check = Some(ConvergenceCheck::Aitken);
+            assert!(builder.get_model(py).unwrap().bind(py).is_instance_of::<EmAitkenCheck>());
+        });
+    }
+}
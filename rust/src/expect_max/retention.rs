@@ -0,0 +1,256 @@
+use ndarray::{aview0, s, Array1, Axis};
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, PyErr};
+use rand::rngs::StdRng;
+use rand::seq::index;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+
+#[derive(Copy, Clone, Debug)]
+pub struct RetentionError(usize);
+
+impl fmt::Display for RetentionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Sample capacity must be positive, got {}.", self.0)
+    }
+}
+
+impl From<RetentionError> for PyErr {
+    fn from(err: RetentionError) -> PyErr {
+        PyValueError::new_err(format!("{}", err))
+    }
+}
+
+/// How an Expectation Maximization model keeps observed points among its samples.
+#[pyclass]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RetentionPolicy {
+    /// Overwrite the last sample with every new point.
+    SwapLast(),
+    /// Keep the newest capacity points, evicting the oldest first. Training samples count as oldest.
+    Fifo { capacity: usize },
+    /// Keep every training sample and grow with new points until capacity,
+    /// then evict the oldest point that is not a training sample.
+    Growing { capacity: usize },
+    /// Keep a uniform random subset of capacity points out of everything observed.
+    #[pyo3(constructor = (capacity, seed=None))]
+    Reservoir { capacity: usize, seed: Option<u64> },
+}
+
+impl RetentionPolicy {
+    pub fn capacity(&self) -> Option<usize> {
+        match *self {
+            RetentionPolicy::SwapLast() => None,
+            RetentionPolicy::Fifo { capacity }
+            | RetentionPolicy::Growing { capacity }
+            | RetentionPolicy::Reservoir { capacity, .. } => Some(capacity),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), RetentionError> {
+        match self.capacity() {
+            Some(0) => Err(RetentionError(0)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy::SwapLast()
+    }
+}
+
+/// Sample buffer state for a retention policy.
+#[derive(Clone, Debug)]
+pub(super) struct SampleRetention {
+    policy: RetentionPolicy,
+    // samples before this index are never evicted
+    pinned: usize,
+    // slot evicted next once the buffer is full
+    next: usize,
+    // points observed so far, including training samples
+    seen: u64,
+    rng: StdRng,
+}
+
+impl SampleRetention {
+    /// Start retaining points in samples, truncating them to the policy capacity.
+    pub(super) fn new(policy: RetentionPolicy, samples: &mut Array1<f64>) -> Self {
+        let mut rng = match policy {
+            RetentionPolicy::Reservoir { seed: Some(seed), .. } => StdRng::seed_from_u64(seed),
+            _ => StdRng::from_rng(&mut rand::rng()),
+        };
+        // every training sample counts as observed, even those not kept
+        let seen = samples.len() as u64;
+        match policy {
+            RetentionPolicy::Fifo { capacity } if samples.len() > capacity => {
+                // keep the newest samples
+                *samples = samples.slice(s![samples.len() - capacity..]).to_owned();
+            }
+            RetentionPolicy::Reservoir { capacity, .. } if samples.len() > capacity => {
+                // keep a uniform subset, in training order
+                let mut kept = index::sample(&mut rng, samples.len(), capacity).into_vec();
+                kept.sort_unstable();
+                *samples = samples.select(Axis(0), &kept);
+            }
+            _ => {}
+        }
+        let pinned = match policy {
+            RetentionPolicy::Growing { .. } => samples.len(),
+            _ => 0,
+        };
+        Self {
+            policy,
+            pinned,
+            next: pinned,
+            seen,
+            rng,
+        }
+    }
+
+    pub(super) fn policy(&self) -> RetentionPolicy {
+        self.policy
+    }
+
+    /// Add point to samples according to the policy.
+    ///
+    /// Returns the sample that was replaced, if any.
+    pub(super) fn insert(&mut self, samples: &mut Array1<f64>, point: f64) -> Option<f64> {
+        self.seen += 1;
+        let capacity = match self.policy {
+            RetentionPolicy::SwapLast() => {
+                let last = samples.last_mut().expect("samples is empty");
+                return Some(std::mem::replace(last, point));
+            }
+            RetentionPolicy::Fifo { capacity }
+            | RetentionPolicy::Growing { capacity }
+            | RetentionPolicy::Reservoir { capacity, .. } => capacity,
+        };
+        // growing can only fill the room left by its pinned samples
+        if samples.len() < capacity.max(self.pinned + 1) {
+            samples
+                .push(Axis(0), aview0(&point))
+                .expect("pushing a scalar onto a 1d array cannot fail");
+            return None;
+        }
+        let slot = match self.policy {
+            RetentionPolicy::Reservoir { .. } => {
+                let slot = self.rng.random_range(0..self.seen) as usize;
+                if slot >= samples.len() {
+                    return None;
+                }
+                slot
+            }
+            _ => {
+                let slot = self.next;
+                self.next = if slot + 1 < samples.len() { slot + 1 } else { self.pinned };
+                slot
+            }
+        };
+        Some(std::mem::replace(&mut samples[slot], point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_swap_last() {
+        let mut samples = array![1.0, 2.0, 3.0];
+        let mut retention = SampleRetention::new(RetentionPolicy::SwapLast(), &mut samples);
+        assert_eq!(retention.insert(&mut samples, 4.0), Some(3.0));
+        assert_eq!(retention.insert(&mut samples, 5.0), Some(4.0));
+        assert_eq!(samples, array![1.0, 2.0, 5.0]);
+    }
+
+    #[test]
+    fn test_fifo() {
+        let mut samples = array![1.0, 2.0, 3.0, 4.0];
+        let mut retention = SampleRetention::new(RetentionPolicy::Fifo { capacity: 3 }, &mut samples);
+        assert_eq!(samples, array![2.0, 3.0, 4.0]);
+        assert_eq!(retention.insert(&mut samples, 5.0), Some(2.0));
+        assert_eq!(retention.insert(&mut samples, 6.0), Some(3.0));
+        assert_eq!(retention.insert(&mut samples, 7.0), Some(4.0));
+        assert_eq!(retention.insert(&mut samples, 8.0), Some(5.0));
+        assert_eq!(samples, array![8.0, 6.0, 7.0]);
+    }
+
+    #[test]
+    fn test_fifo_fills_before_evicting() {
+        let mut samples = array![1.0];
+        let mut retention = SampleRetention::new(RetentionPolicy::Fifo { capacity: 2 }, &mut samples);
+        assert_eq!(retention.insert(&mut samples, 2.0), None);
+        assert_eq!(retention.insert(&mut samples, 3.0), Some(1.0));
+        assert_eq!(samples, array![3.0, 2.0]);
+    }
+
+    #[test]
+    fn test_growing_keeps_training_samples() {
+        let mut samples = array![1.0, 2.0];
+        let mut retention = SampleRetention::new(RetentionPolicy::Growing { capacity: 4 }, &mut samples);
+        assert_eq!(retention.insert(&mut samples, 3.0), None);
+        assert_eq!(retention.insert(&mut samples, 4.0), None);
+        assert_eq!(retention.insert(&mut samples, 5.0), Some(3.0));
+        assert_eq!(retention.insert(&mut samples, 6.0), Some(4.0));
+        assert_eq!(retention.insert(&mut samples, 7.0), Some(5.0));
+        assert_eq!(samples, array![1.0, 2.0, 7.0, 6.0]);
+        // capacity below the training size still keeps one slot for new points
+        let mut samples = array![1.0, 2.0];
+        let mut retention = SampleRetention::new(RetentionPolicy::Growing { capacity: 1 }, &mut samples);
+        assert_eq!(retention.insert(&mut samples, 3.0), None);
+        assert_eq!(retention.insert(&mut samples, 4.0), Some(3.0));
+    }
+
+    #[test]
+    fn test_reservoir_is_uniform() {
+        let capacity = 10;
+        let total = 100;
+        let mut counts = vec![0usize; total];
+        for seed in 0..2_000 {
+            let mut samples = Array1::from_iter((0..capacity).map(|idx| idx as f64));
+            let policy = RetentionPolicy::Reservoir { capacity, seed: Some(seed) };
+            let mut retention = SampleRetention::new(policy, &mut samples);
+            for point in capacity..total {
+                retention.insert(&mut samples, point as f64);
+            }
+            assert_eq!(samples.len(), capacity);
+            for &sample in &samples {
+                counts[sample as usize] += 1;
+            }
+        }
+        // each point is kept with probability capacity / total
+        assert!(counts.iter().all(|&count| (120..=280).contains(&count)), "counts: {:?}", counts);
+    }
+
+    #[test]
+    fn test_reservoir_samples_training_set() {
+        let capacity = 10;
+        let training = 50;
+        let total = 100;
+        let mut counts = vec![0usize; total];
+        for seed in 0..2_000 {
+            let mut samples = Array1::from_iter((0..training).map(|idx| idx as f64));
+            let policy = RetentionPolicy::Reservoir { capacity, seed: Some(seed) };
+            let mut retention = SampleRetention::new(policy, &mut samples);
+            assert_eq!(samples.len(), capacity);
+            assert!(samples.windows(2).into_iter().all(|pair| pair[0] < pair[1]));
+            for point in training..total {
+                retention.insert(&mut samples, point as f64);
+            }
+            for &sample in &samples {
+                counts[sample as usize] += 1;
+            }
+        }
+        // training samples are kept as often as later points
+        assert!(counts.iter().all(|&count| (120..=280).contains(&count)), "counts: {:?}", counts);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(RetentionPolicy::Fifo { capacity: 0 }.validate().is_err());
+        assert!(RetentionPolicy::SwapLast().validate().is_ok());
+    }
+}
@@ -0,0 +1,333 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, pymethods, PyResult};
+use std::collections::VecDeque;
+use std::iter::zip;
+
+const DEFAULT_C: f64 = 3.0;
+const DEFAULT_C_RATIO: f64 = 300.0;
+const DEFAULT_THRESHOLD: f64 = 0.5;
+const WHITENIZATION: f64 = 0.5;
+const DEFAULT_WINDOW_SIZE_V2: usize = 3;
+const DEFAULT_THRESHOLD_V2: f64 = 0.15;
+
+/// A class implementing the grey systems model.
+///
+/// The first full window becomes the reference behaviour; every later window is compared
+/// against it with the degree of grey incidence.
+#[pyclass]
+pub struct GreyModel {
+    window_size: usize,
+    c: f64,
+    c_ratio: f64,
+    threshold: f64,
+    window: VecDeque<f64>,
+    // behavioural and relative difference sequences of the reference window
+    reference: Option<(f64, f64)>,
+    accumulation: Vec<f64>,
+    mean: Vec<f64>,
+    degree: f64,
+    rel_degree: f64,
+}
+
+#[pymethods]
+impl GreyModel {
+    #[new]
+    #[pyo3(signature = (window_size, c=DEFAULT_C, c_ratio=DEFAULT_C_RATIO, threshold=DEFAULT_THRESHOLD))]
+    pub fn new(window_size: usize, c: f64, c_ratio: f64, threshold: f64) -> PyResult<Self> {
+        if window_size == 0 {
+            return Err(PyValueError::new_err("window size must be positive"));
+        }
+        Ok(Self {
+            window_size,
+            c,
+            c_ratio,
+            threshold,
+            window: VecDeque::with_capacity(window_size + 1),
+            reference: None,
+            accumulation: vec![0.0; window_size],
+            mean: vec![0.0; window_size],
+            degree: 1.0,
+            rel_degree: 1.0,
+        })
+    }
+
+    /// Update degrees of grey incidence using given input value.
+    pub fn update(&mut self, point: f64) {
+        self.window.push_back(point);
+        if self.window.len() > self.window_size {
+            self.window.pop_front();
+        }
+        if self.window.len() < self.window_size {
+            return;
+        }
+        accumulation_sequence_inplace(self.window.make_contiguous(), &mut self.accumulation);
+        mean_sequence_inplace(&self.accumulation, &mut self.mean, WHITENIZATION);
+        let behaviour = behavioral_sequence(&self.mean);
+        let rel_behaviour = behavior_relative_difference(&self.mean);
+        let &mut (ref_behaviour, ref_rel_behaviour) =
+            self.reference.get_or_insert((behaviour, rel_behaviour));
+        self.degree = grey_incidence_degree(ref_behaviour, behaviour, self.c);
+        self.rel_degree = grey_incidence_degree(ref_rel_behaviour, rel_behaviour, self.c_ratio);
+    }
+
+    /// Return the smaller of the two degrees of grey incidence. Low values indicate change.
+    pub fn predict(&self, _point: f64) -> f64 {
+        self.degree.min(self.rel_degree)
+    }
+
+    /// Return whether either degree of grey incidence is at or below threshold.
+    pub fn is_change(&self) -> bool {
+        self.degree <= self.threshold || self.rel_degree <= self.threshold
+    }
+
+    pub fn degree(&self) -> f64 {
+        self.degree
+    }
+
+    pub fn rel_degree(&self) -> f64 {
+        self.rel_degree
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Forget the reference window so the next full window becomes the new reference.
+    pub fn reset_reference(&mut self) {
+        self.reference = None;
+        self.window.clear();
+        self.degree = 1.0;
+        self.rel_degree = 1.0;
+    }
+}
+
+/// A class implementing the second version of the grey systems model.
+///
+/// Every point is accumulated over a window of its own and whitenized, then compared with
+/// the first point seen by degree of grey incidence, both directly and relative to it.
+#[pyclass]
+pub struct GreyModelV2 {
+    window_size: usize,
+    whitenization: f64,
+    threshold: f64,
+    // first point, offsets the relative indices to avoid dividing by zero
+    offset: Option<f64>,
+    accumulation: Vec<f64>,
+    mean: Vec<f64>,
+    behaviour: f64,
+    rel_behaviour: f64,
+}
+
+#[pymethods]
+impl GreyModelV2 {
+    #[new]
+    #[pyo3(signature = (window_size=DEFAULT_WINDOW_SIZE_V2, whitenization=WHITENIZATION, threshold=DEFAULT_THRESHOLD_V2))]
+    pub fn new(window_size: usize, whitenization: f64, threshold: f64) -> PyResult<Self> {
+        if window_size == 0 {
+            return Err(PyValueError::new_err("window size must be positive"));
+        }
+        Ok(Self {
+            window_size,
+            whitenization,
+            threshold,
+            offset: None,
+            accumulation: vec![0.0; window_size + 1],
+            mean: vec![0.0; window_size + 1],
+            behaviour: 0.0,
+            rel_behaviour: 0.0,
+        })
+    }
+
+    /// Update grey indices using given input value. The first value only sets the offset.
+    pub fn update(&mut self, point: f64) {
+        let Some(offset) = self.offset else {
+            self.offset = Some(point);
+            return;
+        };
+        let neg_alpha = 1.0 - self.whitenization;
+        for idx in 1..=self.window_size {
+            self.accumulation[idx] = point + self.accumulation[idx - 1];
+            self.mean[idx] = self.whitenization * self.accumulation[idx] + neg_alpha * self.accumulation[idx - 1];
+        }
+        let head = self.mean[0] + offset;
+        let mut behaviour = 0.0;
+        let mut rel_behaviour = 0.0;
+        for (idx, pair) in self.mean.windows(2).enumerate() {
+            // the last step only counts half
+            let weight = if idx + 1 == self.window_size { 0.5 } else { 1.0 };
+            behaviour += weight * (pair[1] - pair[0]).abs();
+            rel_behaviour += weight * ((pair[1] + offset) / head - (pair[0] + offset) / head).abs();
+        }
+        self.behaviour = behaviour;
+        self.rel_behaviour = rel_behaviour;
+    }
+
+    /// Return the smaller of the two grey indices. Low values indicate change.
+    pub fn predict(&self, _point: f64) -> f64 {
+        self.degree().min(self.rel_degree())
+    }
+
+    /// Return whether either grey index is below threshold.
+    pub fn is_change(&self) -> bool {
+        self.degree() < self.threshold || self.rel_degree() < self.threshold
+    }
+
+    pub fn degree(&self) -> f64 {
+        1.0 / (1.0 + self.threshold * self.behaviour)
+    }
+
+    pub fn rel_degree(&self) -> f64 {
+        1.0 / (1.0 + self.threshold * self.rel_behaviour)
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Forget the offset so the next point sets it again.
+    pub fn reset(&mut self) {
+        self.offset = None;
+        self.behaviour = 0.0;
+        self.rel_behaviour = 0.0;
+    }
+}
+
+/// Write the accumulation over window into out.
+pub fn accumulation_sequence_inplace(window: &[f64], out: &mut [f64]) {
+    let mut total = 0.0;
+    for (res, &item) in zip(out, window) {
+        total += item;
+        *res = total;
+    }
+}
+
+/// Write the running average of window into out.
+pub fn mean_sequence_inplace(window: &[f64], out: &mut [f64], alpha: f64) {
+    let Some(&head) = window.first() else {
+        return;
+    };
+    out[0] = head;
+    let neg_alpha = 1.0 - alpha;
+    for (res, pair) in zip(&mut out[1..], window.windows(2)) {
+        *res = pair[0] * alpha + pair[1] * neg_alpha;
+    }
+}
+
+pub fn behavioral_sequence(window: &[f64]) -> f64 {
+    let (Some(&head), Some(&tail)) = (window.first(), window.last()) else {
+        return 0.0;
+    };
+    let body: f64 = window[..window.len() - 1].iter().map(|item| item - head).sum();
+    body + 0.5 * (tail - head)
+}
+
+pub fn behavior_relative_difference(window: &[f64]) -> f64 {
+    let (Some(&head), Some(&tail)) = (window.first(), window.last()) else {
+        return 0.0;
+    };
+    let mut total = if head == 0.0 && tail == 0.0 {
+        0.0
+    } else {
+        0.5 * (tail - head).abs() / (0.5 * (tail.abs() + head.abs()))
+    };
+    for &item in &window[1..] {
+        // if head and item are zero, no difference
+        if head != 0.0 || item != 0.0 {
+            total += (item - head).abs() / (0.5 * (item.abs() + head.abs()));
+        }
+    }
+    total
+}
+
+pub fn grey_incidence_degree(val_1: f64, val_2: f64, c: f64) -> f64 {
+    let num = 1.0 + val_1.abs() + val_2.abs();
+    num / (num + c * (val_1 - val_2).abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floats_close(a: f64, b: f64, epsilon: f64) -> bool {
+        (a - b).abs() < epsilon
+    }
+
+    #[test]
+    fn test_accumulation_sequence() {
+        let mut out = [0.0; 4];
+        accumulation_sequence_inplace(&[1.0, 2.0, 3.0, 4.0], &mut out);
+        assert_eq!(out, [1.0, 3.0, 6.0, 10.0]);
+    }
+
+    #[test]
+    fn test_mean_sequence() {
+        let mut out = [0.0; 4];
+        mean_sequence_inplace(&[1.0, 3.0, 6.0, 10.0], &mut out, 0.5);
+        assert_eq!(out, [1.0, 2.0, 4.5, 8.0]);
+    }
+
+    #[test]
+    fn test_behavioral_sequence() {
+        // (0 + 1 + 3.5) + 0.5 * 7
+        assert!(floats_close(behavioral_sequence(&[1.0, 2.0, 4.5, 8.0]), 8.0, 1e-12));
+        assert_eq!(behavioral_sequence(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_behavior_relative_difference() {
+        let value = behavior_relative_difference(&[1.0, 3.0]);
+        // tail term 0.5 * 2 / 2, then 2 / 2 for the only item after head
+        assert!(floats_close(value, 1.5, 1e-12));
+        assert_eq!(behavior_relative_difference(&[0.0, 0.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_grey_incidence_degree() {
+        assert_eq!(grey_incidence_degree(2.0, 2.0, 3.0), 1.0);
+        assert!(floats_close(grey_incidence_degree(0.0, 1.0, 3.0), 0.4, 1e-12));
+    }
+
+    #[test]
+    fn test_grey_model_bad_window() {
+        assert!(GreyModel::new(0, DEFAULT_C, DEFAULT_C_RATIO, DEFAULT_THRESHOLD).is_err());
+    }
+
+    #[test]
+    fn test_grey_model_steady_then_change() {
+        let mut model = GreyModel::new(4, DEFAULT_C, DEFAULT_C_RATIO, DEFAULT_THRESHOLD).unwrap();
+        for _ in 0..10 {
+            model.update(1.0);
+            assert_eq!(model.predict(1.0), 1.0);
+            assert!(!model.is_change());
+        }
+        for _ in 0..4 {
+            model.update(20.0);
+        }
+        assert!(model.predict(20.0) < DEFAULT_THRESHOLD);
+        assert!(model.is_change());
+        model.reset_reference();
+        for _ in 0..4 {
+            model.update(20.0);
+        }
+        assert!(!model.is_change());
+    }
+
+    #[test]
+    fn test_grey_model_v2_indices() {
+        assert!(GreyModelV2::new(0, WHITENIZATION, DEFAULT_THRESHOLD_V2).is_err());
+        let mut model = GreyModelV2::new(DEFAULT_WINDOW_SIZE_V2, WHITENIZATION, DEFAULT_THRESHOLD_V2).unwrap();
+        model.update(1.0);
+        assert_eq!(model.predict(1.0), 1.0);
+        model.update(2.0);
+        // whitenized [0, 1, 3, 5], steps 1 + 2 + 0.5 * 2 and relative to the offset 1
+        assert!(floats_close(model.behaviour, 4.0, 1e-12));
+        assert!(floats_close(model.rel_behaviour, 4.0, 1e-12));
+        assert!(floats_close(model.degree(), 1.0 / 1.6, 1e-12));
+        assert!(!model.is_change());
+        model.update(100.0);
+        assert!(model.is_change());
+        model.reset();
+        model.update(100.0);
+        assert!(!model.is_change());
+    }
+}
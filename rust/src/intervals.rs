@@ -0,0 +1,168 @@
+use pyo3::{pyclass, pyfunction, pymethods};
+
+/// Change to the set of alarm intervals. Interval ends are exclusive.
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntervalEvent {
+    Opened { start: usize },
+    Closed { start: usize, end: usize },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct OpenInterval {
+    start: usize,
+    last_alarm: usize,
+    // whether the interval has lasted long enough to be reported
+    reported: bool,
+}
+
+/// A class that groups per-sample alarms into intervals.
+///
+/// Alarm runs separated by at most merge_gap quiet samples are merged into one interval.
+/// Intervals spanning fewer than min_duration samples are dropped without being reported.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct IntervalTracker {
+    min_duration: usize,
+    merge_gap: usize,
+    index: usize,
+    current: Option<OpenInterval>,
+}
+
+#[pymethods]
+impl IntervalTracker {
+    #[new]
+    #[pyo3(signature = (min_duration=1, merge_gap=0))]
+    pub fn new(min_duration: usize, merge_gap: usize) -> Self {
+        Self {
+            min_duration,
+            merge_gap,
+            index: 0,
+            current: None,
+        }
+    }
+
+    /// Consume alarm output for the next sample and return any interval event it causes.
+    pub fn update(&mut self, is_alarm: bool) -> Option<IntervalEvent> {
+        let idx = self.index;
+        self.index += 1;
+        if is_alarm {
+            let interval = self.current.get_or_insert(OpenInterval {
+                start: idx,
+                last_alarm: idx,
+                reported: false,
+            });
+            interval.last_alarm = idx;
+            let duration = idx - interval.start + 1;
+            if !interval.reported && duration >= self.min_duration {
+                interval.reported = true;
+                return Some(IntervalEvent::Opened { start: interval.start });
+            }
+            None
+        } else {
+            match self.current {
+                Some(interval) if idx - interval.last_alarm > self.merge_gap => self.close(),
+                _ => None,
+            }
+        }
+    }
+
+    /// Consume alarm output for several samples and return the interval events they cause.
+    pub fn update_many(&mut self, detections: Vec<bool>) -> Vec<IntervalEvent> {
+        detections
+            .into_iter()
+            .filter_map(|is_alarm| self.update(is_alarm))
+            .collect()
+    }
+
+    /// Close the current interval at the end of the stream.
+    pub fn finish(&mut self) -> Option<IntervalEvent> {
+        self.close()
+    }
+
+    /// Index of the next sample.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.current.is_some_and(|interval| interval.reported)
+    }
+
+    fn close(&mut self) -> Option<IntervalEvent> {
+        self.current
+            .take()
+            .filter(|interval| interval.reported)
+            .map(|interval| IntervalEvent::Closed {
+                start: interval.start,
+                end: interval.last_alarm + 1,
+            })
+    }
+}
+
+/// Return (start, end) intervals of alarms in detections. Interval ends are exclusive.
+#[pyfunction]
+#[pyo3(signature = (detections, min_duration=1, merge_gap=0))]
+pub fn detections_to_intervals(
+    detections: Vec<bool>,
+    min_duration: usize,
+    merge_gap: usize,
+) -> Vec<(usize, usize)> {
+    let mut tracker = IntervalTracker::new(min_duration, merge_gap);
+    let mut events = tracker.update_many(detections);
+    events.extend(tracker.finish());
+    events
+        .into_iter()
+        .filter_map(|event| match event {
+            IntervalEvent::Closed { start, end } => Some((start, end)),
+            IntervalEvent::Opened { .. } => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_detections(pattern: &str) -> Vec<bool> {
+        pattern.chars().map(|item| item == '1').collect()
+    }
+
+    #[test]
+    fn test_interval_tracker_streaming() {
+        let mut tracker = IntervalTracker::new(1, 0);
+        assert_eq!(tracker.update(false), None);
+        assert_eq!(tracker.update(true), Some(IntervalEvent::Opened { start: 1 }));
+        assert!(tracker.is_open());
+        assert_eq!(tracker.update(true), None);
+        assert_eq!(tracker.update(false), Some(IntervalEvent::Closed { start: 1, end: 3 }));
+        assert!(!tracker.is_open());
+        assert_eq!(tracker.index(), 4);
+    }
+
+    #[test]
+    fn test_interval_tracker_min_duration() {
+        let mut tracker = IntervalTracker::new(3, 0);
+        let events = tracker.update_many(to_detections("0110111"));
+        assert_eq!(events, vec![IntervalEvent::Opened { start: 4 }]);
+        assert_eq!(tracker.finish(), Some(IntervalEvent::Closed { start: 4, end: 7 }));
+        assert_eq!(tracker.finish(), None);
+    }
+
+    #[test]
+    fn test_interval_tracker_merge_gap() {
+        let intervals = detections_to_intervals(to_detections("1100110001"), 1, 2);
+        assert_eq!(intervals, vec![(0, 6), (9, 10)]);
+        let intervals = detections_to_intervals(to_detections("1100110001"), 1, 0);
+        assert_eq!(intervals, vec![(0, 2), (4, 6), (9, 10)]);
+    }
+
+    #[test]
+    fn test_detections_to_intervals_merged_duration() {
+        // two short runs only pass min_duration once merged
+        let intervals = detections_to_intervals(to_detections("101000"), 3, 1);
+        assert_eq!(intervals, vec![(0, 3)]);
+        assert!(detections_to_intervals(to_detections("100100"), 2, 1).is_empty());
+        assert!(detections_to_intervals(vec![], 1, 0).is_empty());
+    }
+}
@@ -1,19 +1,41 @@
-use std::iter::{once, zip};
+use std::iter::once;
 // use bocpd::beta_cache::BetaCache;
 use bocpd::bocpd_model::BocpdModel;
 // use bocpd::dist_params::DistParams;
 // use bocpd::sparse_probs::{SparseProb, SparseProbs};
 use cusum::{CusumV0, CusumV1};
-use expect_max::em_early_stop_model::EmLikelihoodCheck;
-use expect_max::em_model::EmModel;
-use expect_max::em_model_builder::EmBuilderOne;
+use ewma::EwmaChart;
+use grey::{GreyModel, GreyModelV2};
+use intervals::{IntervalEvent, IntervalTracker};
+use metrics::AlarmCounts;
+use expect_max::diagnostics::{EmDiagnostics, EmEvent};
+use expect_max::em_early_stop_model::{ConvergenceCheck, EmAitkenCheck, EmConvergenceCheck, EmLikelihoodCheck, EmLogLikelihoodCheck, EmParameterCheck};
+use expect_max::em_model::{EmModel, EmOptions};
+use expect_max::em_model_builder::{generate_samples, EmBuilder};
+use expect_max::hmm::HmmModel;
+use expect_max::hmm_builder::HmmBuilder;
+use expect_max::initialization::InitStrategy;
+use expect_max::model_selection::{Criterion, SelectionScore};
+use expect_max::multivariate_em_builder::MultivariateEmBuilder;
+use expect_max::multivariate_em_model::{CovarianceType, MultivariateEmModel};
+use expect_max::multivariate_normal::MultivariateNormalTuple;
+use expect_max::online_em_model::OnlineEmModel;
+use expect_max::py_em_builder::PyEmBuilder;
+use expect_max::retention::RetentionPolicy;
+use quickest::{ShiryaevRoberts, WindowedGlr};
+use synth::Segment;
 
 use pyo3::prelude::*;
-use rand::distr::Distribution;
 
 pub mod bocpd;
 pub mod cusum;
+pub mod ewma;
 pub mod expect_max;
+pub mod grey;
+pub mod intervals;
+pub mod metrics;
+pub mod quickest;
+pub mod synth;
 
 // /// Updates the probability distribution for a set of T-distributions with observed point.
 // #[pyfunction]
@@ -74,66 +96,166 @@ pub mod expect_max;
 // }
 
 /// Use builder to construct expectation maximization model.
+///
+/// Training samples are drawn from each component; identical seeds build identical models.
+/// Observed points are kept according to retention, which defaults to swapping the last sample.
+/// Options select log-domain responsibilities, a variance floor and re-initialization of
+/// collapsed components; by default none are used.
 #[pyfunction]
+#[pyo3(signature = (normal, abnormals, arr_sizes, epochs, seed=None, retention=None, options=None))]
 fn build_em_model(
     normal: (f64, f64, f64),
     abnormals: Vec<(f64, f64, f64)>,
     arr_sizes: Vec<u32>,
     epochs: u32,
+    seed: Option<u64>,
+    retention: Option<RetentionPolicy>,
+    options: Option<EmOptions>,
 ) -> PyResult<EmModel> {
     let (mean, stddev, prob) = normal;
-    let normal_iter = once(&normal);
-    let param_iter = normal_iter.chain(abnormals.iter());
-    let samples: Vec<f64> = zip(param_iter, arr_sizes.iter())
-        .map(|(params, &size)| {
-            let (mean, stddev, _probs) = params;
-            let rng = rand::rng();
-            let n = rand_distr::Normal::new(*mean, *stddev).expect("please don't panic");
-            n.sample_iter(rng).take(size as usize)
-        })
-        .flatten()
-        .collect();
-    let mut em_builder = EmBuilderOne::new();
-    let final_builder = em_builder.build_normal(mean, stddev, prob)?
+    let params: Vec<(f64, f64, f64)> = once(normal).chain(abnormals.iter().copied()).collect();
+    let samples = generate_samples(&params, &arr_sizes, seed)?;
+    let model = EmBuilder::new()
+        .build_normal(mean, stddev, prob)?
         .build_abnormal_from_tuples(&abnormals)?
         .build_epochs(epochs)?
+        .build_retention(retention.unwrap_or_default())?
+        .build_options(options.unwrap_or_default())?
         .build_samples_from_slice(&samples)
-        .next_builder()?
         .build_likelihoods()
-        .next_builder()?;
-    Ok(final_builder.get_standard_model())
+        .get_model();
+    Ok(model)
 }
 
 #[pyfunction]
+#[pyo3(signature = (normal, abnormals, arr_sizes, epochs, seed=None, retention=None, options=None))]
 fn build_em_early_stop_model(
     normal: (f64, f64, f64),
     abnormals: Vec<(f64, f64, f64)>,
     arr_sizes: Vec<u32>,
     epochs: u32,
+    seed: Option<u64>,
+    retention: Option<RetentionPolicy>,
+    options: Option<EmOptions>,
 ) -> PyResult<EmLikelihoodCheck> {
     let (mean, stddev, prob) = normal;
-    let normal_iter = once(&normal);
-    let param_iter = normal_iter.chain(abnormals.iter());
-    let samples: Vec<f64> = zip(param_iter, arr_sizes.iter())
-        .map(|(params, &size)| {
-            let (mean, stddev, _probs) = params;
-            let rng = rand::rng();
-            let n = rand_distr::Normal::new(*mean, *stddev).expect("please don't panic");
-            n.sample_iter(rng).take(size as usize)
-        })
-        .flatten()
-        .collect();
-    let mut em_builder = EmBuilderOne::new();
-    let mut final_builder = em_builder.build_normal(mean, stddev, prob)?
+    let params: Vec<(f64, f64, f64)> = once(normal).chain(abnormals.iter().copied()).collect();
+    let samples = generate_samples(&params, &arr_sizes, seed)?;
+    let early_stop_model = EmBuilder::new()
+        .build_normal(mean, stddev, prob)?
         .build_abnormal_from_tuples(&abnormals)?
         .build_epochs(epochs)?
+        .build_retention(retention.unwrap_or_default())?
+        .build_options(options.unwrap_or_default())?
         .build_samples_from_slice(&samples)
-        .next_builder()?
         .build_likelihoods()
-        .next_builder()?;
-    final_builder.build_likelihood_converge_checker();
-    let wrapped_model = EmLikelihoodCheck::from_early_stop_model(final_builder.get_early_stop_model());
-    Ok(wrapped_model)
+        .build_likelihood_converge_checker()
+        .get_model();
+    Ok(EmLikelihoodCheck::from_early_stop_model(early_stop_model))
+}
+
+/// Use builder to construct expectation maximization model from training samples.
+///
+/// Only the number of abnormal components is given; their parameters and those of the normal
+/// component are chosen by the initialization strategy, keeping the best of the restarts.
+#[pyfunction]
+#[pyo3(signature = (samples, abnormal_count, epochs, initialization=InitStrategy::KMeansPlusPlus, restarts=1, seed=None, options=None))]
+fn build_em_model_from_samples(
+    samples: Vec<f64>,
+    abnormal_count: usize,
+    epochs: u32,
+    initialization: InitStrategy,
+    restarts: u32,
+    seed: Option<u64>,
+    options: Option<EmOptions>,
+) -> PyResult<EmModel> {
+    let model = EmBuilder::new()
+        .build_epochs(epochs)?
+        .build_options(options.unwrap_or_default())?
+        .build_samples_from_slice(&samples)
+        .build_initialization(abnormal_count, initialization, restarts, seed)?
+        .build_likelihoods()
+        .get_model();
+    Ok(model)
+}
+
+/// Fit expectation maximization models with each number of abnormal components to the training
+/// samples and return the one with the best score under the criterion, with the scores of all.
+///
+/// Components are initialized with k-means++, keeping the best of the restarts for each count.
+#[pyfunction]
+#[pyo3(signature = (samples, abnormal_counts, epochs, criterion=Criterion::Bic, restarts=1, seed=None, options=None))]
+fn select_em_model(
+    samples: Vec<f64>,
+    abnormal_counts: Vec<usize>,
+    epochs: u32,
+    criterion: Criterion,
+    restarts: u32,
+    seed: Option<u64>,
+    options: Option<EmOptions>,
+) -> PyResult<(EmModel, Vec<SelectionScore>)> {
+    let (em_builder, scores) = EmBuilder::new()
+        .build_epochs(epochs)?
+        .build_options(options.unwrap_or_default())?
+        .build_samples_from_slice(&samples)
+        .build_selected_components(&abnormal_counts, criterion, InitStrategy::KMeansPlusPlus, restarts, seed)?;
+    Ok((em_builder.build_likelihoods().get_model(), scores))
+}
+
+/// Use builder to construct multivariate expectation maximization model.
+///
+/// Components are (mean, covariance rows, probability) and samples have one row per sample.
+/// Regularization is added to the diagonal of every fitted covariance.
+#[pyfunction]
+#[pyo3(signature = (normal, abnormals, samples, epochs, covariance_type=CovarianceType::Full, regularization=1e-6))]
+fn build_multivariate_em_model(
+    normal: MultivariateNormalTuple,
+    abnormals: Vec<MultivariateNormalTuple>,
+    samples: Vec<Vec<f64>>,
+    epochs: u32,
+    covariance_type: CovarianceType,
+    regularization: f64,
+) -> PyResult<MultivariateEmModel> {
+    let (mean, covariance, prob) = normal;
+    let model = MultivariateEmBuilder::new()
+        .build_normal(mean, covariance, prob)?
+        .build_abnormal_from_tuples(&abnormals)?
+        .build_samples_from_rows(&samples)?
+        .build_epochs(epochs)?
+        .build_covariance_type(covariance_type)
+        .build_regularization(regularization)?
+        .get_model()?;
+    Ok(model)
+}
+
+/// Use builder to construct a Hidden Markov Model and train it with Baum-Welch on the samples.
+///
+/// States are (mean, standard deviation, initial probability) with the normal state first.
+/// Without transitions every state is kept with probability self_transition.
+#[pyfunction]
+#[pyo3(signature = (normal, abnormals, samples, epochs, transitions=None, self_transition=0.9))]
+fn build_hmm_model(
+    normal: (f64, f64, f64),
+    abnormals: Vec<(f64, f64, f64)>,
+    samples: Vec<f64>,
+    epochs: u32,
+    transitions: Option<Vec<Vec<f64>>>,
+    self_transition: f64,
+) -> PyResult<HmmModel> {
+    let (mean, stddev, prob) = normal;
+    let mut builder = HmmBuilder::new();
+    builder
+        .build_normal(mean, stddev, prob)?
+        .build_abnormal_from_tuples(&abnormals)?
+        .build_samples_from_slice(&samples)
+        .build_epochs(epochs)?
+        .build_self_transition(self_transition)?;
+    if let Some(rows) = transitions {
+        builder.build_transitions(&rows)?;
+    }
+    let mut model = builder.get_model()?;
+    model.train()?;
+    Ok(model)
 }
 
 /// A Python module implemented in Rust.
@@ -142,10 +264,48 @@ fn build_em_early_stop_model(
 fn change_point_algorithms(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(build_em_model, m)?)?;
     m.add_function(wrap_pyfunction!(build_em_early_stop_model, m)?)?;
+    m.add_function(wrap_pyfunction!(build_em_model_from_samples, m)?)?;
+    m.add_function(wrap_pyfunction!(build_hmm_model, m)?)?;
+    m.add_function(wrap_pyfunction!(build_multivariate_em_model, m)?)?;
+    m.add_function(wrap_pyfunction!(select_em_model, m)?)?;
+    m.add_function(wrap_pyfunction!(intervals::detections_to_intervals, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics::detection_delays, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics::mean_detection_delay, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics::alarm_counts, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics::precision_recall_f1, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics::mean_time_between_false_alarms, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics::interval_iou, m)?)?;
+    m.add_function(wrap_pyfunction!(synth::generate_stream, m)?)?;
+    m.add_class::<AlarmCounts>()?;
     m.add_class::<BocpdModel>()?;
+    m.add_class::<ConvergenceCheck>()?;
+    m.add_class::<CovarianceType>()?;
+    m.add_class::<Criterion>()?;
+    m.add_class::<EmDiagnostics>()?;
+    m.add_class::<PyEmBuilder>()?;
+    m.add_class::<EmEvent>()?;
     m.add_class::<EmModel>()?;
+    m.add_class::<EmOptions>()?;
     m.add_class::<EmLikelihoodCheck>()?;
+    m.add_class::<EmLogLikelihoodCheck>()?;
+    m.add_class::<EmParameterCheck>()?;
+    m.add_class::<EmAitkenCheck>()?;
+    m.add_class::<EmConvergenceCheck>()?;
+    m.add_class::<HmmModel>()?;
+    m.add_class::<MultivariateEmModel>()?;
+    m.add_class::<OnlineEmModel>()?;
+    m.add_class::<RetentionPolicy>()?;
+    m.add_class::<SelectionScore>()?;
     m.add_class::<CusumV0>()?;
     m.add_class::<CusumV1>()?;
+    m.add_class::<EwmaChart>()?;
+    m.add_class::<GreyModel>()?;
+    m.add_class::<GreyModelV2>()?;
+    m.add_class::<InitStrategy>()?;
+    m.add_class::<IntervalEvent>()?;
+    m.add_class::<IntervalTracker>()?;
+    m.add_class::<Segment>()?;
+    m.add_class::<ShiryaevRoberts>()?;
+    m.add_class::<WindowedGlr>()?;
     Ok(())
 }
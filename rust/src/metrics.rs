@@ -0,0 +1,286 @@
+//! Scores for comparing detector alarms against ground truth change points.
+//!
+//! An alarm at index `a` detects change point `c` when `c <= a <= c + tolerance`
+//! and no later change point occurs at or before `a`.
+//! Intervals are half-open `(start, end)` index pairs.
+use pyo3::{pyclass, pyfunction, pymethods};
+
+#[pyclass]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AlarmCounts {
+    /// First alarms in the tolerance window of a change point.
+    #[pyo3(get)]
+    pub true_alarms: usize,
+    /// Alarms outside every tolerance window, or repeating an earlier alarm in one.
+    #[pyo3(get)]
+    pub false_alarms: usize,
+    /// Change points with at least one alarm in their tolerance window.
+    #[pyo3(get)]
+    pub detected: usize,
+    /// Change points with no alarm in their tolerance window.
+    #[pyo3(get)]
+    pub missed: usize,
+}
+
+#[pymethods]
+impl AlarmCounts {
+    /// Fraction of alarms that are true alarms.
+    pub fn precision(&self) -> f64 {
+        ratio(self.true_alarms, self.true_alarms + self.false_alarms)
+    }
+
+    /// Fraction of change points that were detected.
+    pub fn recall(&self) -> f64 {
+        ratio(self.detected, self.detected + self.missed)
+    }
+
+    /// Harmonic mean of precision and recall.
+    pub fn f1(&self) -> f64 {
+        let (precision, recall) = (self.precision(), self.recall());
+        if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// Return the delay of the first alarm after each change point, or None if it was missed.
+#[pyfunction]
+pub fn detection_delays(
+    changepoints: Vec<usize>,
+    alarms: Vec<usize>,
+    tolerance: usize,
+) -> Vec<Option<usize>> {
+    let changepoints = sorted(changepoints);
+    let alarms = sorted(alarms);
+    changepoints
+        .iter()
+        .enumerate()
+        .map(|(idx, &changepoint)| {
+            let end = window_end(&changepoints, idx, tolerance);
+            let first = alarms.partition_point(|&alarm| alarm < changepoint);
+            alarms
+                .get(first)
+                .filter(|&&alarm| alarm <= end)
+                .map(|&alarm| alarm - changepoint)
+        })
+        .collect()
+}
+
+/// Return mean delay over detected change points, or NaN if none were detected.
+#[pyfunction]
+pub fn mean_detection_delay(
+    changepoints: Vec<usize>,
+    alarms: Vec<usize>,
+    tolerance: usize,
+) -> f64 {
+    let delays: Vec<usize> = detection_delays(changepoints, alarms, tolerance)
+        .into_iter()
+        .flatten()
+        .collect();
+    if delays.is_empty() {
+        f64::NAN
+    } else {
+        delays.iter().sum::<usize>() as f64 / delays.len() as f64
+    }
+}
+
+/// Count true and false alarms and detected and missed change points.
+///
+/// Only the first alarm in a change point's window is a true alarm; later ones are false alarms.
+#[pyfunction]
+pub fn alarm_counts(
+    changepoints: Vec<usize>,
+    alarms: Vec<usize>,
+    tolerance: usize,
+) -> AlarmCounts {
+    let changepoints = sorted(changepoints);
+    let mut counts = AlarmCounts::default();
+    let mut detected = vec![false; changepoints.len()];
+    for alarm in sorted(alarms) {
+        // latest change point at or before the alarm
+        let next = changepoints.partition_point(|&changepoint| changepoint <= alarm);
+        match next.checked_sub(1) {
+            // only the first alarm in a window detects the change point
+            Some(idx) if !detected[idx] && alarm <= window_end(&changepoints, idx, tolerance) => {
+                counts.true_alarms += 1;
+                detected[idx] = true;
+            }
+            _ => counts.false_alarms += 1,
+        }
+    }
+    counts.detected = detected.iter().filter(|&&item| item).count();
+    counts.missed = detected.len() - counts.detected;
+    counts
+}
+
+/// Return (precision, recall, F1) of alarms against change points.
+#[pyfunction]
+pub fn precision_recall_f1(
+    changepoints: Vec<usize>,
+    alarms: Vec<usize>,
+    tolerance: usize,
+) -> (f64, f64, f64) {
+    let counts = alarm_counts(changepoints, alarms, tolerance);
+    (counts.precision(), counts.recall(), counts.f1())
+}
+
+/// Return number of samples observed per false alarm, or infinity without false alarms.
+#[pyfunction]
+pub fn mean_time_between_false_alarms(
+    changepoints: Vec<usize>,
+    alarms: Vec<usize>,
+    tolerance: usize,
+    length: usize,
+) -> f64 {
+    let false_alarms = alarm_counts(changepoints, alarms, tolerance).false_alarms;
+    if false_alarms == 0 {
+        f64::INFINITY
+    } else {
+        length as f64 / false_alarms as f64
+    }
+}
+
+/// Return intersection over union of the samples covered by each set of intervals.
+///
+/// Returns 1.0 when both sets are empty.
+#[pyfunction]
+pub fn interval_iou(truth: Vec<(usize, usize)>, predicted: Vec<(usize, usize)>) -> f64 {
+    let truth = merge_intervals(truth);
+    let predicted = merge_intervals(predicted);
+    let intersection = intersection_length(&truth, &predicted);
+    let union = total_length(&truth) + total_length(&predicted) - intersection;
+    if union == 0 {
+        1.0
+    } else {
+        intersection as f64 / union as f64
+    }
+}
+
+fn ratio(num: usize, denom: usize) -> f64 {
+    if denom == 0 {
+        0.0
+    } else {
+        num as f64 / denom as f64
+    }
+}
+
+fn sorted(mut values: Vec<usize>) -> Vec<usize> {
+    values.sort_unstable();
+    values
+}
+
+/// Last index an alarm may have to count for the change point at idx.
+fn window_end(changepoints: &[usize], idx: usize, tolerance: usize) -> usize {
+    let end = changepoints[idx].saturating_add(tolerance);
+    match changepoints.get(idx + 1) {
+        Some(&next) => end.min(next.saturating_sub(1)),
+        None => end,
+    }
+}
+
+/// Sort intervals and merge any that overlap or touch, dropping empty ones.
+fn merge_intervals(mut intervals: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    intervals.retain(|&(start, end)| start < end);
+    intervals.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(intervals.len());
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn total_length(intervals: &[(usize, usize)]) -> usize {
+    intervals.iter().map(|(start, end)| end - start).sum()
+}
+
+/// Length of overlap between two sorted, disjoint sets of intervals.
+fn intersection_length(first: &[(usize, usize)], second: &[(usize, usize)]) -> usize {
+    let (mut i, mut j) = (0, 0);
+    let mut total = 0;
+    while i < first.len() && j < second.len() {
+        let start = first[i].0.max(second[j].0);
+        let end = first[i].1.min(second[j].1);
+        total += end.saturating_sub(start);
+        if first[i].1 < second[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detection_delays() {
+        let delays = detection_delays(vec![100, 50], vec![10, 53, 99, 130], 20);
+        assert_eq!(delays, vec![Some(3), None]);
+        // alarm after the next change point does not count for the earlier one
+        let delays = detection_delays(vec![10, 15], vec![16], 20);
+        assert_eq!(delays, vec![None, Some(1)]);
+    }
+
+    #[test]
+    fn test_mean_detection_delay() {
+        assert_eq!(mean_detection_delay(vec![0, 100], vec![4, 106], 10), 5.0);
+        assert!(mean_detection_delay(vec![0], vec![], 10).is_nan());
+    }
+
+    #[test]
+    fn test_alarm_counts() {
+        let counts = alarm_counts(vec![50, 100], vec![10, 53, 55, 99, 130], 20);
+        assert_eq!(
+            counts,
+            AlarmCounts { true_alarms: 1, false_alarms: 4, detected: 1, missed: 1 }
+        );
+        assert_eq!(counts.precision(), 0.2);
+        assert_eq!(counts.recall(), 0.5);
+        assert!((counts.f1() - 2.0 / 7.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_alarm_counts_repeated_alarms() {
+        // a detector that keeps alarming after one change point is not perfectly precise
+        let counts = alarm_counts(vec![100], vec![102, 100, 101], 5);
+        assert_eq!(
+            counts,
+            AlarmCounts { true_alarms: 1, false_alarms: 2, detected: 1, missed: 0 }
+        );
+        assert_eq!(counts.precision(), 1.0 / 3.0);
+        assert_eq!(counts.recall(), 1.0);
+    }
+
+    #[test]
+    fn test_precision_recall_f1_empty() {
+        assert_eq!(precision_recall_f1(vec![], vec![], 5), (0.0, 0.0, 0.0));
+        assert_eq!(precision_recall_f1(vec![5], vec![5], 0), (1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_mean_time_between_false_alarms() {
+        assert_eq!(mean_time_between_false_alarms(vec![500], vec![100, 300, 505], 10, 1_000), 500.0);
+        assert_eq!(mean_time_between_false_alarms(vec![], vec![], 10, 1_000), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_interval_iou() {
+        assert_eq!(interval_iou(vec![(0, 10)], vec![(5, 15)]), 5.0 / 15.0);
+        // overlapping predictions are merged before scoring
+        assert_eq!(interval_iou(vec![(0, 10)], vec![(0, 6), (4, 10)]), 1.0);
+        assert_eq!(interval_iou(vec![(0, 4), (8, 12)], vec![(2, 10)]), 4.0 / 12.0);
+        assert_eq!(interval_iou(vec![], vec![]), 1.0);
+        assert_eq!(interval_iou(vec![(0, 5)], vec![]), 0.0);
+    }
+}
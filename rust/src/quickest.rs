@@ -0,0 +1,261 @@
+use crate::expect_max::normal::Normal;
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, pymethods, PyResult};
+use std::collections::VecDeque;
+
+/// A class implementing the Shiryaev-Roberts procedure for a known change in a normal mean.
+#[pyclass]
+pub struct ShiryaevRoberts {
+    pre_change: Normal,
+    post_change: Normal,
+    // statistic is kept in log space since it grows geometrically after a change
+    log_statistic: f64,
+    log_threshold: f64,
+}
+
+#[pymethods]
+impl ShiryaevRoberts {
+    #[new]
+    pub fn new(
+        pre_mean: f64,
+        pre_std_dev: f64,
+        post_mean: f64,
+        post_std_dev: f64,
+        threshold: f64,
+    ) -> PyResult<Self> {
+        let pre_change = positive_normal(pre_mean, pre_std_dev)?;
+        let post_change = positive_normal(post_mean, post_std_dev)?;
+        if !(threshold.is_finite() && threshold > 0.0) {
+            return Err(PyValueError::new_err("threshold must be positive and finite"));
+        }
+        Ok(Self {
+            pre_change,
+            post_change,
+            log_statistic: f64::NEG_INFINITY,
+            log_threshold: threshold.ln(),
+        })
+    }
+
+    /// Update statistic using given input value. R_n = (1 + R_{n-1}) * L(point).
+    pub fn update(&mut self, point: f64) {
+        let log_ratio = self.post_change.log_phi(point) - self.pre_change.log_phi(point);
+        self.log_statistic = log_one_plus_exp(self.log_statistic) + log_ratio;
+    }
+
+    /// Return the Shiryaev-Roberts statistic, restarting it if threshold was crossed.
+    pub fn predict(&mut self, _point: f64) -> f64 {
+        let out = self.log_statistic.exp();
+        if self.log_statistic > self.log_threshold {
+            self.reset();
+        }
+        out
+    }
+
+    pub fn statistic(&self) -> f64 {
+        self.log_statistic.exp()
+    }
+
+    pub fn log_statistic(&self) -> f64 {
+        self.log_statistic
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.log_threshold.exp()
+    }
+
+    fn reset(&mut self) {
+        self.log_statistic = f64::NEG_INFINITY;
+    }
+}
+
+/// A class implementing a windowed generalized likelihood ratio test for an unknown
+/// change in a normal mean.
+#[pyclass]
+pub struct WindowedGlr {
+    pre_change: Normal,
+    // deviations from the pre-change mean
+    window: VecDeque<f64>,
+    window_size: usize,
+    threshold: f64,
+    statistic: f64,
+    change_mean: f64,
+}
+
+#[pymethods]
+impl WindowedGlr {
+    #[new]
+    pub fn new(mean: f64, std_dev: f64, window_size: usize, threshold: f64) -> PyResult<Self> {
+        let pre_change = positive_normal(mean, std_dev)?;
+        if window_size == 0 {
+            return Err(PyValueError::new_err("window size must be positive"));
+        }
+        if !(threshold.is_finite() && threshold > 0.0) {
+            return Err(PyValueError::new_err("threshold must be positive and finite"));
+        }
+        Ok(Self {
+            pre_change,
+            window: VecDeque::with_capacity(window_size + 1),
+            window_size,
+            threshold,
+            statistic: 0.0,
+            change_mean: mean,
+        })
+    }
+
+    /// Update statistic using given input value.
+    ///
+    /// Maximizes the log likelihood ratio over every candidate change time in the window,
+    /// using the post-change mean estimate at each candidate.
+    pub fn update(&mut self, point: f64) {
+        let mean = self.pre_change.mean();
+        self.window.push_back(point - mean);
+        if self.window.len() > self.window_size {
+            self.window.pop_front();
+        }
+        let scale = 0.5 / self.pre_change.stddev().powi(2);
+        let mut total = 0.0;
+        let mut max_value = 0.0;
+        let mut change_mean = mean;
+        for (count, &deviation) in self.window.iter().rev().enumerate() {
+            total += deviation;
+            let size = (count + 1) as f64;
+            let value = scale * total.powi(2) / size;
+            if value > max_value {
+                max_value = value;
+                change_mean = mean + total / size;
+            }
+        }
+        self.statistic = max_value;
+        self.change_mean = change_mean;
+    }
+
+    /// Return the generalized likelihood ratio statistic, restarting if threshold was crossed.
+    pub fn predict(&mut self, _point: f64) -> f64 {
+        let out = self.statistic;
+        if out > self.threshold {
+            self.reset();
+        }
+        out
+    }
+
+    pub fn statistic(&self) -> f64 {
+        self.statistic
+    }
+
+    /// Return maximum likelihood estimate of the post-change mean.
+    pub fn change_mean(&self) -> f64 {
+        self.change_mean
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.statistic = 0.0;
+        self.change_mean = self.pre_change.mean();
+    }
+}
+
+fn positive_normal(mean: f64, std_dev: f64) -> PyResult<Normal> {
+    if std_dev == 0.0 {
+        return Err(PyValueError::new_err("standard deviation must be positive"));
+    }
+    Ok(Normal::new(mean, std_dev)?)
+}
+
+/// Return ln(1 + exp(value)) without overflowing.
+fn log_one_plus_exp(value: f64) -> f64 {
+    if value > 0.0 {
+        value + (-value).exp().ln_1p()
+    } else {
+        value.exp().ln_1p()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floats_close(a: f64, b: f64, epsilon: f64) -> bool {
+        (a - b).abs() < epsilon
+    }
+
+    #[test]
+    fn test_log_one_plus_exp() {
+        assert_eq!(log_one_plus_exp(f64::NEG_INFINITY), 0.0);
+        assert!(floats_close(log_one_plus_exp(0.0), 2.0_f64.ln(), 1e-12));
+        assert!(floats_close(log_one_plus_exp(1_000.0), 1_000.0, 1e-12));
+    }
+
+    // Shiryaev-Roberts tests
+    #[test]
+    fn test_shiryaev_roberts_bad_params() {
+        assert!(ShiryaevRoberts::new(0.0, 0.0, 1.0, 1.0, 100.0).is_err());
+        assert!(ShiryaevRoberts::new(0.0, 1.0, 1.0, -1.0, 100.0).is_err());
+        assert!(ShiryaevRoberts::new(0.0, 1.0, 1.0, 1.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_shiryaev_roberts_recursion() {
+        let mut model = ShiryaevRoberts::new(0.0, 1.0, 1.0, 1.0, 1e6).unwrap();
+        let points = [0.3, -0.2, 1.4];
+        let mut expected = 0.0;
+        for point in points {
+            // likelihood ratio for unit variance is exp(delta * (x - delta / 2))
+            expected = (1.0 + expected) * (point - 0.5_f64).exp();
+            model.update(point);
+            assert!(floats_close(model.predict(point), expected, 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_shiryaev_roberts_resets_after_alarm() {
+        let mut model = ShiryaevRoberts::new(0.0, 1.0, 2.0, 1.0, 100.0).unwrap();
+        let mut alarmed = false;
+        for _ in 0..20 {
+            model.update(2.0);
+            if model.predict(2.0) > model.threshold() {
+                alarmed = true;
+                break;
+            }
+        }
+        assert!(alarmed);
+        assert_eq!(model.statistic(), 0.0);
+    }
+
+    // Windowed GLR tests
+    #[test]
+    fn test_windowed_glr_bad_params() {
+        assert!(WindowedGlr::new(0.0, 0.0, 10, 5.0).is_err());
+        assert!(WindowedGlr::new(0.0, 1.0, 0, 5.0).is_err());
+        assert!(WindowedGlr::new(0.0, 1.0, 10, -1.0).is_err());
+        assert!(WindowedGlr::new(0.0, 1.0, 10, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_windowed_glr_statistic() {
+        let mut model = WindowedGlr::new(0.0, 1.0, 3, 100.0).unwrap();
+        model.update(0.0);
+        model.update(2.0);
+        model.update(2.0);
+        // best candidate uses the last two points, mean 2
+        assert!(floats_close(model.statistic(), 4.0, 1e-12));
+        assert!(floats_close(model.change_mean(), 2.0, 1e-12));
+        // window drops the first point
+        model.update(2.0);
+        assert!(floats_close(model.statistic(), 6.0, 1e-12));
+    }
+
+    #[test]
+    fn test_windowed_glr_resets_after_alarm() {
+        let mut model = WindowedGlr::new(0.0, 1.0, 10, 5.0).unwrap();
+        for _ in 0..5 {
+            model.update(3.0);
+        }
+        assert!(model.predict(3.0) > 5.0);
+        assert_eq!(model.statistic(), 0.0);
+        assert_eq!(model.change_mean(), 0.0);
+    }
+}
@@ -0,0 +1,254 @@
+//! Labeled synthetic streams for testing and benchmarking detectors.
+//!
+//! A stream is described by a list of segments that are generated back to back.
+//! The start of every segment after the first is a ground truth change point.
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, pyfunction, PyErr};
+use rand::distr::Distribution;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Normal, Poisson};
+use std::fmt;
+
+/// A piece of a synthetic stream. Mean and variance shifts are consecutive Normal segments.
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    /// Independent normal samples.
+    Normal { length: usize, mean: f64, std_dev: f64 },
+    /// Normal samples whose mean moves linearly from start towards end.
+    Drift { length: usize, start: f64, end: f64, std_dev: f64 },
+    /// First order autoregressive process around mean with innovations of std_dev.
+    Ar { length: usize, mean: f64, coef: f64, std_dev: f64 },
+    /// Normal samples where the first sample is shifted by magnitude.
+    Impulse { length: usize, mean: f64, std_dev: f64, magnitude: f64 },
+    /// Poisson counts with the given rate.
+    Poisson { length: usize, rate: f64 },
+}
+
+impl Segment {
+    pub fn length(&self) -> usize {
+        match *self {
+            Segment::Normal { length, .. }
+            | Segment::Drift { length, .. }
+            | Segment::Ar { length, .. }
+            | Segment::Impulse { length, .. }
+            | Segment::Poisson { length, .. } => length,
+        }
+    }
+
+    fn validate(&self) -> Result<(), SynthError> {
+        if self.length() == 0 {
+            return Err(SynthError::EmptySegment);
+        }
+        let check_finite = |name, value: f64| {
+            if value.is_finite() { Ok(()) } else { Err(SynthError::BadParameter(name, value)) }
+        };
+        let check_std_dev = |value: f64| {
+            if value.is_finite() && value >= 0.0 {
+                Ok(())
+            } else {
+                Err(SynthError::BadParameter("std_dev", value))
+            }
+        };
+        match *self {
+            Segment::Normal { mean, std_dev, .. } => {
+                check_finite("mean", mean)?;
+                check_std_dev(std_dev)
+            }
+            Segment::Drift { start, end, std_dev, .. } => {
+                check_finite("start", start)?;
+                check_finite("end", end)?;
+                check_std_dev(std_dev)
+            }
+            Segment::Ar { mean, coef, std_dev, .. } => {
+                check_finite("mean", mean)?;
+                if coef.abs() >= 1.0 || coef.is_nan() {
+                    return Err(SynthError::BadParameter("coef", coef));
+                }
+                check_std_dev(std_dev)
+            }
+            Segment::Impulse { mean, std_dev, magnitude, .. } => {
+                check_finite("mean", mean)?;
+                check_finite("magnitude", magnitude)?;
+                check_std_dev(std_dev)
+            }
+            Segment::Poisson { rate, .. } => {
+                // rejects rates that are not positive and finite, or above Poisson::MAX_LAMBDA
+                match Poisson::new(rate) {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(SynthError::BadParameter("rate", rate)),
+                }
+            }
+        }
+    }
+
+    fn generate_into<R: Rng + ?Sized>(&self, rng: &mut R, out: &mut Vec<f64>) {
+        match *self {
+            Segment::Normal { length, mean, std_dev } => {
+                out.extend(normal(mean, std_dev).sample_iter(rng).take(length));
+            }
+            Segment::Drift { length, start, end, std_dev } => {
+                let slope = (end - start) / length as f64;
+                let noise = normal(0.0, std_dev);
+                out.extend((0..length).map(|idx| start + slope * idx as f64 + noise.sample(rng)));
+            }
+            Segment::Ar { length, mean, coef, std_dev } => {
+                let noise = normal(0.0, std_dev);
+                // start from the stationary distribution so the segment has no warm up
+                let stationary = std_dev / (1.0 - coef * coef).sqrt();
+                let mut deviation = normal(0.0, stationary).sample(rng);
+                for _ in 0..length {
+                    out.push(mean + deviation);
+                    deviation = coef * deviation + noise.sample(rng);
+                }
+            }
+            Segment::Impulse { length, mean, std_dev, magnitude } => {
+                let first = out.len();
+                out.extend(normal(mean, std_dev).sample_iter(rng).take(length));
+                out[first] += magnitude;
+            }
+            Segment::Poisson { length, rate } => {
+                let dist = Poisson::new(rate).expect("Rate was validated");
+                out.extend(dist.sample_iter(rng).take(length));
+            }
+        }
+    }
+}
+
+fn normal(mean: f64, std_dev: f64) -> Normal<f64> {
+    Normal::new(mean, std_dev).expect("Parameters were validated")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SynthError {
+    EmptySegment,
+    BadParameter(&'static str, f64),
+}
+
+impl fmt::Display for SynthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SynthError::EmptySegment => write!(f, "Segment length must be positive"),
+            SynthError::BadParameter(name, value) => write!(f, "Bad {}: {}", name, value),
+        }
+    }
+}
+
+impl From<SynthError> for PyErr {
+    fn from(err: SynthError) -> PyErr {
+        PyValueError::new_err(format!("{}", err))
+    }
+}
+
+/// Return samples of the segments and the indices where each segment after the first starts.
+///
+/// Identical seeds give identical streams; without a seed the thread rng is used.
+#[pyfunction]
+#[pyo3(signature = (segments, seed=None))]
+pub fn generate_stream(
+    segments: Vec<Segment>,
+    seed: Option<u64>,
+) -> Result<(Vec<f64>, Vec<usize>), SynthError> {
+    match seed {
+        Some(seed) => generate_stream_with_rng(&segments, &mut StdRng::seed_from_u64(seed)),
+        None => generate_stream_with_rng(&segments, &mut rand::rng()),
+    }
+}
+
+/// Return samples of the segments and their change points using the given random number generator.
+pub fn generate_stream_with_rng<R: Rng + ?Sized>(
+    segments: &[Segment],
+    rng: &mut R,
+) -> Result<(Vec<f64>, Vec<usize>), SynthError> {
+    for segment in segments {
+        segment.validate()?;
+    }
+    let total = segments.iter().map(Segment::length).sum();
+    let mut data = Vec::with_capacity(total);
+    let mut changepoints = Vec::with_capacity(segments.len().saturating_sub(1));
+    for (idx, segment) in segments.iter().enumerate() {
+        if idx > 0 {
+            changepoints.push(data.len());
+        }
+        segment.generate_into(rng, &mut data);
+    }
+    Ok((data, changepoints))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mean(values: &[f64]) -> f64 {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+
+    fn variance(values: &[f64]) -> f64 {
+        let center = mean(values);
+        values.iter().map(|value| (value - center).powi(2)).sum::<f64>() / values.len() as f64
+    }
+
+    #[test]
+    fn test_generate_stream_changepoints() {
+        let segments = vec![
+            Segment::Normal { length: 100, mean: 0.0, std_dev: 1.0 },
+            Segment::Poisson { length: 50, rate: 3.0 },
+            Segment::Drift { length: 25, start: 0.0, end: 5.0, std_dev: 1.0 },
+        ];
+        let (data, changepoints) = generate_stream(segments, Some(1)).unwrap();
+        assert_eq!(data.len(), 175);
+        assert_eq!(changepoints, vec![100, 150]);
+        assert!(data[100..150].iter().all(|&count| count >= 0.0 && count.fract() == 0.0));
+    }
+
+    #[test]
+    fn test_generate_stream_seeded() {
+        let segments = vec![
+            Segment::Ar { length: 100, mean: 1.0, coef: 0.5, std_dev: 1.0 },
+            Segment::Impulse { length: 10, mean: 0.0, std_dev: 1.0, magnitude: 20.0 },
+        ];
+        let first = generate_stream(segments.clone(), Some(3)).unwrap();
+        assert_eq!(first, generate_stream(segments.clone(), Some(3)).unwrap());
+        assert_ne!(first, generate_stream(segments, Some(4)).unwrap());
+    }
+
+    #[test]
+    fn test_generate_stream_shifts() {
+        let segments = vec![
+            Segment::Normal { length: 5_000, mean: 0.0, std_dev: 1.0 },
+            Segment::Normal { length: 5_000, mean: 10.0, std_dev: 1.0 },
+            Segment::Normal { length: 5_000, mean: 10.0, std_dev: 5.0 },
+        ];
+        let (data, _) = generate_stream(segments, Some(5)).unwrap();
+        assert!((mean(&data[5_000..10_000]) - 10.0).abs() < 0.1);
+        assert!((variance(&data[10_000..]) - 25.0).abs() < 2.0);
+    }
+
+    #[test]
+    fn test_generate_stream_ar_and_impulse() {
+        let coef = 0.8;
+        let segments = vec![
+            Segment::Ar { length: 20_000, mean: 0.0, coef, std_dev: 1.0 },
+            Segment::Impulse { length: 10, mean: 0.0, std_dev: 0.0, magnitude: 7.0 },
+        ];
+        let (data, changepoints) = generate_stream(segments, Some(9)).unwrap();
+        let ar = &data[..20_000];
+        // stationary variance of an AR(1) process
+        assert!((variance(ar) - 1.0 / (1.0 - coef * coef)).abs() < 0.3);
+        assert_eq!(&data[changepoints[0]..], &[7.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_generate_stream_bad_segments() {
+        let empty = Segment::Normal { length: 0, mean: 0.0, std_dev: 1.0 };
+        assert_eq!(generate_stream(vec![empty], None), Err(SynthError::EmptySegment));
+        let explosive = Segment::Ar { length: 5, mean: 0.0, coef: 1.0, std_dev: 1.0 };
+        assert!(generate_stream(vec![explosive], None).is_err());
+        let rate = Segment::Poisson { length: 5, rate: 0.0 };
+        assert!(generate_stream(vec![rate], None).is_err());
+        let huge_rate = Segment::Poisson { length: 5, rate: 1e20 };
+        assert_eq!(generate_stream(vec![huge_rate], None), Err(SynthError::BadParameter("rate", 1e20)));
+        assert_eq!(generate_stream(vec![], None), Ok((vec![], vec![])));
+    }
+}
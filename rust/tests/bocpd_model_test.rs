@@ -10,7 +10,7 @@ fn test_bocpd_model_all_normal() {
     let mean = 0.0;
     let std_dev = 1.0;
     let num_unknowns = 100;
-    let data = generate_normal_data(mean, std_dev, num_unknowns);
+    let data = generate_normal_data(mean, std_dev, num_unknowns, Some(41));
     let lambda = 2.0;
     let mut model = BocpdModel::default();
     let mut count_safe = 0;
@@ -32,7 +32,7 @@ fn test_bocpd_model_all_normal_param_size() {
     let mean = 0.0;
     let std_dev = 1.0;
     let num_unknowns = 500_000;
-    let data = generate_normal_data(mean, std_dev, num_unknowns);
+    let data = generate_normal_data(mean, std_dev, num_unknowns, Some(42));
     let lambda = 20.0;
     let mut model = BocpdModel::default();
     let mut max_length = 0;
@@ -0,0 +1,95 @@
+use _change_point_algorithms::cusum::CusumV0;
+use helpers::generate_normal_data;
+use std::iter::zip;
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Output, Stdio};
+
+mod helpers;
+
+/// Run the changepoint binary with given arguments and data piped to stdin.
+fn run_cli(args: &[&str], data: &[f64]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_changepoint"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("binary should start");
+    let input: String = data.iter().map(|value| format!("{}\n", value)).collect();
+    let written = child.stdin.take().expect("stdin is piped").write_all(input.as_bytes());
+    // the binary may reject its arguments and exit before reading any input
+    if let Err(err) = written
+        && err.kind() != ErrorKind::BrokenPipe
+    {
+        panic!("binary should read stdin: {}", err);
+    }
+    child.wait_with_output().expect("binary should finish")
+}
+
+fn generate_shifted_data() -> Vec<f64> {
+    let mut data = generate_normal_data(0.0, 1.0, 200, Some(61));
+    data.extend(generate_normal_data(50.0, 1.0, 200, Some(62)));
+    data
+}
+
+#[test]
+fn test_cli_writes_scores() {
+    let data = generate_normal_data(0.0, 1.0, 100, Some(63));
+    let output = run_cli(&["--detector", "cusum-v0", "--alpha", "0.5", "--h", "3"], &data);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some("index,value,score,alarm"));
+    assert_eq!(lines.count(), data.len());
+}
+
+#[test]
+fn test_cli_alarms_only() {
+    let data = generate_shifted_data();
+    let output = run_cli(&["--detector", "cusum-v1", "--alpha", "0.5", "--h", "3", "--alarms-only"], &data);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let alarms: Vec<usize> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    let late_alarms = alarms.iter().filter(|&&idx| idx >= 200).count();
+    assert!(late_alarms >= 100, "alarms: {:?}", alarms);
+}
+
+#[test]
+fn test_cli_config_file() {
+    let path = std::env::temp_dir().join(format!("changepoint_cli_{}.toml", std::process::id()));
+    std::fs::write(&path, "detector = \"em\"\n\n[em]\nnormal = [0.0, 1.0, 0.7]\nabnormals = [[50.0, 2.0, 0.3]]\narr_sizes = [70, 30]\nepochs = 5\n").unwrap();
+    let data = generate_shifted_data();
+    let output = run_cli(&["--config", path.to_str().unwrap(), "--alarms-only"], &data);
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let alarm_count = String::from_utf8(output.stdout).unwrap().lines().count();
+    assert!((100..=300).contains(&alarm_count), "alarm count: {}", alarm_count);
+}
+
+#[test]
+fn test_cli_rejects_missing_detector() {
+    let output = run_cli(&["--lamb", "50"], &[0.0]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cli_cusum_v0_resets_where_it_alarms() {
+    let mut data = generate_normal_data(0.0, 2.0, 200, Some(3));
+    data.extend(generate_normal_data(50.0, 2.0, 200, Some(4)));
+    let output = run_cli(&["--detector", "cusum-v0", "--std-dev", "2", "--alpha", "0.5", "--h", "3"], &data);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    // threshold is h standard deviations for both resetting and alarming
+    let threshold = 6.0;
+    let mut model = CusumV0::new(0.0, 4.0, 0.5, threshold);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    for (line, &point) in zip(stdout.lines().skip(1), &data) {
+        let fields: Vec<&str> = line.split(',').collect();
+        model.update(point);
+        let expected = model.predict(point);
+        assert_eq!(fields[2].parse::<f64>().unwrap(), expected, "line: {}", line);
+        assert_eq!(fields[3] == "1", expected > threshold, "line: {}", line);
+    }
+}
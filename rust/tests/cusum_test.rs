@@ -7,14 +7,14 @@ fn generate_data() -> Vec<f64> {
     let mean = 0.0;
     let std_dev = 1.0;
     let num = 1_000;
-    generate_normal_data(mean, std_dev, num)
+    generate_normal_data(mean, std_dev, num, Some(21))
 }
 
 fn generate_abnormal_data() -> Vec<f64> {
     let mean = 50.0;
     let std_dev = 1.0;
     let num = 1_000;
-    generate_normal_data(mean, std_dev, num)
+    generate_normal_data(mean, std_dev, num, Some(22))
 }
 
 // CusumV0 tests
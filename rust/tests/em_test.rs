@@ -1,5 +1,8 @@
 use _change_point_algorithms::expect_max::{em_model::EmModel, em_early_stop_model::EarlyStopEmModel};
+use _change_point_algorithms::expect_max::component::MixtureComponent;
 use _change_point_algorithms::expect_max::em_model_builder;
+use _change_point_algorithms::expect_max::hmm::HmmModel;
+use _change_point_algorithms::expect_max::online_em_model::OnlineEmModel;
 use _change_point_algorithms::expect_max::em_early_stop_model::EmLikelihoodCheck;
 use helpers::generate_normal_data;
 
@@ -9,14 +12,14 @@ fn generate_data() -> Vec<f64> {
     let mean = 0.0;
     let std_dev = 1.0;
     let num = 1_000;
-    generate_normal_data(mean, std_dev, num)
+    generate_normal_data(mean, std_dev, num, Some(31))
 }
 
 fn generate_abnormal_data() -> Vec<f64> {
     let mean = 50.0;
     let std_dev = 2.0;
     let num = 1_000;
-    generate_normal_data(mean, std_dev, num)
+    generate_normal_data(mean, std_dev, num, Some(32))
 }
 
 // #[test]
@@ -27,16 +30,13 @@ fn generate_abnormal_data() -> Vec<f64> {
 #[test]
 fn test_em_all_normal() {
     let data = generate_data();
-    let mut builder = em_model_builder::EmBuilderOne::new();
-    let early_stop_builder = builder
+    let early_stop_builder = em_model_builder::EmBuilder::new()
         .build_normal(0.0, 1.0, 0.7).unwrap()
         .build_abnormal_from_tuples(&[(50.0, 2.0, 0.3)]).unwrap()
         .build_samples_from_slice(&[0.0, -0.2, 0.2, -1.0, 1.0, -0.5, 0.5, 50.0, 49.0, 51.0])
-        .next_builder().unwrap()
         .build_likelihoods()
-        .next_builder().unwrap()
         .build_likelihood_converge_checker()
-        .get_early_stop_model();
+        .get_model();
     let mut model: EmLikelihoodCheck = EmLikelihoodCheck::from_early_stop_model(early_stop_builder);
     let threshold = 1e-8;
     let boundary = 0.5;
@@ -57,16 +57,13 @@ fn test_em_all_normal() {
 #[test]
 fn test_em_all_abnormal() {
     let data = generate_abnormal_data();
-    let mut builder = em_model_builder::EmBuilderOne::new();
-    let early_stop_builder = builder
+    let early_stop_builder = em_model_builder::EmBuilder::new()
         .build_normal(0.0, 1.0, 0.7).unwrap()
         .build_abnormal_from_tuples(&[(50.0, 2.0, 0.3)]).unwrap()
         .build_samples_from_slice(&[0.0, -0.2, 0.2, -1.0, 1.0, -0.5, 0.5, 50.0, 49.0, 51.0])
-        .next_builder().unwrap()
         .build_likelihoods()
-        .next_builder().unwrap()
         .build_likelihood_converge_checker()
-        .get_early_stop_model();
+        .get_model();
     let mut model: EmLikelihoodCheck = EmLikelihoodCheck::from_early_stop_model(early_stop_builder);
     let threshold = 1e-8;
     let boundary = 0.5;
@@ -82,4 +79,333 @@ fn test_em_all_abnormal() {
         }
     }
     assert!(count_unsafe >= count_safe, "count_safe: {}, count_unsafe: {}", count_safe, count_unsafe);
-}
\ No newline at end of file
+}
+
+fn build_seeded_model(seed: u64) -> EmModel {
+    let params = [(0.0, 1.0, 0.7), (50.0, 2.0, 0.3)];
+    let samples = em_model_builder::generate_samples(&params, &[70, 30], Some(seed)).unwrap();
+    em_model_builder::EmBuilder::new()
+        .build_normal(0.0, 1.0, 0.7).unwrap()
+        .build_abnormal_from_tuples(&params[1..]).unwrap()
+        .build_epochs(10).unwrap()
+        .build_samples_from_slice(&samples)
+        .build_likelihoods()
+        .get_model()
+}
+
+#[test]
+fn test_em_seeded_models_identical() {
+    let data = generate_normal_data(5.0, 10.0, 200, Some(7));
+    assert_eq!(data, generate_normal_data(5.0, 10.0, 200, Some(7)));
+    let mut first = build_seeded_model(42);
+    let mut second = build_seeded_model(42);
+    for event in data {
+        first.update(event).unwrap();
+        second.update(event).unwrap();
+        assert_eq!(first.predict(event), second.predict(event));
+    }
+}
+
+#[test]
+fn test_online_em_normal_then_abnormal() {
+    let mut model = OnlineEmModel::py_new((0.0, 1.0, 0.9), vec![(50.0, 2.0, 0.1)], 1.0, 0.6, 10.0, 0.01).unwrap();
+    let boundary = 0.5;
+    let normal = generate_normal_data(0.0, 1.0, 1_000, Some(1));
+    let safe = normal.iter().filter(|&&event| {
+        model.update(event).unwrap();
+        model.predict(event) >= boundary
+    }).count();
+    assert!(safe >= 990, "safe: {}", safe);
+    let abnormal = generate_normal_data(50.0, 2.0, 100, Some(2));
+    let detected = abnormal.iter().filter(|&&event| {
+        model.update(event).unwrap();
+        model.predict(event) < boundary
+    }).count();
+    assert!(detected >= 90, "detected: {}", detected);
+}
+
+#[test]
+fn test_em_fifo_retention_tracks_stream() {
+    use _change_point_algorithms::expect_max::retention::RetentionPolicy;
+    let params = [(0.0, 1.0, 0.9), (50.0, 2.0, 0.1)];
+    let samples = em_model_builder::generate_samples(&params, &[90, 10], Some(3)).unwrap();
+    let build = |policy| {
+        em_model_builder::EmBuilder::new()
+            .build_normal(0.0, 1.0, 0.9).unwrap()
+            .build_abnormal_from_tuples(&params[1..]).unwrap()
+            .build_retention(policy).unwrap()
+            .build_samples_from_slice(&samples)
+            .build_likelihoods()
+            .get_model()
+    };
+    let mut swap_last = build(RetentionPolicy::SwapLast());
+    let mut fifo = build(RetentionPolicy::Fifo { capacity: 100 });
+    assert_eq!(fifo.sample_count(), 100);
+    for event in generate_normal_data(5.0, 1.0, 300, Some(4)) {
+        swap_last.update(event).unwrap();
+        fifo.update(event).unwrap();
+    }
+    assert_eq!(fifo.sample_count(), 100);
+    // the window only holds stream points, so the normal component follows the new level
+    assert!((fifo.normal().mean() - 5.0).abs() < 0.5, "fifo: {:?}", fifo.normal());
+    assert!(swap_last.normal().mean().abs() < 0.5, "swap last: {:?}", swap_last.normal());
+}
+
+#[test]
+fn test_em_variance_floor_on_constant_stream() {
+    use _change_point_algorithms::expect_max::diagnostics::EmEvent;
+    use _change_point_algorithms::expect_max::em_model::EmOptions;
+    use _change_point_algorithms::expect_max::retention::RetentionPolicy;
+    let params = [(0.0, 1.0, 0.9), (50.0, 2.0, 0.1)];
+    let samples = em_model_builder::generate_samples(&params, &[90, 10], Some(8)).unwrap();
+    let options = EmOptions { variance_floor: 0.01, reinitialize: true, ..EmOptions::default() };
+    let mut model = em_model_builder::EmBuilder::new()
+        .build_normal(0.0, 1.0, 0.9).unwrap()
+        .build_abnormal_from_tuples(&params[1..]).unwrap()
+        .build_retention(RetentionPolicy::Fifo { capacity: 50 }).unwrap()
+        .build_options(options).unwrap()
+        .build_samples_from_slice(&samples)
+        .build_likelihoods()
+        .get_model();
+    let mut floored = false;
+    // once the window only holds the constant, every component would lose its variance
+    for _ in 0..100 {
+        let diagnostics = model.update(3.0).unwrap();
+        floored |= diagnostics.events.iter().any(|event| matches!(event, EmEvent::VarianceFloored { .. }));
+    }
+    assert!(floored);
+    assert!((model.normal().mean() - 3.0).abs() < 1e-9, "normal: {:?}", model.normal());
+    assert!(model.normal().stddev() >= 0.1 - 1e-12, "normal: {:?}", model.normal());
+    assert!(model.predict(3.0).is_finite());
+}
+
+#[test]
+fn test_em_map_priors_keep_sparse_component() {
+    use _change_point_algorithms::bocpd::normal_inverse_gamma::NormalInverseGamma;
+    let mut samples = generate_normal_data(0.0, 1.0, 200, Some(11));
+    // a sparse abnormal class of identical readings
+    samples.extend([20.0, 20.0]);
+    let build = |map: bool| {
+        let mut builder = em_model_builder::EmBuilder::new()
+            .build_normal(0.0, 1.0, 0.9).unwrap()
+            .build_abnormal_from_tuples(&[(20.0, 2.0, 0.1)]).unwrap();
+        if map {
+            let prior = NormalInverseGamma { alpha: 3.0, beta: 18.0, mu: 20.0, kappa: 1.0 };
+            builder = builder
+                .build_component_prior(1, prior).unwrap()
+                .build_weight_prior(&[1.0, 5.0]).unwrap();
+        }
+        builder
+            .build_samples_from_slice(&samples)
+            .build_likelihoods()
+            .get_model()
+    };
+    let mut ml = build(false);
+    let mut map = build(true);
+    ml.update(0.0).unwrap();
+    let map_diagnostics = map.update(0.0).unwrap();
+    // maximum likelihood shrinks the abnormal component onto its two samples
+    assert!(ml.abnormals()[0].stddev() < 0.1, "abnormal: {:?}", ml.abnormals()[0]);
+    assert!(map_diagnostics.events.is_empty());
+    let abnormal = map.abnormals()[0];
+    assert!((abnormal.mean() - 20.0).abs() < 1e-6, "abnormal: {:?}", abnormal);
+    // beta / (alpha + 1 + 1.5) with two samples at the prior mean
+    assert!((abnormal.stddev() - (18.0f64 / 5.5).sqrt()).abs() < 1e-6, "abnormal: {:?}", abnormal);
+    // four Dirichlet pseudo-samples are added to the two abnormal ones
+    assert!((abnormal.weight() - 6.0 / 207.0).abs() < 1e-6, "abnormal: {:?}", abnormal);
+    assert!(map.predict(17.0) < 0.01);
+}
+
+#[test]
+fn test_em_mixed_component_families() {
+    use _change_point_algorithms::expect_max::component::{Component, LogNormal, StudentT};
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use rand_distr::Distribution;
+    let mut samples = generate_normal_data(5.0, 1.0, 300, Some(12));
+    let impacts = rand_distr::LogNormal::new(3.0, 0.3).unwrap();
+    samples.extend(impacts.sample_iter(StdRng::seed_from_u64(13)).take(60));
+    let normal: Component = StudentT::new(4.0, 2.0, 5.0, 0.8).unwrap().into();
+    let abnormal: Component = LogNormal::new(2.5, 0.5, 0.2).unwrap().into();
+    let mut model = em_model_builder::EmBuilder::new()
+        .build_normal_component(normal).unwrap()
+        .build_abnormal_components(&[abnormal]).unwrap()
+        .build_epochs(30).unwrap()
+        .build_samples_from_slice(&samples)
+        .build_likelihoods()
+        .get_model();
+    let diagnostics = model.update(5.0).unwrap();
+    assert!(diagnostics.events.is_empty());
+    let parameters = model.parameters();
+    assert!((parameters[0].0 - 5.0).abs() < 0.2, "parameters: {:?}", parameters);
+    // log-normal parameters are on the log scale
+    assert!((parameters[1].0 - 3.0).abs() < 0.1, "parameters: {:?}", parameters);
+    assert!((parameters[1].1 - 0.3).abs() < 0.1, "parameters: {:?}", parameters);
+    assert!((model.abnormals()[0].weight() - 60.0 / 361.0).abs() < 0.02);
+    assert!(model.predict(5.0) > 0.99);
+    assert!(model.predict(25.0) < 0.01);
+    // a log-normal component cannot explain non-positive readings
+    assert_eq!(model.predict(-3.0), 1.0);
+}
+
+#[test]
+fn test_em_multivariate_recovers_correlation() {
+    use _change_point_algorithms::expect_max::multivariate_em_model::{CovarianceType, MultivariateEmModel};
+    let xs = generate_normal_data(0.0, 1.0, 400, Some(21));
+    let noise = generate_normal_data(0.0, 0.4, 400, Some(22));
+    let mut rows: Vec<Vec<f64>> = xs.iter().zip(&noise).map(|(x, e)| vec![*x, 0.9 * x + e]).collect();
+    let faults_x = generate_normal_data(6.0, 0.5, 40, Some(23));
+    let faults_y = generate_normal_data(-6.0, 0.5, 40, Some(24));
+    rows.extend(faults_x.iter().zip(&faults_y).map(|(x, y)| vec![*x, *y]));
+    let build = |covariance_type| {
+        let mut builder = MultivariateEmModel::builder();
+        builder
+            .build_normal(vec![0.5, 0.5], vec![vec![1.0, 0.0], vec![0.0, 1.0]], 0.8).unwrap()
+            .build_abnormal_from_tuples(&[(vec![4.0, -4.0], vec![vec![1.0, 0.0], vec![0.0, 1.0]], 0.2)]).unwrap()
+            .build_samples_from_rows(&rows).unwrap()
+            .build_covariance_type(covariance_type)
+            .build_epochs(30).unwrap();
+        builder.get_model().unwrap()
+    };
+    let mut full = build(CovarianceType::Full);
+    let diagnostics = full.update(vec![0.0, 0.0]).unwrap();
+    assert!(diagnostics.events.is_empty());
+    let covariance = full.components()[0].covariance();
+    let correlation = covariance[[0, 1]] / (covariance[[0, 0]] * covariance[[1, 1]]).sqrt();
+    assert!(correlation > 0.85, "covariance: {:?}", covariance);
+    assert!((full.components()[1].mean()[0] - 6.0).abs() < 0.3);
+    assert!(full.predict(vec![1.0, 0.9]).unwrap() > 0.99);
+    assert!(full.predict(vec![6.0, -6.0]).unwrap() < 0.01);
+    // a point off the correlation line is only unusual once channels may correlate
+    let mut diagonal = build(CovarianceType::Diagonal);
+    diagonal.update(vec![0.0, 0.0]).unwrap();
+    let off_line = ndarray::array![1.5, -1.5];
+    let full_density = full.components()[0].log_density(off_line.view());
+    assert!(full_density < diagonal.components()[0].log_density(off_line.view()) - 5.0);
+}
+
+#[test]
+fn test_em_convergence_checkers_separate_normal_and_abnormal() {
+    use _change_point_algorithms::expect_max::em_early_stop_model::{EmAitkenCheck, EmLogLikelihoodCheck, EmParameterCheck};
+    let models = (build_seeded_model(5), build_seeded_model(5), build_seeded_model(5));
+    let mut log_likelihood = EmLogLikelihoodCheck::new(models.0);
+    let mut parameter = EmParameterCheck::new(models.1);
+    let mut aitken = EmAitkenCheck::new(models.2);
+    let threshold = 1e-6;
+    for event in generate_normal_data(0.0, 1.0, 100, Some(6)) {
+        log_likelihood.update_check_convergence(event, threshold).unwrap();
+        parameter.update_check_convergence(event, threshold).unwrap();
+        aitken.update_check_convergence(event, threshold).unwrap();
+    }
+    for (event, expected) in [(0.0, true), (50.0, false)] {
+        assert_eq!(log_likelihood.predict(event) >= 0.5, expected);
+        assert_eq!(parameter.predict(event) >= 0.5, expected);
+        assert_eq!(aitken.predict(event) >= 0.5, expected);
+    }
+}
+
+#[test]
+fn test_em_initialization_finds_unknown_components() {
+    use _change_point_algorithms::expect_max::initialization::InitStrategy;
+    let mut samples = generate_normal_data(0.0, 1.0, 300, Some(31));
+    samples.extend(generate_normal_data(8.0, 1.0, 40, Some(32)));
+    samples.extend(generate_normal_data(-8.0, 0.5, 20, Some(33)));
+    for strategy in [InitStrategy::KMeansPlusPlus, InitStrategy::Quantiles] {
+        let mut model = em_model_builder::EmBuilder::new()
+            .build_epochs(20).unwrap()
+            .build_samples_from_slice(&samples)
+            .build_initialization(2, strategy, 5, Some(34)).unwrap()
+            .build_likelihoods()
+            .get_model();
+        model.update(0.0).unwrap();
+        let parameters = model.parameters();
+        assert!(parameters[0].0.abs() < 0.2, "{:?}: {:?}", strategy, parameters);
+        assert!((parameters[1].0 + 8.0).abs() < 0.3, "{:?}: {:?}", strategy, parameters);
+        assert!((parameters[2].0 - 8.0).abs() < 0.3, "{:?}: {:?}", strategy, parameters);
+        assert!(model.predict(0.5) > 0.99);
+        assert!(model.predict(8.0) < 0.01);
+    }
+}
+
+#[test]
+fn test_em_selection_finds_number_of_regimes() {
+    use _change_point_algorithms::expect_max::initialization::InitStrategy;
+    use _change_point_algorithms::expect_max::model_selection::Criterion;
+    let mut samples = generate_normal_data(0.0, 1.0, 400, Some(41));
+    samples.extend(generate_normal_data(7.0, 1.0, 60, Some(42)));
+    samples.extend(generate_normal_data(-7.0, 1.0, 60, Some(43)));
+    for criterion in [Criterion::Aic, Criterion::Bic, Criterion::Icl] {
+        let (builder, scores) = em_model_builder::EmBuilder::new()
+            .build_epochs(30).unwrap()
+            .build_samples_from_slice(&samples)
+            .build_selected_components(&[0, 1, 2, 3], criterion, InitStrategy::KMeansPlusPlus, 3, Some(44))
+            .unwrap();
+        let best = scores
+            .iter()
+            .min_by(|left, right| left.score(criterion).total_cmp(&right.score(criterion)))
+            .unwrap();
+        assert_eq!(best.abnormal_count, 2, "{:?}: {:?}", criterion, scores);
+        let model = builder
+            .build_likelihoods()
+            .get_model();
+        assert_eq!(model.abnormals().len(), 2);
+        assert!(model.predict(0.0) > 0.99);
+    }
+}
+
+#[test]
+fn test_em_classify_abnormal_regimes() {
+    let mut samples = generate_normal_data(0.0, 1.0, 300, Some(51));
+    samples.extend(generate_normal_data(10.0, 1.0, 30, Some(52)));
+    samples.extend(generate_normal_data(-10.0, 1.0, 30, Some(53)));
+    let mut model = em_model_builder::EmBuilder::new()
+        .build_normal(0.0, 1.0, 0.8).unwrap()
+        .build_abnormal_from_tuples(&[(-8.0, 2.0, 0.1), (8.0, 2.0, 0.1)]).unwrap()
+        .build_epochs(20).unwrap()
+        .build_samples_from_slice(&samples)
+        .build_likelihoods()
+        .get_model();
+    model.update(0.0).unwrap();
+    assert_eq!(model.classify(0.3).0, 0);
+    assert_eq!(model.classify(-9.5).0, 1);
+    assert_eq!(model.classify(11.0).0, 2);
+    // predict only separates normal from abnormal, predict_proba tells the regimes apart
+    let proba = model.predict_proba(11.0);
+    assert_eq!(proba[0], model.predict(11.0));
+    assert!(proba[2] > 0.99 && proba[1] < 1e-6);
+    // between the regimes every component explains the point poorly
+    assert!(model.novelty_score(5.0) > model.novelty_score(0.0));
+    assert!(model.novelty_score(5.0) > model.novelty_score(10.0));
+    assert!(model.novelty_score(100.0) > 1000.0);
+}
+
+#[test]
+fn test_hmm_segments_overlapping_regimes() {
+    let mut samples = generate_normal_data(0.0, 1.0, 200, Some(61));
+    samples.extend(generate_normal_data(3.0, 1.0, 100, Some(62)));
+    samples.extend(generate_normal_data(0.0, 1.0, 200, Some(63)));
+    let mut model = HmmModel::builder()
+        .build_normal(0.0, 1.0, 0.5).unwrap()
+        .build_abnormal_from_tuples(&[(2.0, 1.0, 0.5)]).unwrap()
+        .build_samples_from_slice(&samples)
+        .build_epochs(30).unwrap()
+        .get_model().unwrap();
+    let diagnostics = model.train().unwrap();
+    assert!(diagnostics.log_likelihoods.windows(2).all(|pair| pair[1] >= pair[0] - 1e-6));
+    let parameters = model.parameters();
+    assert!((parameters[1].0 - 3.0).abs() < 0.5, "{:?}", parameters);
+    // regimes overlap, so points alone flicker but the learned transitions keep segments whole
+    let path = model.viterbi(samples.clone());
+    let switches = path.windows(2).filter(|pair| pair[0] != pair[1]).count();
+    assert!(switches <= 4, "switches: {}", switches);
+    let wrong = path.iter().enumerate().filter(|&(idx, &state)| (state == 1) != (200..300).contains(&idx)).count();
+    assert!(wrong < 25, "wrong: {}", wrong);
+    let flips = samples
+        .iter()
+        .map(|&point| model.update(point) < 0.5)
+        .collect::<Vec<_>>()
+        .windows(2)
+        .filter(|pair| pair[0] != pair[1])
+        .count();
+    assert!(flips <= 6, "flips: {}", flips);
+}
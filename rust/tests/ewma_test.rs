@@ -0,0 +1,48 @@
+use _change_point_algorithms::ewma::EwmaChart;
+use helpers::generate_normal_data;
+
+mod helpers;
+
+/// Return number of alarms raised over data.
+fn count_alarms(model: &mut EwmaChart, data: &[f64], l_sigma: f64) -> usize {
+    let mut alarms = 0;
+    for &event in data {
+        model.update(event);
+        if model.predict(event) > l_sigma {
+            alarms += 1;
+        }
+    }
+    alarms
+}
+
+#[test]
+fn test_ewma_all_abnormal() {
+    let data = generate_normal_data(50.0, 1.0, 1_000, Some(17));
+    let l_sigma = 3.0;
+    let mut model = EwmaChart::new(0.0, 1.0, 0.2, l_sigma).unwrap();
+    let alarms = count_alarms(&mut model, &data, l_sigma);
+    assert!(alarms >= data.len() / 2, "alarms: {}", alarms);
+}
+
+#[test]
+fn test_ewma_shewhart_false_alarm_rate() {
+    // With lamb = 1 the chart reduces to a Shewhart chart, false alarm rate 2 * (1 - Phi(3))
+    let data = generate_normal_data(0.0, 1.0, 200_000, Some(18));
+    let l_sigma = 3.0;
+    let mut model = EwmaChart::new(0.0, 1.0, 1.0, l_sigma).unwrap();
+    let alarms = count_alarms(&mut model, &data, l_sigma);
+    let rate = alarms as f64 / data.len() as f64;
+    assert!((0.0022..0.0032).contains(&rate), "false alarm rate: {}", rate);
+}
+
+#[test]
+fn test_ewma_in_control_average_run_length() {
+    // lamb = 0.2 and L = 2.962 give an in-control average run length of about 500
+    // (Lucas and Saccucci, 1990). Exact limits shorten it slightly.
+    let data = generate_normal_data(0.0, 1.0, 400_000, Some(19));
+    let l_sigma = 2.962;
+    let mut model = EwmaChart::new(0.0, 1.0, 0.2, l_sigma).unwrap();
+    let alarms = count_alarms(&mut model, &data, l_sigma);
+    let arl = data.len() as f64 / alarms as f64;
+    assert!((400.0..560.0).contains(&arl), "average run length: {}", arl);
+}
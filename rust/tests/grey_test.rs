@@ -0,0 +1,61 @@
+use _change_point_algorithms::grey::{GreyModel, GreyModelV2};
+use helpers::generate_normal_data;
+
+mod helpers;
+
+fn make_model() -> GreyModel {
+    let window_size = 4;
+    let c = 3.0;
+    let c_ratio = 300.0;
+    let threshold = 0.5;
+    GreyModel::new(window_size, c, c_ratio, threshold).unwrap()
+}
+
+#[test]
+fn test_grey_all_normal() {
+    let data = generate_normal_data(10.0, 0.1, 1_000, Some(51));
+    let mut model = make_model();
+    let mut count_safe = 0;
+    let mut count_unsafe = 0;
+    for event in data {
+        model.update(event.abs());
+        if model.is_change() {
+            count_unsafe += 1;
+        } else {
+            count_safe += 1;
+        }
+    }
+    assert!(count_safe >= count_unsafe, "count_safe: {}, count_unsafe: {}", count_safe, count_unsafe);
+}
+
+#[test]
+fn test_grey_after_change() {
+    let mut data = generate_normal_data(10.0, 0.1, 10, Some(52));
+    data.extend(generate_normal_data(50.0, 0.1, 1_000, Some(53)));
+    let mut model = make_model();
+    let mut count_safe = 0;
+    let mut count_unsafe = 0;
+    for event in data {
+        model.update(event.abs());
+        if model.is_change() {
+            count_unsafe += 1;
+        } else {
+            count_safe += 1;
+        }
+    }
+    assert!(count_unsafe >= count_safe, "count_safe: {}, count_unsafe: {}", count_safe, count_unsafe);
+}
+
+#[test]
+fn test_grey_v2_detects_level_shift() {
+    let mut data = generate_normal_data(1.0, 0.1, 100, Some(5));
+    data.extend(generate_normal_data(50.0, 0.1, 100, Some(6)));
+    let mut model = GreyModelV2::new(3, 0.5, 0.15).unwrap();
+    let mut changes = Vec::new();
+    for event in data {
+        model.update(event.abs());
+        changes.push(model.is_change());
+    }
+    assert!(changes[..100].iter().all(|&change| !change));
+    assert!(changes[100..].iter().all(|&change| change));
+}
@@ -1,8 +1,17 @@
 use rand::distr::Distribution;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rand_distr::Normal;
 
-pub fn generate_normal_data(mean: f64, std_dev: f64, num: usize) -> Vec<f64> {
-    let rng = rand::rng();
+/// Draw normal samples. Identical seeds give identical samples; without a seed the thread rng is used.
+pub fn generate_normal_data(mean: f64, std_dev: f64, num: usize, seed: Option<u64>) -> Vec<f64> {
+    match seed {
+        Some(seed) => generate_normal_data_with_rng(mean, std_dev, num, &mut StdRng::seed_from_u64(seed)),
+        None => generate_normal_data_with_rng(mean, std_dev, num, &mut rand::rng()),
+    }
+}
+
+pub fn generate_normal_data_with_rng<R: Rng + ?Sized>(mean: f64, std_dev: f64, num: usize, rng: &mut R) -> Vec<f64> {
     let normal = Normal::new(mean, std_dev).unwrap_or_else(|_| Normal::new(0.0, 1.0).expect("Standard normal distribution should never fail to initialize"));
     normal.sample_iter(rng).take(num).collect()
-}
\ No newline at end of file
+}
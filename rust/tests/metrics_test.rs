@@ -0,0 +1,28 @@
+use _change_point_algorithms::cusum::CusumV0;
+use _change_point_algorithms::metrics::{alarm_counts, detection_delays};
+use helpers::generate_normal_data;
+
+mod helpers;
+
+#[test]
+fn test_metrics_score_cusum_alarms() {
+    let changepoint = 500;
+    let mut data = generate_normal_data(0.0, 1.0, changepoint, Some(71));
+    data.extend(generate_normal_data(50.0, 1.0, 500, Some(72)));
+    let threshold = 3.0;
+    let mut model = CusumV0::new(0.0, 1.0, 0.5, threshold);
+    let mut alarms = Vec::new();
+    for (idx, &event) in data.iter().enumerate() {
+        model.update(event);
+        if model.predict(event) > threshold {
+            alarms.push(idx);
+        }
+    }
+    let tolerance = 10;
+    let delays = detection_delays(vec![changepoint], alarms.clone(), tolerance);
+    assert!(matches!(delays[..], [Some(delay)] if delay <= tolerance), "delays: {:?}", delays);
+    let counts = alarm_counts(vec![changepoint], alarms, tolerance);
+    assert_eq!(counts.detected, 1);
+    assert_eq!(counts.missed, 0);
+    assert!(counts.recall() == 1.0);
+}
@@ -0,0 +1,83 @@
+use _change_point_algorithms::quickest::{ShiryaevRoberts, WindowedGlr};
+use helpers::generate_normal_data;
+
+mod helpers;
+
+fn generate_data() -> Vec<f64> {
+    let mean = 0.0;
+    let std_dev = 1.0;
+    let num = 1_000;
+    generate_normal_data(mean, std_dev, num, Some(81))
+}
+
+fn generate_abnormal_data() -> Vec<f64> {
+    let mean = 50.0;
+    let std_dev = 1.0;
+    let num = 1_000;
+    generate_normal_data(mean, std_dev, num, Some(82))
+}
+
+/// Feed data to the detector and return the number of (safe, unsafe) predictions.
+fn count_predictions(data: &[f64], threshold: f64, mut step: impl FnMut(f64) -> f64) -> (usize, usize) {
+    let count_unsafe = data.iter().filter(|&&event| step(event) >= threshold).count();
+    (data.len() - count_unsafe, count_unsafe)
+}
+
+// ShiryaevRoberts tests
+#[test]
+fn test_shiryaev_roberts_all_normal() {
+    let threshold = 1e4;
+    let mut model = ShiryaevRoberts::new(0.0, 1.0, 3.0, 1.0, threshold).unwrap();
+    let (count_safe, count_unsafe) = count_predictions(&generate_data(), threshold, |event| {
+        model.update(event);
+        model.predict(event)
+    });
+    assert!(count_safe >= count_unsafe, "count_safe: {}, count_unsafe: {}", count_safe, count_unsafe);
+}
+
+#[test]
+fn test_shiryaev_roberts_all_abnormal() {
+    let threshold = 1e4;
+    let mut model = ShiryaevRoberts::new(0.0, 1.0, 3.0, 1.0, threshold).unwrap();
+    let (count_safe, count_unsafe) = count_predictions(&generate_abnormal_data(), threshold, |event| {
+        model.update(event);
+        model.predict(event)
+    });
+    assert!(count_unsafe >= count_safe, "count_safe: {}, count_unsafe: {}", count_safe, count_unsafe);
+}
+
+// WindowedGlr tests
+#[test]
+fn test_windowed_glr_all_normal() {
+    let threshold = 10.0;
+    let mut model = WindowedGlr::new(0.0, 1.0, 50, threshold).unwrap();
+    let (count_safe, count_unsafe) = count_predictions(&generate_data(), threshold, |event| {
+        model.update(event);
+        model.predict(event)
+    });
+    assert!(count_safe >= count_unsafe, "count_safe: {}, count_unsafe: {}", count_safe, count_unsafe);
+}
+
+#[test]
+fn test_windowed_glr_all_abnormal() {
+    let threshold = 10.0;
+    let mut model = WindowedGlr::new(0.0, 1.0, 50, threshold).unwrap();
+    let (count_safe, count_unsafe) = count_predictions(&generate_abnormal_data(), threshold, |event| {
+        model.update(event);
+        model.predict(event)
+    });
+    assert!(count_unsafe >= count_safe, "count_safe: {}, count_unsafe: {}", count_safe, count_unsafe);
+}
+
+#[test]
+fn test_windowed_glr_estimates_change_mean() {
+    let mut data = generate_normal_data(0.0, 1.0, 200, Some(83));
+    data.extend(generate_normal_data(2.0, 1.0, 30, Some(84)));
+    // too high to ever reset
+    let mut model = WindowedGlr::new(0.0, 1.0, 50, f64::MAX).unwrap();
+    for event in data {
+        model.update(event);
+        model.predict(event);
+    }
+    assert!((model.change_mean() - 2.0).abs() < 1.0, "change mean: {}", model.change_mean());
+}
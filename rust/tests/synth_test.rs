@@ -0,0 +1,25 @@
+use _change_point_algorithms::ewma::EwmaChart;
+use _change_point_algorithms::metrics::alarm_counts;
+use _change_point_algorithms::synth::{generate_stream, Segment};
+
+#[test]
+fn test_synth_ewma_detects_mean_shifts() {
+    let segments = vec![
+        Segment::Normal { length: 1_000, mean: 0.0, std_dev: 1.0 },
+        Segment::Normal { length: 1_000, mean: 3.0, std_dev: 1.0 },
+        Segment::Normal { length: 1_000, mean: -3.0, std_dev: 1.0 },
+    ];
+    let (data, changepoints) = generate_stream(segments, Some(11)).unwrap();
+    assert_eq!(changepoints, vec![1_000, 2_000]);
+    let l_sigma = 3.0;
+    let mut model = EwmaChart::new(0.0, 1.0, 0.2, l_sigma).unwrap();
+    let mut alarms = Vec::new();
+    for (idx, &event) in data.iter().enumerate() {
+        model.update(event);
+        if model.predict(event) > l_sigma {
+            alarms.push(idx);
+        }
+    }
+    let counts = alarm_counts(changepoints, alarms, 20);
+    assert_eq!(counts.detected, 2, "counts: {:?}", counts);
+}
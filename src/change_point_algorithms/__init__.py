@@ -10,6 +10,9 @@ if hasattr(_change_point_algorithms, "__all__"):
     __all__ = _change_point_algorithms.__all__
 
 from change_point_algorithms._change_point_algorithms import (
-    BocpdModel, EmModel, EmLikelihoodCheck, CusumV0, CusumV1,
-    build_em_model, build_em_early_stop_model
+    AlarmCounts, BocpdModel, ConvergenceCheck, CovarianceType, Criterion, EmBuilder, EmDiagnostics, EmEvent, EmModel, EmOptions, EmLikelihoodCheck, EmLogLikelihoodCheck, EmParameterCheck, EmAitkenCheck, EmConvergenceCheck, CusumV0, CusumV1,
+    EwmaChart, GreyModel, GreyModelV2, HmmModel, InitStrategy, IntervalEvent, IntervalTracker, MultivariateEmModel, OnlineEmModel, RetentionPolicy, Segment, SelectionScore, ShiryaevRoberts, WindowedGlr, build_em_model, build_em_early_stop_model,
+    build_em_model_from_samples, build_hmm_model, build_multivariate_em_model, generate_stream,
+    select_em_model, alarm_counts, detections_to_intervals, detection_delays, interval_iou, mean_detection_delay,
+    mean_time_between_false_alarms, precision_recall_f1
 )
@@ -1,21 +1,28 @@
-from collections.abc import Sequence
+from collections.abc import Callable, Sequence
 from typing import TypeAlias
 
-from change_point_algorithms import EmLikelihoodCheck, EmModel
+import numpy as np
+import numpy.typing as npt
+
+from change_point_algorithms import EmLikelihoodCheck, EmModel, EmOptions, RetentionPolicy
 
 NormalTuple: TypeAlias = tuple[float, float, float]
+MultivariateNormalTuple: TypeAlias = tuple[list[float], list[list[float]], float]
 
-def build_em_early_stop_model(normal: NormalTuple, abnormals: Sequence[NormalTuple], arr_sizes: list[int], epochs: int) -> EmLikelihoodCheck:
+def build_em_early_stop_model(normal: NormalTuple, abnormals: Sequence[NormalTuple], arr_sizes: list[int], epochs: int, seed: int | None = None, retention: RetentionPolicy | None = None, options: EmOptions | None = None) -> EmLikelihoodCheck:
     """ Return an Expectation Maximization model with early stopping for parameter updates.
     :param normal: A 3-tuple of (mean, standard deviation, probability of occurrence)
     :param abnormals: List of 3-tuples (mean, standard deviation, probability of occurrence)
     :param arr_sizes: List representing the number of samples for each distribution.
      The first size is for the normal parameter distribution. The remaining correspond to the abnormal case(s).
     :param epochs: The maximum number of iterations to perform for each parameter update.
+    :param seed: Seed for drawing the training samples. Identical seeds build identical models.
+    :param retention: How observed points are kept among the samples. Defaults to overwriting the last sample.
+    :param options: Log-domain responsibilities, variance floor and re-initialization of collapsed components. Defaults to none of them.
     :return: Expectation Maximization model with early stopping. The model update stops early when the change in likelihoods is negligible.
     """
 
-def build_em_model(normal: NormalTuple, abnormals: Sequence[NormalTuple], arr_sizes: list[int], epochs: int) -> EmModel:
+def build_em_model(normal: NormalTuple, abnormals: Sequence[NormalTuple], arr_sizes: list[int], epochs: int, seed: int | None = None, retention: RetentionPolicy | None = None, options: EmOptions | None = None) -> EmModel:
     """ Return an Expectation Maximization model.
 
     :param normal: A 3-tuple of (mean, standard deviation, probability of occurrence)
@@ -23,13 +30,192 @@ def build_em_model(normal: NormalTuple, abnormals: Sequence[NormalTuple], arr_si
     :param arr_sizes: List representing the number of samples for each distribution.
      The first size is for the normal parameter distribution. The remaining correspond to the abnormal case(s).
     :param epochs: The maximum number of iterations to perform for each parameter update.
+    :param seed: Seed for drawing the training samples. Identical seeds build identical models.
+    :param retention: How observed points are kept among the samples. Defaults to overwriting the last sample.
+    :param options: Log-domain responsibilities, variance floor and re-initialization of collapsed components. Defaults to none of them.
+    :return: Expectation Maximization model.
+    """
+
+def build_em_model_from_samples(samples: Sequence[float], abnormal_count: int, epochs: int, initialization: InitStrategy = InitStrategy.KMeansPlusPlus, restarts: int = 1, seed: int | None = None, options: EmOptions | None = None) -> EmModel:
+    """ Return an Expectation Maximization model whose components are chosen from the training samples.
+
+    :param samples: Training samples.
+    :param abnormal_count: Number of abnormal components. The largest cluster becomes the normal component.
+    :param epochs: The maximum number of iterations to perform for initialization and for each parameter update.
+    :param initialization: How initial components are seeded before k-means refinement.
+    :param restarts: Number of initializations to fit, keeping the one with the best log-likelihood. Quantile seeding runs once.
+    :param seed: Seed for the initialization. Identical seeds build identical models.
+    :param options: Log-domain responsibilities, variance floor and re-initialization of collapsed components. Defaults to none of them.
     :return: Expectation Maximization model.
     """
 
+def select_em_model(samples: Sequence[float], abnormal_counts: Sequence[int], epochs: int, criterion: Criterion = Criterion.Bic, restarts: int = 1, seed: int | None = None, options: EmOptions | None = None) -> tuple[EmModel, list[SelectionScore]]:
+    """ Return the Expectation Maximization model whose number of abnormal components scores best, with the scores of every candidate.
+
+    :param samples: Training samples.
+    :param abnormal_counts: Numbers of abnormal components to try.
+    :param epochs: The maximum number of iterations to perform for fitting each candidate and for each parameter update.
+    :param criterion: Information criterion used to choose among the candidates. Lower scores are better.
+    :param restarts: Number of k-means++ initializations to fit for each candidate, keeping the one with the best log-likelihood.
+    :param seed: Seed for the initializations. Identical seeds build identical models.
+    :param options: Log-domain responsibilities, variance floor and re-initialization of collapsed components. Defaults to none of them.
+    :return: Chosen model and the scores in the order of abnormal_counts.
+    """
+
+def build_multivariate_em_model(normal: MultivariateNormalTuple, abnormals: Sequence[MultivariateNormalTuple], samples: Sequence[Sequence[float]], epochs: int, covariance_type: CovarianceType = CovarianceType.Full, regularization: float = 1e-6) -> MultivariateEmModel:
+    """ Return an Expectation Maximization model over multi-channel samples.
+
+    :param normal: A 3-tuple of (mean, covariance rows, probability of occurrence)
+    :param abnormals: List of 3-tuples (mean, covariance rows, probability of occurrence)
+    :param samples: Training samples, one list of channels per sample.
+    :param epochs: The maximum number of iterations to perform for each parameter update.
+    :param covariance_type: Whether fitted covariances are full or diagonal.
+    :param regularization: Value added to the diagonal of every fitted covariance.
+    :return: Multivariate Expectation Maximization model.
+    """
+
+def build_hmm_model(normal: tuple[float, float, float], abnormals: Sequence[tuple[float, float, float]], samples: Sequence[float], epochs: int, transitions: Sequence[Sequence[float]] | None = None, self_transition: float = 0.9) -> HmmModel:
+    """ Return a Hidden Markov Model trained with Baum-Welch on the samples.
+
+    :param normal: A 3-tuple of (mean, standard deviation, initial probability)
+    :param abnormals: List of 3-tuples (mean, standard deviation, initial probability)
+    :param samples: Training samples in the order they were observed.
+    :param epochs: The number of Baum-Welch iterations to run.
+    :param transitions: Rows of the transition matrix, normal state first. Every row must sum to 1.
+    :param self_transition: Probability of staying in a state, used when transitions are not given.
+    :return: Trained Hidden Markov Model.
+    """
+
+def detections_to_intervals(detections: Sequence[bool], min_duration: int = 1, merge_gap: int = 0) -> list[tuple[int, int]]:
+    """ Return (start, end) index intervals of alarms. Interval ends are exclusive.
+
+    :param detections: Per-sample alarm output of a detector.
+    :param min_duration: Intervals spanning fewer samples are dropped.
+    :param merge_gap: Alarm runs separated by at most this many quiet samples are merged.
+    """
+
+class IntervalEvent:
+    """ Change to the set of alarm intervals. Interval ends are exclusive.
+    """
+    class Opened(IntervalEvent):
+        start: int
+
+    class Closed(IntervalEvent):
+        start: int
+        end: int
+
+class IntervalTracker:
+    """ A class that groups per-sample alarms into intervals.
+    """
+    def __init__(self, min_duration: int = 1, merge_gap: int = 0):
+        """
+        :param min_duration: Intervals spanning fewer samples are dropped without being reported.
+        :param merge_gap: Alarm runs separated by at most this many quiet samples are merged.
+        """
+
+    def update(self, is_alarm: bool) -> IntervalEvent | None:
+        """ Consume alarm output for the next sample and return any interval event it causes."""
+
+    def update_many(self, detections: Sequence[bool]) -> list[IntervalEvent]:
+        """ Consume alarm output for several samples and return the interval events they cause."""
+
+    def finish(self) -> IntervalEvent | None:
+        """ Close the current interval at the end of the stream."""
+
+    def index(self) -> int:
+        """ Return index of the next sample."""
+
+    def is_open(self) -> bool:
+        """ Return whether an interval has been opened and not yet closed."""
+
+def detection_delays(changepoints: Sequence[int], alarms: Sequence[int], tolerance: int) -> list[int | None]:
+    """ Return the delay of the first alarm after each change point.
+
+    An alarm detects a change point when it occurs no more than tolerance samples after it and before the next change point.
+    :param changepoints: Indices of true change points.
+    :param alarms: Indices where the detector raised an alarm.
+    :param tolerance: Maximum number of samples between change point and detecting alarm.
+    :return: Delay for each change point in sorted order, None if the change point was missed.
+    """
+
+def mean_detection_delay(changepoints: Sequence[int], alarms: Sequence[int], tolerance: int) -> float:
+    """ Return mean delay over detected change points, NaN if none were detected."""
+
+def alarm_counts(changepoints: Sequence[int], alarms: Sequence[int], tolerance: int) -> AlarmCounts:
+    """ Count true and false alarms and detected and missed change points within the tolerance window.
+
+    Only the first alarm in a change point's window is a true alarm; later ones are false alarms.
+    """
+
+def precision_recall_f1(changepoints: Sequence[int], alarms: Sequence[int], tolerance: int) -> tuple[float, float, float]:
+    """ Return (precision, recall, F1) of alarms against change points within the tolerance window."""
+
+def mean_time_between_false_alarms(changepoints: Sequence[int], alarms: Sequence[int], tolerance: int, length: int) -> float:
+    """ Return number of samples observed per false alarm, infinity if there were no false alarms.
+
+    :param length: Number of samples in the stream.
+    """
+
+def interval_iou(truth: Sequence[tuple[int, int]], predicted: Sequence[tuple[int, int]]) -> float:
+    """ Return intersection over union of the samples covered by each set of half-open (start, end) intervals."""
+
+class Segment:
+    """ A piece of a synthetic stream. Mean and variance shifts are consecutive Normal segments.
+    """
+    class Normal(Segment):
+        """ Independent normal samples."""
+        def __init__(self, length: int, mean: float, std_dev: float): ...
+
+    class Drift(Segment):
+        """ Normal samples whose mean moves linearly from start towards end."""
+        def __init__(self, length: int, start: float, end: float, std_dev: float): ...
+
+    class Ar(Segment):
+        """ First order autoregressive process around mean with innovations of std_dev."""
+        def __init__(self, length: int, mean: float, coef: float, std_dev: float): ...
+
+    class Impulse(Segment):
+        """ Normal samples where the first sample is shifted by magnitude."""
+        def __init__(self, length: int, mean: float, std_dev: float, magnitude: float): ...
+
+    class Poisson(Segment):
+        """ Poisson counts with the given rate."""
+        def __init__(self, length: int, rate: float): ...
+
+def generate_stream(segments: Sequence[Segment], seed: int | None = None) -> tuple[list[float], list[int]]:
+    """ Return samples of the segments and the ground truth change points.
+
+    :param segments: Segments generated back to back. The start of each segment after the first is a change point.
+    :param seed: Seed for the random number generator. Identical seeds give identical streams.
+    :return: Tuple of (samples, change point indices).
+    """
+
+class AlarmCounts:
+    """ Counts of alarms and change points from scoring a detector against ground truth.
+    """
+    true_alarms: int
+    false_alarms: int
+    detected: int
+    missed: int
+
+    def precision(self) -> float:
+        """ Return fraction of alarms that are true alarms."""
+
+    def recall(self) -> float:
+        """ Return fraction of change points that were detected."""
+
+    def f1(self) -> float:
+        """ Return harmonic mean of precision and recall."""
+
 class EmLikelihoodCheck:
     """ A class implementing Expectation Maximization with early stopping.
     """
-    def update_check_convergence(self, point: float, early_stop_threshold: float):
+    def __init__(self, model: EmModel):
+        """
+        :param model: Model to update. Stops early when no responsibility changes by more than the threshold.
+        """
+
+    def update_check_convergence(self, point: float, early_stop_threshold: float) -> EmDiagnostics:
         """ Update model parameters using given point with early stopping when likelihood is below threshold.
         """
 
@@ -37,17 +223,529 @@ class EmLikelihoodCheck:
         """ Return prediction for given point.
         """
 
+    def predict_proba(self, point: float) -> list[float]:
+        """ Return posterior probability of every component for the point, normal first."""
+
+    def component_posteriors(self, point: float) -> list[float]:
+        """ Same as predict_proba."""
+
+    def classify(self, point: float) -> tuple[int, float]:
+        """ Return index of the most probable component for the point, normal first, with its posterior probability."""
+
+    def novelty_score(self, point: float) -> float:
+        """ Return negative log-likelihood of the point under the whole mixture. Large values mean no component explains it well."""
+
+    def parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every component, normal first."""
+
+    def normal_parameters(self) -> tuple[float, float, float]:
+        """ Return (location, scale, weight) of the normal component."""
+
+    def abnormal_parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every abnormal component."""
+
+    def parameters_array(self) -> npt.NDArray[np.float64]:
+        """ Return components by (location, scale, weight) as an array of shape (components, 3), normal first."""
+
+    def likelihoods(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the responsibilities from the last expectation step, of shape (components, samples)."""
+
+    def samples(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the samples the model is fit to."""
+
+class EmLogLikelihoodCheck:
+    """ A class implementing Expectation Maximization that stops early when the mean log-likelihood per sample changes by no more than the threshold.
+    """
+    def __init__(self, model: EmModel):
+        """
+        :param model: Model to update.
+        """
+
+    def update_check_convergence(self, point: float, early_stop_threshold: float) -> EmDiagnostics:
+        """ Update model parameters using given point with early stopping.
+        """
+
+    def predict(self, point: float) -> float:
+        """ Return prediction for given point.
+        """
+
+    def predict_proba(self, point: float) -> list[float]:
+        """ Return posterior probability of every component for the point, normal first."""
+
+    def component_posteriors(self, point: float) -> list[float]:
+        """ Same as predict_proba."""
+
+    def classify(self, point: float) -> tuple[int, float]:
+        """ Return index of the most probable component for the point, normal first, with its posterior probability."""
+
+    def novelty_score(self, point: float) -> float:
+        """ Return negative log-likelihood of the point under the whole mixture. Large values mean no component explains it well."""
+
+    def parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every component, normal first."""
+
+    def normal_parameters(self) -> tuple[float, float, float]:
+        """ Return (location, scale, weight) of the normal component."""
+
+    def abnormal_parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every abnormal component."""
+
+    def parameters_array(self) -> npt.NDArray[np.float64]:
+        """ Return components by (location, scale, weight) as an array of shape (components, 3), normal first."""
+
+    def likelihoods(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the responsibilities from the last expectation step, of shape (components, samples)."""
+
+    def samples(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the samples the model is fit to."""
+
+class EmParameterCheck:
+    """ A class implementing Expectation Maximization that stops early when no mean, standard deviation or weight changes by more than the threshold.
+    """
+    def __init__(self, model: EmModel):
+        """
+        :param model: Model to update.
+        """
+
+    def update_check_convergence(self, point: float, early_stop_threshold: float) -> EmDiagnostics:
+        """ Update model parameters using given point with early stopping.
+        """
+
+    def predict(self, point: float) -> float:
+        """ Return prediction for given point.
+        """
+
+    def predict_proba(self, point: float) -> list[float]:
+        """ Return posterior probability of every component for the point, normal first."""
+
+    def component_posteriors(self, point: float) -> list[float]:
+        """ Same as predict_proba."""
+
+    def classify(self, point: float) -> tuple[int, float]:
+        """ Return index of the most probable component for the point, normal first, with its posterior probability."""
+
+    def novelty_score(self, point: float) -> float:
+        """ Return negative log-likelihood of the point under the whole mixture. Large values mean no component explains it well."""
+
+    def parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every component, normal first."""
+
+    def normal_parameters(self) -> tuple[float, float, float]:
+        """ Return (location, scale, weight) of the normal component."""
+
+    def abnormal_parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every abnormal component."""
+
+    def parameters_array(self) -> npt.NDArray[np.float64]:
+        """ Return components by (location, scale, weight) as an array of shape (components, 3), normal first."""
+
+    def likelihoods(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the responsibilities from the last expectation step, of shape (components, samples)."""
+
+    def samples(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the samples the model is fit to."""
+
+class EmAitkenCheck:
+    """ A class implementing Expectation Maximization that stops early when the Aitken accelerated estimate of the converged mean log-likelihood per sample is within the threshold of the current one.
+    """
+    def __init__(self, model: EmModel):
+        """
+        :param model: Model to update.
+        """
+
+    def update_check_convergence(self, point: float, early_stop_threshold: float) -> EmDiagnostics:
+        """ Update model parameters using given point with early stopping.
+        """
+
+    def predict(self, point: float) -> float:
+        """ Return prediction for given point.
+        """
+
+    def predict_proba(self, point: float) -> list[float]:
+        """ Return posterior probability of every component for the point, normal first."""
+
+    def component_posteriors(self, point: float) -> list[float]:
+        """ Same as predict_proba."""
+
+    def classify(self, point: float) -> tuple[int, float]:
+        """ Return index of the most probable component for the point, normal first, with its posterior probability."""
+
+    def novelty_score(self, point: float) -> float:
+        """ Return negative log-likelihood of the point under the whole mixture. Large values mean no component explains it well."""
+
+    def parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every component, normal first."""
+
+    def normal_parameters(self) -> tuple[float, float, float]:
+        """ Return (location, scale, weight) of the normal component."""
+
+    def abnormal_parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every abnormal component."""
+
+    def parameters_array(self) -> npt.NDArray[np.float64]:
+        """ Return components by (location, scale, weight) as an array of shape (components, 3), normal first."""
+
+    def likelihoods(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the responsibilities from the last expectation step, of shape (components, samples)."""
+
+    def samples(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the samples the model is fit to."""
+
+class EmConvergenceCheck:
+    """ A class implementing Expectation Maximization that stops early with a convergence check chosen at runtime.
+    """
+    def __init__(self, model: EmModel, check: ConvergenceCheck | Callable[[EmModel, float], bool]):
+        """
+        :param model: Model to update.
+        :param check: Convergence check to stop updates early with, or a callable given a copy of the model and the threshold after every expectation step that returns whether the model has converged. An exception raised by the callable is raised from update_check_convergence.
+        """
+
+    def update_check_convergence(self, point: float, early_stop_threshold: float) -> EmDiagnostics:
+        """ Update model parameters using given point with early stopping.
+        """
+
+    def predict(self, point: float) -> float:
+        """ Return prediction for given point.
+        """
+
+    def predict_proba(self, point: float) -> list[float]:
+        """ Return posterior probability of every component for the point, normal first."""
+
+    def component_posteriors(self, point: float) -> list[float]:
+        """ Same as predict_proba."""
+
+    def classify(self, point: float) -> tuple[int, float]:
+        """ Return index of the most probable component for the point, normal first, with its posterior probability."""
+
+    def novelty_score(self, point: float) -> float:
+        """ Return negative log-likelihood of the point under the whole mixture. Large values mean no component explains it well."""
+
+    def parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every component, normal first."""
+
+    def normal_parameters(self) -> tuple[float, float, float]:
+        """ Return (location, scale, weight) of the normal component."""
+
+    def abnormal_parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every abnormal component."""
+
+    def parameters_array(self) -> npt.NDArray[np.float64]:
+        """ Return components by (location, scale, weight) as an array of shape (components, 3), normal first."""
+
+    def likelihoods(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the responsibilities from the last expectation step, of shape (components, samples)."""
+
+    def samples(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the samples the model is fit to."""
+
+class EmEvent:
+    """ Intervention made by a maximization step on a degenerate component.
+
+    Components are numbered with the normal component first.
+    """
+    class VarianceFloored(EmEvent):
+        """ Variance fell below the floor and was raised to it."""
+        component: int
+        variance: float
+
+    class Collapsed(EmEvent):
+        """ Component explains no samples or its variance vanished, so its parameters were kept."""
+        component: int
+
+    class Reinitialized(EmEvent):
+        """ Component collapsed and was reset to its initial parameters."""
+        component: int
+
+class EmDiagnostics:
+    """ Report of the epochs run by a single Expectation Maximization update.
+    """
+    epochs: int
+    """ Number of expectation steps run."""
+    converged: bool
+    """ Whether the update stopped early because the convergence check passed."""
+    log_likelihoods: list[float]
+    """ Log-likelihood of the samples at each expectation step."""
+    parameter_deltas: list[float]
+    """ Largest change of any mean, standard deviation or weight at each maximization step."""
+    events: list[EmEvent]
+    """ Interventions on degenerate components, in the order they happened."""
+
+    def final_log_likelihood(self) -> float | None:
+        """ Return log-likelihood at the last expectation step, or None if no epoch ran."""
+
 class EmModel:
     """ A class implementing Expectation Maximization.
     """
+    def update(self, point: float) -> EmDiagnostics:
+        """ Update model parameters using given point, running every epoch.
+        """
+
+    def update_without_diagnostics(self, point: float):
+        """ Same as update without recording diagnostics, for updating on every sample.
+        """
+
+    def predict(self, point: float) -> float:
+        """ Return prediction for given point.
+        """
+
+    def retain_sample(self, point: float) -> float | None:
+        """ Add point to the samples according to the retention policy and return the sample it replaced, if any."""
+
+    def sample_count(self) -> int:
+        """ Return number of samples the model is fit to."""
+
+    def log_likelihood(self) -> float:
+        """ Return log-likelihood of the samples under the current parameters."""
+
+    def predict_proba(self, point: float) -> list[float]:
+        """ Return posterior probability of every component for the point, normal first."""
+
+    def component_posteriors(self, point: float) -> list[float]:
+        """ Same as predict_proba."""
+
+    def classify(self, point: float) -> tuple[int, float]:
+        """ Return index of the most probable component for the point, normal first, with its posterior probability."""
+
+    def novelty_score(self, point: float) -> float:
+        """ Return negative log-likelihood of the point under the whole mixture. Large values mean no component explains it well."""
+
+    def parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every component, normal first."""
+
+    def normal_parameters(self) -> tuple[float, float, float]:
+        """ Return (location, scale, weight) of the normal component."""
+
+    def abnormal_parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (location, scale, weight) of every abnormal component."""
+
+    def parameters_array(self) -> npt.NDArray[np.float64]:
+        """ Return components by (location, scale, weight) as an array of shape (components, 3), normal first."""
+
+    def likelihoods(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the responsibilities from the last expectation step, of shape (components, samples)."""
+
+    def samples(self) -> npt.NDArray[np.float64]:
+        """ Return copy of the samples the model is fit to."""
+
+class CovarianceType:
+    """ Shape of the covariance matrices fitted by the maximization step."""
+    Full: CovarianceType
+    """ Every channel may correlate with every other channel."""
+    Diagonal: CovarianceType
+    """ Channels are independent within a component."""
+
+class MultivariateEmModel:
+    """ A class implementing Expectation Maximization over multi-channel samples.
+    """
+    def update(self, point: Sequence[float]) -> EmDiagnostics:
+        """ Update model parameters using given multi-channel point, running every epoch.
+        """
+
+    def predict(self, point: Sequence[float]) -> float:
+        """ Return posterior probability that the point belongs to the normal component.
+        """
+
+    def predict_proba(self, point: Sequence[float]) -> list[float]:
+        """ Return posterior probability of every component for the point, normal first."""
+
+    def classify(self, point: Sequence[float]) -> tuple[int, float]:
+        """ Return index of the most probable component for the point, normal first, with its posterior probability."""
+
+    def novelty_score(self, point: Sequence[float]) -> float:
+        """ Return negative log-likelihood of the point under the whole mixture."""
+
+    def expectation(self) -> float:
+        """ Compute responsibilities and return log-likelihood of the samples."""
+
+    def log_likelihood(self) -> float:
+        """ Return log-likelihood of the samples under the current parameters."""
+
+    def dim(self) -> int:
+        """ Return number of channels in every sample."""
+
+    def sample_count(self) -> int:
+        """ Return number of samples the model is fit to."""
+
+class HmmModel:
+    """ A Hidden Markov Model with normal emissions, normal state first.
+    """
+    def update(self, point: float) -> float:
+        """ Advance the forward filter with the point and return probability of the normal state.
+        """
+
+    def predict(self, point: float) -> float:
+        """ Return probability of the normal state if the point were observed next, without advancing the filter.
+        """
+
+    def filtered(self) -> list[float]:
+        """ Return current state distribution, normal state first."""
+
+    def reset_filter(self) -> None:
+        """ Restart filtering from the initial state distribution."""
+
+    def train(self) -> EmDiagnostics:
+        """ Run Baum-Welch on the training samples for every epoch and restart filtering."""
+
+    def viterbi(self, points: Sequence[float]) -> list[int]:
+        """ Return most likely state of every point, normal state being 0."""
+
+    def log_likelihood(self, points: Sequence[float]) -> float:
+        """ Return log-likelihood of the points under the model."""
+
+    def parameters(self) -> list[tuple[float, float, float]]:
+        """ Return (mean, standard deviation, initial probability) of every state, normal first."""
+
+    def transitions(self) -> list[list[float]]:
+        """ Return rows of the transition matrix, row being the current state."""
+
+    def state_count(self) -> int:
+        """ Return number of states."""
+
+class InitStrategy:
+    """ How initial component parameters are chosen from the training samples."""
+    KMeansPlusPlus: InitStrategy
+    """ Seed centers with probability proportional to squared distance from chosen centers, then refine with k-means."""
+    Quantiles: InitStrategy
+    """ Seed centers at the midpoints of equally sized groups of the sorted samples, then refine with k-means."""
+
+class Criterion:
+    """ Information criterion used to choose the number of components. Lower scores are better."""
+    Aic: Criterion
+    """ Akaike information criterion, -2 log-likelihood + 2 parameters."""
+    Bic: Criterion
+    """ Bayesian information criterion, -2 log-likelihood + parameters * ln(samples)."""
+    Icl: Criterion
+    """ Integrated completed likelihood, BIC plus twice the entropy of the responsibilities."""
+
+class SelectionScore:
+    """ Fit quality of a model with a given number of abnormal components.
+    """
+    abnormal_count: int
+    log_likelihood: float
+    """ Log-likelihood of the training samples."""
+    parameter_count: int
+    """ Number of free means, standard deviations and weights."""
+    aic: float
+    bic: float
+    icl: float
+
+    def score(self, criterion: Criterion) -> float:
+        """ Return the score under the given criterion."""
+
+class ConvergenceCheck:
+    """ Convergence check a model built by EmBuilder stops its updates early with."""
+    Likelihood: ConvergenceCheck
+    """ Change in responsibilities, as EmLikelihoodCheck."""
+    LogLikelihood: ConvergenceCheck
+    """ Change in mean log-likelihood per sample, as EmLogLikelihoodCheck."""
+    Parameter: ConvergenceCheck
+    """ Change in any mean, standard deviation or weight, as EmParameterCheck."""
+    Aitken: ConvergenceCheck
+    """ Aitken estimate of the converged mean log-likelihood, as EmAitkenCheck."""
+
+class EmBuilder:
+    """ Builder of Expectation Maximization models from real training samples.
+
+    Every method validates its values straight away, raising ValueError, and returns the builder so calls can be chained.
+    The normal component defaults to (0, 1, 1), with no abnormal components and one epoch.
+    """
+    def __init__(self): ...
+
+    def build_normal(self, mean: float, stddev: float, prob: float) -> EmBuilder:
+        """ Set the normal component."""
+
+    def build_abnormal(self, abnormals: Sequence[tuple[float, float, float]]) -> EmBuilder:
+        """ Set the abnormal components as 3-tuples (mean, standard deviation, probability of occurrence)."""
+
+    def build_samples(self, samples: npt.NDArray[np.float64]) -> EmBuilder:
+        """ Set the training samples. The array is copied."""
+
+    def build_epochs(self, epochs: int) -> EmBuilder:
+        """ Set the maximum number of iterations to perform for each parameter update."""
+
+    def build_retention(self, retention: RetentionPolicy) -> EmBuilder:
+        """ Set how observed points are kept among the samples."""
+
+    def build_options(self, options: EmOptions) -> EmBuilder:
+        """ Set log-domain responsibilities, variance floor and re-initialization of collapsed components."""
+
+    def build_convergence_check(self, convergence_check: ConvergenceCheck | None = None) -> EmBuilder:
+        """ Choose the check that stops updates early, or None to run every epoch."""
+
+    def get_model(self) -> EmModel | EmLikelihoodCheck | EmLogLikelihoodCheck | EmParameterCheck | EmAitkenCheck:
+        """ Return EmModel without a convergence check, otherwise the early stopping model for the check.
+
+        Raise ValueError if samples were never given.
+        """
+
+class EmOptions:
+    """ Optional behaviour of the expectation and maximization steps.
+    """
+    log_domain: bool
+    """ Compute responsibilities from log densities with log-sum-exp, so samples far from every component still count."""
+    variance_floor: float
+    """ Smallest variance a component may take."""
+    reinitialize: bool
+    """ Reset collapsed components to their initial parameters instead of keeping them."""
+
+    def __init__(self, log_domain: bool = False, variance_floor: float = 0.0, reinitialize: bool = False):
+        """ Raise ValueError if variance_floor is negative or not finite."""
+
+class RetentionPolicy:
+    """ How an Expectation Maximization model keeps observed points among its samples.
+    """
+    class SwapLast(RetentionPolicy):
+        """ Overwrite the last sample with every new point."""
+        def __init__(self): ...
+
+    class Fifo(RetentionPolicy):
+        """ Keep the newest capacity points, evicting the oldest first. Training samples count as oldest."""
+        capacity: int
+        def __init__(self, capacity: int): ...
+
+    class Growing(RetentionPolicy):
+        """ Keep every training sample and grow with new points until capacity,
+        then evict the oldest point that is not a training sample.
+        """
+        capacity: int
+        def __init__(self, capacity: int): ...
+
+    class Reservoir(RetentionPolicy):
+        """ Keep a uniform random subset of capacity points out of everything observed."""
+        capacity: int
+        seed: int | None
+        def __init__(self, capacity: int, seed: int | None = None): ...
+
+class OnlineEmModel:
+    """ A class implementing online Expectation Maximization.
+
+    Each point moves running sufficient statistics towards its own contribution by a decreasing step size,
+    so updates cost O(components) per point instead of refitting over stored samples.
+    """
+    def __init__(self, normal: NormalTuple, abnormals: Sequence[NormalTuple], gamma0: float = 1.0, decay: float = 0.6, offset: float = 10.0, prior_weight: float = 0.01):
+        """
+        :param normal: A 3-tuple of (mean, standard deviation, probability of occurrence)
+        :param abnormals: List of 3-tuples (mean, standard deviation, probability of occurrence)
+        :param gamma0: Scale of the step size gamma0 * (n + offset) ** -decay for the n-th point.
+        :param decay: Decay of the step size, in (0.5, 1].
+        :param offset: Offset of the step size. Larger values make early updates smaller.
+        :param prior_weight: Weight of the initial parameters, kept as pseudo-observations so rarely seen components do not collapse.
+        """
+
     def update(self, point: float):
         """ Update model parameters using given point.
         """
 
     def predict(self, point: float) -> float:
-        """ Return prediction for given point.
+        """ Return posterior probability that point belongs to the normal component.
         """
 
+    def count(self) -> int:
+        """ Return number of points used to update the model."""
+
+    def next_step_size(self) -> float:
+        """ Return step size that will be applied to the next point."""
+
 class BocpdModel:
     """ A class implementing Bayesian Online Change Point Detection.
     """
@@ -121,3 +819,186 @@ class CusumV1:
         :param _point: Not used for prediction.
         :return: Max cumulative deviation from mean.
         """
+
+
+class EwmaChart:
+    """ A class implementing an exponentially weighted moving average control chart.
+    """
+    def __init__(self, mean: float, std_dev: float, lamb: float, l_sigma: float):
+        """
+        :param mean: In-control mean.
+        :param std_dev: In-control standard deviation.
+        :param lamb: Smoothing constant in (0, 1].
+        :param l_sigma: Width of control limits in standard deviations of the smoothed statistic.
+        """
+
+    def update(self, point: float):
+        """
+        :param point: Observation used to update model.
+        :return:
+        """
+
+    def predict(self, _point: float) -> float:
+        """
+        :param _point: Not used for prediction.
+        :return: Distance of smoothed statistic from mean in standard deviations. Chart restarts once it exceeds l_sigma.
+        """
+
+    def smoothed(self) -> float:
+        """ Return current smoothed statistic."""
+
+    def smoothed_std_dev(self) -> float:
+        """ Return exact standard deviation of the smoothed statistic at the current sample."""
+
+    def upper_limit(self) -> float:
+        """ Return current upper control limit."""
+
+    def lower_limit(self) -> float:
+        """ Return current lower control limit."""
+
+
+class GreyModel:
+    """ A class implementing the grey systems model.
+
+    The first full window is the reference behaviour. Later windows are compared to it by degree of grey incidence.
+    """
+    def __init__(self, window_size: int, c: float = 3.0, c_ratio: float = 300.0, threshold: float = 0.5):
+        """
+        :param window_size: Number of observations in each window.
+        :param c: Constant multiplier for degree of grey incidence.
+        :param c_ratio: Constant multiplier for degree of relative grey incidence.
+        :param threshold: Degree of grey incidence at or below which a change is likely.
+        """
+
+    def update(self, point: float):
+        """
+        :param point: Observation used to update model. Expected to be non-negative.
+        :return:
+        """
+
+    def predict(self, _point: float) -> float:
+        """
+        :param _point: Not used for prediction.
+        :return: Smaller of the two degrees of grey incidence. Low values indicate change.
+        """
+
+    def is_change(self) -> bool:
+        """ Return whether either degree of grey incidence is at or below threshold."""
+
+    def degree(self) -> float:
+        """ Return degree of grey incidence of the behavioural sequences."""
+
+    def rel_degree(self) -> float:
+        """ Return degree of grey incidence of the relative difference sequences."""
+
+    def window_size(self) -> int:
+        """ Return number of observations in each window."""
+
+    def reset_reference(self):
+        """ Forget the reference window so the next full window becomes the new reference."""
+
+
+class GreyModelV2:
+    """ A class implementing the second version of the grey systems model.
+
+    Every point is accumulated over its own window and whitenized, then compared with the first point by grey index.
+    """
+    def __init__(self, window_size: int = 3, whitenization: float = 0.5, threshold: float = 0.15):
+        """
+        :param window_size: Number of accumulation steps for each point.
+        :param whitenization: Weight of the newer accumulated value when whitenizing.
+        :param threshold: Sensitivity of the grey indices, and the index below which a change is likely.
+        """
+
+    def update(self, point: float):
+        """
+        :param point: Observation used to update model. The first observation only sets the offset of the relative index.
+        :return:
+        """
+
+    def predict(self, _point: float) -> float:
+        """
+        :param _point: Not used for prediction.
+        :return: Smaller of the two grey indices. Low values indicate change.
+        """
+
+    def is_change(self) -> bool:
+        """ Return whether either grey index is below threshold."""
+
+    def degree(self) -> float:
+        """ Return grey index of the whitenized sequence."""
+
+    def rel_degree(self) -> float:
+        """ Return grey index of the whitenized sequence relative to the first observation."""
+
+    def window_size(self) -> int:
+        """ Return number of accumulation steps for each point."""
+
+    def reset(self):
+        """ Forget the first observation so the next one sets the offset again."""
+
+class ShiryaevRoberts:
+    """ A class implementing the Shiryaev-Roberts procedure for a known change in a normal mean.
+    """
+    def __init__(self, pre_mean: float, pre_std_dev: float, post_mean: float, post_std_dev: float, threshold: float):
+        """
+        :param pre_mean: Mean before the change.
+        :param pre_std_dev: Standard deviation before the change.
+        :param post_mean: Mean after the change.
+        :param post_std_dev: Standard deviation after the change.
+        :param threshold: Alarm threshold for the statistic.
+        """
+
+    def update(self, point: float):
+        """
+        :param point: Observation used to update model.
+        :return:
+        """
+
+    def predict(self, _point: float) -> float:
+        """
+        :param _point: Not used for prediction.
+        :return: Shiryaev-Roberts statistic. Statistic restarts once it exceeds threshold.
+        """
+
+    def statistic(self) -> float:
+        """ Return current Shiryaev-Roberts statistic."""
+
+    def log_statistic(self) -> float:
+        """ Return natural log of current Shiryaev-Roberts statistic."""
+
+    def threshold(self) -> float:
+        """ Return alarm threshold."""
+
+
+class WindowedGlr:
+    """ A class implementing a windowed generalized likelihood ratio test for an unknown change in a normal mean.
+    """
+    def __init__(self, mean: float, std_dev: float, window_size: int, threshold: float):
+        """
+        :param mean: Mean before the change.
+        :param std_dev: Standard deviation, assumed unchanged.
+        :param window_size: Number of recent observations searched for the change time.
+        :param threshold: Alarm threshold for the statistic.
+        """
+
+    def update(self, point: float):
+        """
+        :param point: Observation used to update model.
+        :return:
+        """
+
+    def predict(self, _point: float) -> float:
+        """
+        :param _point: Not used for prediction.
+        :return: Generalized log likelihood ratio. Window restarts once it exceeds threshold.
+        """
+
+    def statistic(self) -> float:
+        """ Return current generalized log likelihood ratio."""
+
+    def change_mean(self) -> float:
+        """ Return maximum likelihood estimate of the post-change mean."""
+
+    def window_size(self) -> int:
+        """ Return number of observations searched for the change time."""
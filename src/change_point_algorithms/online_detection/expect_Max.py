@@ -229,14 +229,14 @@ def update_attack_prob(density: float, size: int) -> float:
 
 def em_rust_hybrid(data, safe_mean: float, safe_stddev: float, num_safe: int, unsafe_mean: float, unsafe_stddev: float, num_unsafe: int,
                    # , mean_1, mean_2, var_1, var_2,
-                   pi: float, epochs=1, prob_threshold=0.05, early_stopping=False):
+                   pi: float, epochs=1, prob_threshold=0.05, early_stopping=False, seed=None):
     """ Return decision of each observation in data as normal or abnormal using Expectation Maximization algorithm."""
     prob_threshold_normal = 1.0 - prob_threshold
     if early_stopping:
         early_stop_threshold = 1e-5
         model = _change_point_algorithms.build_em_early_stop_model(
             (safe_mean, safe_stddev, pi), [(unsafe_mean, unsafe_stddev, 1 - pi)],
-            [num_safe, num_unsafe], epochs=epochs,)
+            [num_safe, num_unsafe], epochs=epochs, seed=seed)
         update_model = model.update_check_convergence
         predict_model = model.predict
         for idx, event in enumerate(data):
@@ -249,8 +249,8 @@ def em_rust_hybrid(data, safe_mean: float, safe_stddev: float, num_safe: int, un
     else:
         model = _change_point_algorithms.build_em_model(
             (safe_mean, safe_stddev, pi), [(unsafe_mean, unsafe_stddev, 1 - pi)],
-            [num_safe, num_unsafe], epochs=epochs)
-        update_model = model.update
+            [num_safe, num_unsafe], epochs=epochs, seed=seed)
+        update_model = model.update_without_diagnostics
         predict_model = model.predict
         for idx, event in enumerate(data):
             update_model(event)
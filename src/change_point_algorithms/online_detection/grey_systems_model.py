@@ -4,6 +4,8 @@ import numpy as np
 
 from numba import njit
 
+from change_point_algorithms import _change_point_algorithms
+
 from change_point_algorithms.online_detection.model_helpers import (
     detection_to_intervals_for_generator_v1,
     detection_to_intervals_for_generator_v1_with_progress)
@@ -77,6 +79,42 @@ def grey_model_generator_2(data, window_size, w_factor, threshold):
         change_likely = (1 / (1 + threshold * sp)) < threshold or (1 / (1 + threshold * s)) < threshold
         yield change_likely
 
+def grey_model_rust_hybrid(data, window_size=1, c=3, c_ratio=300, threshold=0.5):
+    """ Generator for Grey Model using Rust class.
+
+        Yields once every window is full, so the first yield compares the reference window with itself.
+
+        :param np.ndarray data: Array of data. Data expected to be non-negative.
+        :param int window_size: Size of window to iterate over array.
+        :param float c: Constant multiplier for degree of grey incidence.
+        :param float c_ratio: Constant multiplier for degree of ratio grey incidence.
+        :param float threshold: Degree of grey incidence at or below which a change is likely."""
+    my_data = np.asarray(data)
+    model = _change_point_algorithms.GreyModel(window_size, c, c_ratio, threshold)
+    for event in my_data[:window_size - 1]:
+        model.update(event)
+    for event in my_data[window_size - 1:]:
+        model.update(event)
+        yield model.is_change()
+
+
+
+def grey_model_2_rust_hybrid(data, window_size=3, w_factor=0.5, threshold=0.15):
+    """ Generator for the second Grey Model using Rust class.
+
+        Yields from the second observation on, the first one only sets the offset of the relative index.
+
+        :param np.ndarray data: Array of data.
+        :param int window_size: Number of accumulation steps for each point.
+        :param float w_factor: Whitenization amount.
+        :param float threshold: Sensitivity of the grey indices, and the index below which a change is likely."""
+    my_data = np.asarray(data)
+    model = _change_point_algorithms.GreyModelV2(window_size, w_factor, threshold)
+    model.update(my_data[0])
+    for event in my_data[1:]:
+        model.update(event)
+        yield model.is_change()
+
 @njit
 def accumulation_sequence(window: np.ndarray):
     """ Return the accumulation over the window."""
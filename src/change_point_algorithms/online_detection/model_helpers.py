@@ -1,5 +1,7 @@
 import warnings
 
+from change_point_algorithms import _change_point_algorithms
+
 try:
     from tqdm import tqdm
 except ModuleNotFoundError:
@@ -45,6 +47,38 @@ def detection_to_intervals_for_generator_v1_with_progress(
         return detection_to_intervals_for_generator_v1(time_vec, begin, model_generator, start_offset)
 
 
+def detection_to_intervals_for_generator_rust(
+        time_vec, begin, model_generator, start_offset=0, min_duration=1, merge_gap=0):
+    """ Convert detections from generator to time intervals using the Rust interval tracker.
+
+        Same output as detection_to_intervals_for_generator_v1, except alarm runs
+        separated by at most merge_gap quiet samples are merged and intervals spanning
+        fewer than min_duration samples are ignored.
+    """
+    tracker = _change_point_algorithms.IntervalTracker(min_duration, merge_gap)
+    Closed = _change_point_algorithms.IntervalEvent.Closed
+    shocks = list()
+    nonshocks = list()
+    for is_change in model_generator:
+        event = tracker.update(bool(is_change))
+        if isinstance(event, Closed):
+            start, end = start_offset + event.start, start_offset + event.end
+            nonshocks.append((time_vec[begin], time_vec[start]))
+            shocks.append((time_vec[start], time_vec[end]))
+            begin = end
+    event = tracker.finish()
+    if isinstance(event, Closed):
+        start, end = start_offset + event.start, start_offset + event.end
+        nonshocks.append((time_vec[begin], time_vec[start]))
+        if end >= len(time_vec) - 1:
+            shocks.append((time_vec[start], time_vec[-1]))
+            return shocks, nonshocks
+        shocks.append((time_vec[start], time_vec[end]))
+        begin = end
+    nonshocks.append((time_vec[begin], time_vec[-1]))
+    return shocks, nonshocks
+
+
 # def detection_to_intervals_for_generator_v2(time_vec, begin, model_generator):
 #     """
 #
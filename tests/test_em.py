@@ -1,6 +1,9 @@
-
 import numpy as np
+import pytest
+
 
+from change_point_algorithms import (
+    ConvergenceCheck, EmAitkenCheck, EmBuilder, EmLikelihoodCheck, EmLogLikelihoodCheck, EmModel, EmParameterCheck)
 from change_point_algorithms.online_detection.expect_Max import em_rust_hybrid
 
 
@@ -66,6 +69,43 @@ class TestRustModels:
                     predictions]), f'Model predicted that {[item for item in predictions].count(False)} were change points.'
 
 
+
+class TestEmBuilder:
+
+    def make_builder(self):
+        rng = np.random.default_rng(7)
+        samples = np.concatenate((rng.normal(0.0, 1.0, 70), rng.normal(50.0, 2.0, 30)))
+        return (EmBuilder()
+                .build_normal(0.0, 1.0, 0.7)
+                .build_abnormal([(50.0, 2.0, 0.3)])
+                .build_samples(samples)
+                .build_epochs(10))
+
+    def test_em_builder_numpy_samples(self):
+        model = self.make_builder().get_model()
+        assert isinstance(model, EmModel)
+        # a placeholder sample is kept for the point of every update
+        assert len(model.samples()) == 101
+        assert model.predict(0.0) > 0.99
+        assert model.predict(50.0) < 0.01
+
+    def test_em_builder_convergence_checks(self):
+        expected = {
+            ConvergenceCheck.Likelihood: EmLikelihoodCheck,
+            ConvergenceCheck.LogLikelihood: EmLogLikelihoodCheck,
+            ConvergenceCheck.Parameter: EmParameterCheck,
+            ConvergenceCheck.Aitken: EmAitkenCheck,
+        }
+        for check, model_class in expected.items():
+            model = self.make_builder().build_convergence_check(check).get_model()
+            assert isinstance(model, model_class), f'{check} built {type(model)}'
+            diagnostics = model.update_check_convergence(0.1, 1e-6)
+            assert diagnostics.epochs <= 10
+
+    def test_em_builder_requires_samples(self):
+        with pytest.raises(ValueError):
+            EmBuilder().get_model()
+
 # def test_expectation_maximization_generator_all_normal():
 #     mean_1, var_1, mean_2, var_2 = get_parameters()
 #     safe_size, unsafe_size = 70, 30
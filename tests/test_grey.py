@@ -0,0 +1,48 @@
+import numpy as np
+
+from change_point_algorithms.online_detection.grey_systems_model import grey_model_2_rust_hybrid, grey_model_rust_hybrid
+
+
+def generate_normal_points(mean: float, stddev: float, num_points: int):
+    """
+    :param mean:
+    :param stddev:
+    :param num_points:
+    :return:
+    """
+    rng = np.random.default_rng()
+    return rng.normal(mean, stddev, num_points)
+
+
+class TestRustGrey:
+    safe_mean = 10.0
+    safe_std_dev = 0.1
+    unsafe_mean = 50.0
+    unsafe_std_dev = 0.1
+    window_size = 4
+    num_unknowns = 1_000
+
+    def test_grey_rust_hybrid_all_normal(self):
+        my_unknowns = np.abs(generate_normal_points(self.safe_mean, self.safe_std_dev, self.num_unknowns))
+        model_gen = grey_model_rust_hybrid(my_unknowns, self.window_size)
+        predictions = [item for item in model_gen]
+        assert len(predictions) == self.num_unknowns - self.window_size + 1
+        assert predictions.count(False) >= predictions.count(True), f'Model predicted that {predictions.count(True)} were change points.'
+
+    def test_grey_rust_hybrid_after_change(self):
+        my_unknowns = np.abs(np.concatenate((
+            generate_normal_points(self.safe_mean, self.safe_std_dev, self.window_size),
+            generate_normal_points(self.unsafe_mean, self.unsafe_std_dev, self.num_unknowns))))
+        model_gen = grey_model_rust_hybrid(my_unknowns, self.window_size)
+        predictions = [item for item in model_gen]
+        # Skip windows that still overlap the reference
+        assert all(predictions[self.window_size:]), f'Model predicted that {predictions[self.window_size:].count(False)} were not change points.'
+
+    def test_grey_2_rust_hybrid_level_shift(self):
+        my_unknowns = np.abs(np.concatenate((
+            generate_normal_points(1.0, self.safe_std_dev, self.num_unknowns),
+            generate_normal_points(self.unsafe_mean, self.unsafe_std_dev, self.num_unknowns))))
+        predictions = list(grey_model_2_rust_hybrid(my_unknowns))
+        assert len(predictions) == 2 * self.num_unknowns - 1
+        assert not any(predictions[:self.num_unknowns - 1]), f'Model predicted that {predictions[:self.num_unknowns - 1].count(True)} were change points.'
+        assert all(predictions[self.num_unknowns - 1:]), f'Model predicted that {predictions[self.num_unknowns - 1:].count(False)} were not change points.'